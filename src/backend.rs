@@ -0,0 +1,190 @@
+//! Storage behind `Store`. Issues are addressed by their path, a status
+//! directory joined with the issue's filename; backends decide what that path
+//! refers to.
+
+use crate::error::{Context, Error, Result};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long to wait for another moth process to release the board.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub trait Backend {
+    /// Paths of the issue files (`*.md`) in a status directory.
+    fn list(&self, dir: &Path) -> Result<Vec<PathBuf>>;
+
+    fn read(&self, path: &Path) -> Result<String>;
+
+    /// Create or replace an issue file.
+    fn write(&self, path: &Path, content: &str) -> Result<()>;
+
+    /// Move an issue file, e.g. to another status or filename.
+    fn rename(&self, from: &Path, to: &Path) -> Result<()>;
+
+    fn delete(&self, path: &Path) -> Result<()>;
+
+    /// When an issue file last changed, if the backend knows.
+    fn modified(&self, _path: &Path) -> Option<SystemTime> {
+        None
+    }
+
+    /// Take an exclusive lock on the board, using the file at `path`, held
+    /// until the returned file is dropped. `None` for backends no other
+    /// process can see.
+    fn lock(&self, _path: &Path) -> Result<Option<File>> {
+        Ok(None)
+    }
+
+    /// Bring the issue files in line with changes stored elsewhere, such as
+    /// a ref other clones push to. Called once the board lock is taken.
+    fn refresh(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Record a command's changes under `message`, for backends that keep
+    /// their own history. Returns `false` when history is left to the work
+    /// tree's git commits (`auto_commit`).
+    fn record(&self, _message: &str) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+fn is_issue_file(path: &Path) -> bool {
+    path.extension().and_then(|s| s.to_str()) == Some("md")
+}
+
+/// The directory-per-status layout under `.moth/`.
+pub struct FsBackend;
+
+impl Backend for FsBackend {
+    fn list(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let entries = fs::read_dir(dir)
+            .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+
+        let mut paths = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if is_issue_file(&path) {
+                paths.push(path);
+            }
+        }
+        Ok(paths)
+    }
+
+    fn read(&self, path: &Path) -> Result<String> {
+        fs::read_to_string(path)
+            .with_context(|| format!("Failed to read issue file: {}", path.display()))
+    }
+
+    fn write(&self, path: &Path, content: &str) -> Result<()> {
+        fs::write(path, content)
+            .with_context(|| format!("Failed to write issue file: {}", path.display()))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        fs::rename(from, to)
+            .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))
+    }
+
+    fn delete(&self, path: &Path) -> Result<()> {
+        fs::remove_file(path).with_context(|| format!("Failed to delete {}", path.display()))
+    }
+
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    fn lock(&self, path: &Path) -> Result<Option<File>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("Failed to open lock file: {}", path.display()))?;
+
+        let started = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Some(file)),
+                Err(TryLockError::WouldBlock) if started.elapsed() < LOCK_TIMEOUT => {
+                    thread::sleep(Duration::from_millis(50));
+                }
+                Err(TryLockError::WouldBlock) => return Err(Error::Locked(path.to_path_buf())),
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).with_context(|| format!("Failed to lock {}", path.display()));
+                }
+            }
+        }
+    }
+}
+
+/// Issues held in memory, for tests and for embedding moth without a
+/// `.moth/` directory.
+#[derive(Default)]
+pub struct MemoryBackend {
+    files: RefCell<BTreeMap<PathBuf, String>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// The error `FsBackend` gives for a missing file.
+fn not_found(path: &Path) -> Error {
+    Error::Io {
+        context: Some(format!("No such issue file: {}", path.display())),
+        source: std::io::ErrorKind::NotFound.into(),
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn list(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        Ok(self
+            .files
+            .borrow()
+            .keys()
+            .filter(|path| path.parent() == Some(dir) && is_issue_file(path))
+            .cloned()
+            .collect())
+    }
+
+    fn read(&self, path: &Path) -> Result<String> {
+        self.files
+            .borrow()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn write(&self, path: &Path, content: &str) -> Result<()> {
+        self.files
+            .borrow_mut()
+            .insert(path.to_path_buf(), content.to_string());
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let mut files = self.files.borrow_mut();
+        let content = files.remove(from).ok_or_else(|| not_found(from))?;
+        files.insert(to.to_path_buf(), content);
+        Ok(())
+    }
+
+    fn delete(&self, path: &Path) -> Result<()> {
+        self.files
+            .borrow_mut()
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| not_found(path))
+    }
+}
@@ -0,0 +1,349 @@
+//! Board storage on a git ref. With `storage.backend: git_ref` issues live in
+//! commits on an orphan ref (`refs/moth/board` by default) shared by every
+//! branch, so board changes never show up in feature branch diffs. The status
+//! directories under `.moth/` become an untracked working copy of the ref:
+//! issue files are read and written there as with `FsBackend`, each command's
+//! changes are recorded on the ref, and changes arriving on the ref, from
+//! `moth sync` or another worktree, are written back to the copy when the
+//! next command takes the board lock.
+
+use crate::backend::{Backend, FsBackend};
+use crate::config::Config;
+use crate::error::{Context, Error, Result};
+use git2::{Commit, FileMode, Oid, PushOptions, RemoteCallbacks, Repository, Tree};
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Bits of an index entry's flags holding its merge stage.
+const STAGE_MASK: u16 = 0x3000;
+
+/// The `git_ref` backend.
+pub struct BoardRef {
+    repo: Repository,
+    config: Config,
+}
+
+impl BoardRef {
+    pub fn open(config: &Config) -> Result<Self> {
+        let repo = Repository::discover(&config.moth_dir).map_err(|_| {
+            Error::Repository(
+                "storage.backend git_ref needs the board to be in a git repository".to_string(),
+            )
+        })?;
+        Ok(BoardRef {
+            repo,
+            config: config.clone(),
+        })
+    }
+
+    fn ref_name(&self) -> &str {
+        &self.config.storage.git_ref
+    }
+
+    fn tip(&self) -> Option<Commit<'_>> {
+        let reference = self.repo.find_reference(self.ref_name()).ok()?;
+        reference.peel_to_commit().ok()
+    }
+
+    /// The ref commit this worktree's working copy was last in line with.
+    fn synced_file(&self) -> PathBuf {
+        self.repo.path().join("moth-board-synced")
+    }
+
+    fn synced(&self) -> Option<Commit<'_>> {
+        let content = fs::read_to_string(self.synced_file()).ok()?;
+        let oid = Oid::from_str(content.trim()).ok()?;
+        self.repo.find_commit(oid).ok()
+    }
+
+    fn set_synced(&self, oid: Oid) -> Result<()> {
+        fs::write(self.synced_file(), oid.to_string())
+            .with_context(|| format!("Failed to write {}", self.synced_file().display()))
+    }
+
+    fn empty_tree(&self) -> Result<Tree<'_>> {
+        Ok(self.repo.find_tree(self.repo.treebuilder(None)?.write()?)?)
+    }
+
+    /// Tree of the issue files in the working copy, one subtree per status.
+    fn working_tree(&self) -> Result<Oid> {
+        let mut root = self.repo.treebuilder(None)?;
+
+        for status in &self.config.statuses {
+            let dir = self.config.status_dir(status);
+            if !dir.is_dir() {
+                continue;
+            }
+
+            let mut builder = self.repo.treebuilder(None)?;
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                if path.is_file()
+                    && path.extension().and_then(|s| s.to_str()) == Some("md")
+                    && let Some(name) = path.file_name()
+                {
+                    let blob = self.repo.blob_path(&path)?;
+                    builder.insert(name, blob, FileMode::Blob.into())?;
+                }
+            }
+
+            if !builder.is_empty() {
+                root.insert(&status.dir, builder.write()?, FileMode::Tree.into())?;
+            }
+        }
+
+        Ok(root.write()?)
+    }
+
+    /// Replace the issue files in the working copy with those in `tree`.
+    fn checkout(&self, tree: &Tree) -> Result<()> {
+        for status in &self.config.statuses {
+            let dir = self.config.status_dir(status);
+            fs::create_dir_all(&dir)?;
+
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("md") {
+                    fs::remove_file(&path)?;
+                }
+            }
+
+            let Some(entry) = tree.get_name(&status.dir) else {
+                continue;
+            };
+            for file in self.repo.find_tree(entry.id())?.iter() {
+                let Some(name) = file.name() else {
+                    continue;
+                };
+                let blob = self.repo.find_blob(file.id())?;
+                fs::write(dir.join(name), blob.content())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn commit(&self, tree: Oid, parents: &[&Commit], message: &str) -> Result<Oid> {
+        let signature = self
+            .repo
+            .signature()
+            .context("Failed to get git signature")?;
+        let tree = self.repo.find_tree(tree)?;
+        let oid = self
+            .repo
+            .commit(None, &signature, &signature, message, &tree, parents)?;
+        self.repo.reference(self.ref_name(), oid, true, message)?;
+        Ok(oid)
+    }
+
+    /// Bring the working copy and the ref in line. Local changes are
+    /// committed to the ref with `message`; changes that arrived on the ref
+    /// since the last update are written to the working copy, merged with
+    /// any local changes.
+    fn update(&self, message: &str) -> Result<()> {
+        let local = self.working_tree()?;
+        let synced = self.synced();
+
+        let Some(tip) = self.tip() else {
+            if local != self.empty_tree()?.id() {
+                let oid = self.commit(local, &[], message)?;
+                self.set_synced(oid)?;
+            }
+            return Ok(());
+        };
+
+        if synced.as_ref().is_some_and(|c| c.id() == tip.id()) {
+            if local != tip.tree_id() {
+                let oid = self.commit(local, &[&tip], message)?;
+                self.set_synced(oid)?;
+            }
+            return Ok(());
+        }
+
+        // The ref moved on without us
+        let ancestor = match &synced {
+            Some(commit) => commit.tree()?,
+            None => self.empty_tree()?,
+        };
+        if local == tip.tree_id() {
+            self.set_synced(tip.id())?;
+        } else if local == ancestor.id() {
+            self.checkout(&tip.tree()?)?;
+            self.set_synced(tip.id())?;
+        } else {
+            let local_tree = self.repo.find_tree(local)?;
+            let merged = self.merge(&ancestor, &local_tree, &tip.tree()?)?;
+            let oid = self.commit(merged, &[&tip], message)?;
+            self.checkout(&self.repo.find_tree(merged)?)?;
+            self.set_synced(oid)?;
+        }
+
+        Ok(())
+    }
+
+    /// Merge two versions of the board. Conflicts keep both sides: an issue
+    /// moved to different statuses ends up in both, for `moth resolve` to
+    /// pick one, and an issue file both sides edited gets conflict markers.
+    fn merge(&self, ancestor: &Tree, ours: &Tree, theirs: &Tree) -> Result<Oid> {
+        let mut index = self.repo.merge_trees(ancestor, ours, theirs, None)?;
+        if !index.has_conflicts() {
+            return Ok(index.write_tree_to(&self.repo)?);
+        }
+
+        let mut kept = Vec::new();
+        let mut conflicted = Vec::new();
+        for conflict in index.conflicts()? {
+            let conflict = conflict?;
+            conflicted.extend(conflict.ancestor.iter().map(|e| (e.path.clone(), 1)));
+            conflicted.extend(conflict.our.iter().map(|e| (e.path.clone(), 2)));
+            conflicted.extend(conflict.their.iter().map(|e| (e.path.clone(), 3)));
+
+            match (conflict.our, conflict.their) {
+                (Some(mut our), Some(their)) if our.path == their.path => {
+                    let content = format!(
+                        "<<<<<<< ours\n{}=======\n{}>>>>>>> {}\n",
+                        self.blob_text(our.id)?,
+                        self.blob_text(their.id)?,
+                        self.ref_name()
+                    );
+                    our.id = self.repo.blob(content.as_bytes())?;
+                    kept.push(our);
+                }
+                (our, their) => kept.extend(our.into_iter().chain(their)),
+            }
+        }
+
+        for (path, stage) in conflicted {
+            index.remove(Path::new(&*String::from_utf8_lossy(&path)), stage)?;
+        }
+        for mut entry in kept {
+            entry.flags &= !STAGE_MASK;
+            index.add(&entry)?;
+        }
+        Ok(index.write_tree_to(&self.repo)?)
+    }
+
+    /// A blob's content, ending in a newline.
+    fn blob_text(&self, oid: Oid) -> Result<String> {
+        let blob = self.repo.find_blob(oid)?;
+        let mut text = String::from_utf8_lossy(blob.content()).into_owned();
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        Ok(text)
+    }
+
+    /// Fetch the board ref from `remote`, merge it with ours and push the
+    /// result back. Returns the new tip, or `None` if neither side has a board.
+    pub fn sync(&self, remote_name: &str) -> Result<Option<Oid>> {
+        self.update("moth: update board")?;
+
+        let mut remote = self
+            .repo
+            .find_remote(remote_name)
+            .map_err(|_| Error::Repository(format!("Unknown remote: {}", remote_name)))?;
+        let tracking = format!("refs/moth/remotes/{}/board", remote_name);
+        remote
+            .fetch(&[format!("+{}:{}", self.ref_name(), tracking)], None, None)
+            .with_context(|| format!("Failed to fetch from {}", remote_name))?;
+
+        let theirs = self
+            .repo
+            .find_reference(&tracking)
+            .ok()
+            .and_then(|r| r.peel_to_commit().ok());
+
+        let new_tip = match (self.tip(), theirs) {
+            (None, None) => return Ok(None),
+            (Some(ours), None) => ours.id(),
+            (None, Some(theirs)) => theirs.id(),
+            (Some(ours), Some(theirs)) => {
+                if ours.id() == theirs.id()
+                    || self.repo.graph_descendant_of(ours.id(), theirs.id())?
+                {
+                    ours.id()
+                } else if self.repo.graph_descendant_of(theirs.id(), ours.id())? {
+                    theirs.id()
+                } else {
+                    let ancestor = match self.repo.merge_base(ours.id(), theirs.id()) {
+                        Ok(base) => self.repo.find_commit(base)?.tree()?,
+                        Err(_) => self.empty_tree()?,
+                    };
+                    let merged = self.merge(&ancestor, &ours.tree()?, &theirs.tree()?)?;
+                    let message = format!("moth: merge {}", remote_name);
+                    self.commit(merged, &[&ours, &theirs], &message)?
+                }
+            }
+        };
+        self.repo
+            .reference(self.ref_name(), new_tip, true, "moth: sync")?;
+
+        // Libgit2 reports rejected updates through a callback
+        let rejected = RefCell::new(None);
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.push_update_reference(|_, status| {
+            if let Some(message) = status {
+                *rejected.borrow_mut() = Some(message.to_string());
+            }
+            Ok(())
+        });
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
+        let refspec = format!("{}:{}", self.ref_name(), self.ref_name());
+        remote
+            .push(&[refspec], Some(&mut options))
+            .with_context(|| format!("Failed to push to {}", remote_name))?;
+        drop(options);
+        if let Some(message) = rejected.into_inner() {
+            return Err(Error::Repository(format!(
+                "{} rejected the board: {}",
+                remote_name, message
+            )));
+        }
+
+        self.update("moth: update board")?;
+
+        Ok(Some(new_tip))
+    }
+}
+
+impl Backend for BoardRef {
+    fn list(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        FsBackend.list(dir)
+    }
+
+    fn read(&self, path: &Path) -> Result<String> {
+        FsBackend.read(path)
+    }
+
+    fn write(&self, path: &Path, content: &str) -> Result<()> {
+        FsBackend.write(path, content)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        FsBackend.rename(from, to)
+    }
+
+    fn delete(&self, path: &Path) -> Result<()> {
+        FsBackend.delete(path)
+    }
+
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        FsBackend.modified(path)
+    }
+
+    fn lock(&self, path: &Path) -> Result<Option<File>> {
+        FsBackend.lock(path)
+    }
+
+    fn refresh(&self) -> Result<()> {
+        self.update("moth: update board")
+    }
+
+    fn record(&self, message: &str) -> Result<bool> {
+        self.update(message)?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,78 @@
+use crate::cmd::start;
+use crate::config::Config;
+use crate::issue::record_branch;
+use crate::store::Store;
+use anyhow::{Context, Result, anyhow};
+use git2::build::CheckoutBuilder;
+use git2::{Branch, BranchType, Repository};
+
+/// Create (or switch to) the issue's branch, start the issue and record the
+/// branch in the issue's content.
+pub fn run(id: &str) -> Result<()> {
+    let config = Config::load()?;
+    let store = Store::new(config)?;
+
+    let issue = store.find(id)?;
+    let name = issue.branch_name(&store.config().branches.pattern);
+    if !Branch::name_is_valid(&name)? {
+        return Err(anyhow!("Invalid branch name: {}", name));
+    }
+
+    let repo = Repository::discover(".").context("Failed to open git repository")?;
+    match repo.find_branch(&name, BranchType::Local) {
+        Ok(branch) => {
+            let commit = branch.get().peel_to_commit()?;
+            repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().safe()))
+                .with_context(|| format!("Failed to check out {}", name))?;
+        }
+        Err(_) => {
+            let head = repo
+                .head()
+                .and_then(|head| head.peel_to_commit())
+                .map_err(|_| anyhow!("Cannot create a branch before the first commit"))?;
+            repo.branch(&name, &head, false)?;
+        }
+    }
+    repo.set_head(&format!("refs/heads/{}", name))?;
+    println!("Switched to branch {}", name);
+
+    start::run(&issue.id)?;
+
+    let _lock = store.lock()?;
+    let issue = store.find(&issue.id)?;
+    let content = store.read(&issue)?;
+    store.write(&issue, &record_branch(&content, &name))?;
+    store.auto_commit(
+        &[&issue.path],
+        &format!("moth: record branch {} for {}", name, issue.id),
+    )?;
+
+    Ok(())
+}
+
+/// Delete a local branch once HEAD contains it. Unmerged and checked-out
+/// branches are kept with a warning.
+pub fn delete_merged(name: &str) -> Result<()> {
+    let repo = Repository::discover(".").context("Failed to open git repository")?;
+    let Ok(mut branch) = repo.find_branch(name, BranchType::Local) else {
+        eprintln!("Warning: Branch {} not found", name);
+        return Ok(());
+    };
+
+    if branch.is_head() {
+        eprintln!("Warning: Not deleting {}, it is checked out", name);
+        return Ok(());
+    }
+
+    let tip = branch.get().peel_to_commit()?.id();
+    let head = repo.head()?.peel_to_commit()?.id();
+    if tip != head && !repo.graph_descendant_of(head, tip)? {
+        eprintln!("Warning: Not deleting {}, it is not merged", name);
+        return Ok(());
+    }
+
+    branch.delete()?;
+    println!("Deleted branch {}", name);
+
+    Ok(())
+}
@@ -0,0 +1,512 @@
+use crate::config::Config;
+use crate::history::{BoardWalker, get_commits, history_board_path};
+use anyhow::{Context, Result, anyhow};
+use chrono::{Days, NaiveDate};
+use git2::Repository;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+const ASCII_SYMBOLS: &[char] = &['#', '=', '+', '-', '.', ':', '*', 'o'];
+const SVG_COLORS: &[&str] = &[
+    "#59a14f", "#f28e2b", "#4e79a7", "#e15759", "#76b7b2", "#edc948", "#b07aa1", "#ff9da7",
+];
+const SVG_WIDTH: f64 = 800.0;
+const SVG_HEIGHT: f64 = 400.0;
+const SVG_MARGIN: f64 = 50.0;
+
+/// Issue counts per status at the end of each day, oldest day first.
+struct DailyCounts {
+    columns: Vec<String>,
+    days: Vec<(NaiveDate, Vec<usize>)>,
+}
+
+impl DailyCounts {
+    fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c == name)
+    }
+}
+
+pub fn cfd(
+    since: Option<&str>,
+    until: Option<&str>,
+    svg: Option<&str>,
+    width: usize,
+) -> Result<()> {
+    let repo = Repository::discover(".").context("Failed to open git repository")?;
+    let data = daily_counts(&repo, &history_board_path(&repo)?, since, until)?;
+
+    match svg {
+        Some(path) => {
+            fs::write(path, render_cfd_svg(&data))
+                .with_context(|| format!("Failed to write chart: {}", path))?;
+            println!("Wrote cumulative flow diagram to {}", path);
+        }
+        None => print!("{}", render_cfd_ascii(&data, width)),
+    }
+
+    Ok(())
+}
+
+pub fn burnup(
+    since: Option<&str>,
+    until: Option<&str>,
+    svg: Option<&str>,
+    width: usize,
+) -> Result<()> {
+    let repo = Repository::discover(".").context("Failed to open git repository")?;
+    let data = daily_counts(&repo, &history_board_path(&repo)?, since, until)?;
+    let series = burnup_series(&data, &done_column(&data));
+
+    match svg {
+        Some(path) => {
+            fs::write(path, render_burnup_svg(&series))
+                .with_context(|| format!("Failed to write chart: {}", path))?;
+            println!("Wrote burn-up chart to {}", path);
+        }
+        None => print!("{}", render_burnup_ascii(&series, width)),
+    }
+
+    Ok(())
+}
+
+/// Counts from the current branch's first-parent history, so a merged
+/// branch's changes show up on the day it was merged.
+fn daily_counts(
+    repo: &Repository,
+    board: &Path,
+    since: Option<&str>,
+    until: Option<&str>,
+) -> Result<DailyCounts> {
+    let commits = get_commits(repo, since, until, true)?;
+
+    let mut by_day: BTreeMap<NaiveDate, HashMap<String, usize>> = BTreeMap::new();
+    let mut found = BTreeSet::new();
+    let mut walker = BoardWalker::new(repo, board);
+
+    for commit in &commits {
+        walker.advance(commit)?;
+        let stories = walker.state();
+        if stories.is_empty() && by_day.is_empty() {
+            continue; // Board not created yet
+        }
+
+        let mut counts = HashMap::new();
+        for story in stories.values() {
+            *counts.entry(story.column.clone()).or_insert(0) += 1;
+            found.insert(story.column.clone());
+        }
+
+        // Later commits on the same day overwrite earlier ones
+        let date = chrono::DateTime::from_timestamp(commit.committer().when().seconds(), 0)
+            .unwrap_or_default()
+            .date_naive();
+        by_day.insert(date, counts);
+    }
+
+    walker.save_cache();
+
+    if by_day.is_empty() {
+        return Err(anyhow!("No board history found in the selected commits"));
+    }
+
+    let columns = column_order(&found);
+    let days = fill_days(&by_day, &columns);
+
+    Ok(DailyCounts { columns, days })
+}
+
+/// Order columns as configured (first status first), with any columns
+/// that only exist in history appended alphabetically.
+fn column_order(found: &BTreeSet<String>) -> Vec<String> {
+    let mut columns: Vec<String> = match Config::load() {
+        Ok(config) => config.statuses.iter().map(|s| s.name.clone()).collect(),
+        Err(_) => Vec::new(),
+    };
+
+    for column in found {
+        if !columns.contains(column) {
+            columns.push(column.clone());
+        }
+    }
+
+    columns
+}
+
+/// Expand sparse per-day counts into one entry per calendar day, carrying
+/// the last known counts over days without commits.
+fn fill_days(
+    by_day: &BTreeMap<NaiveDate, HashMap<String, usize>>,
+    columns: &[String],
+) -> Vec<(NaiveDate, Vec<usize>)> {
+    let (Some(first), Some(last)) = (by_day.keys().next(), by_day.keys().next_back()) else {
+        return Vec::new();
+    };
+
+    let mut days = Vec::new();
+    let mut current = vec![0; columns.len()];
+    let mut date = *first;
+
+    while date <= *last {
+        if let Some(counts) = by_day.get(&date) {
+            current = columns
+                .iter()
+                .map(|c| counts.get(c).copied().unwrap_or(0))
+                .collect();
+        }
+        days.push((date, current.clone()));
+        date = match date.checked_add_days(Days::new(1)) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+
+    days
+}
+
+fn done_column(data: &DailyCounts) -> String {
+    match Config::load() {
+        Ok(config) => config.last_status().name.clone(),
+        Err(_) => data.columns.last().cloned().unwrap_or_default(),
+    }
+}
+
+/// (date, done, scope) for each day.
+fn burnup_series(data: &DailyCounts, done_column: &str) -> Vec<(NaiveDate, usize, usize)> {
+    let done_idx = data.column_index(done_column);
+
+    data.days
+        .iter()
+        .map(|(date, counts)| {
+            let done = done_idx.map(|i| counts[i]).unwrap_or(0);
+            (*date, done, counts.iter().sum())
+        })
+        .collect()
+}
+
+/// Scale a value to a bar position, keeping stacked segments aligned.
+fn scale(value: usize, max: usize, width: usize) -> usize {
+    if max == 0 {
+        return 0;
+    }
+    (value * width + max / 2) / max
+}
+
+fn render_cfd_ascii(data: &DailyCounts, width: usize) -> String {
+    let max = data
+        .days
+        .iter()
+        .map(|(_, counts)| counts.iter().sum::<usize>())
+        .max()
+        .unwrap_or(0);
+
+    // Stack from the last status (bottom of a CFD) to the first
+    let stack: Vec<usize> = (0..data.columns.len()).rev().collect();
+
+    let legend: Vec<String> = stack
+        .iter()
+        .enumerate()
+        .map(|(i, &col)| {
+            format!(
+                "{} {}",
+                ASCII_SYMBOLS[i % ASCII_SYMBOLS.len()],
+                data.columns[col]
+            )
+        })
+        .collect();
+
+    let mut out = format!("Cumulative flow ({})\n", legend.join("  "));
+
+    for (date, counts) in &data.days {
+        let mut bar = String::new();
+        let mut cumulative = 0;
+        for (i, &col) in stack.iter().enumerate() {
+            let start = scale(cumulative, max, width);
+            cumulative += counts[col];
+            let end = scale(cumulative, max, width);
+            let symbol = ASCII_SYMBOLS[i % ASCII_SYMBOLS.len()];
+            bar.extend(std::iter::repeat_n(symbol, end - start));
+        }
+        out.push_str(&format!(
+            "{} |{:<width$}| {}\n",
+            date,
+            bar,
+            cumulative,
+            width = width
+        ));
+    }
+
+    out
+}
+
+fn render_burnup_ascii(series: &[(NaiveDate, usize, usize)], width: usize) -> String {
+    let max = series.iter().map(|(_, _, scope)| *scope).max().unwrap_or(0);
+
+    let mut out = String::from("Burn-up (# done  . remaining scope)\n");
+
+    for (date, done, scope) in series {
+        let done_len = scale(*done, max, width);
+        let scope_len = scale(*scope, max, width);
+        let bar = format!(
+            "{}{}",
+            "#".repeat(done_len),
+            ".".repeat(scope_len.saturating_sub(done_len))
+        );
+        out.push_str(&format!(
+            "{} |{:<width$}| {}/{}\n",
+            date,
+            bar,
+            done,
+            scope,
+            width = width
+        ));
+    }
+
+    out
+}
+
+fn svg_x(index: usize, count: usize) -> f64 {
+    let span = SVG_WIDTH - 2.0 * SVG_MARGIN;
+    if count <= 1 {
+        return SVG_MARGIN;
+    }
+    SVG_MARGIN + index as f64 * span / (count - 1) as f64
+}
+
+fn svg_y(value: usize, max: usize) -> f64 {
+    let span = SVG_HEIGHT - 2.0 * SVG_MARGIN;
+    if max == 0 {
+        return SVG_HEIGHT - SVG_MARGIN;
+    }
+    SVG_HEIGHT - SVG_MARGIN - value as f64 * span / max as f64
+}
+
+fn svg_frame(title: &str, first: NaiveDate, last: NaiveDate, max: usize) -> String {
+    let bottom = SVG_HEIGHT - SVG_MARGIN;
+    let right = SVG_WIDTH - SVG_MARGIN;
+    format!(
+        concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" ",
+            "viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\" font-size=\"12\">\n",
+            "<rect width=\"{w}\" height=\"{h}\" fill=\"white\"/>\n",
+            "<text x=\"{m}\" y=\"20\" font-size=\"16\">{title}</text>\n",
+            "<line x1=\"{m}\" y1=\"{b}\" x2=\"{r}\" y2=\"{b}\" stroke=\"black\"/>\n",
+            "<line x1=\"{m}\" y1=\"{m}\" x2=\"{m}\" y2=\"{b}\" stroke=\"black\"/>\n",
+            "<text x=\"{m}\" y=\"{date_y}\">{first}</text>\n",
+            "<text x=\"{r}\" y=\"{date_y}\" text-anchor=\"end\">{last}</text>\n",
+            "<text x=\"{label_x}\" y=\"{m}\" text-anchor=\"end\">{max}</text>\n",
+            "<text x=\"{label_x}\" y=\"{b}\" text-anchor=\"end\">0</text>\n",
+        ),
+        w = SVG_WIDTH,
+        h = SVG_HEIGHT,
+        m = SVG_MARGIN,
+        b = bottom,
+        r = right,
+        title = title,
+        date_y = bottom + 20.0,
+        label_x = SVG_MARGIN - 5.0,
+        first = first,
+        last = last,
+        max = max,
+    )
+}
+
+fn svg_legend(items: &[(&str, &str)]) -> String {
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, (name, color))| {
+            let x = SVG_WIDTH - SVG_MARGIN - 120.0;
+            let y = SVG_MARGIN + 10.0 + i as f64 * 18.0;
+            format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"12\" height=\"12\" fill=\"{}\"/>\n<text x=\"{}\" y=\"{}\">{}</text>\n",
+                x,
+                y - 10.0,
+                color,
+                x + 18.0,
+                y,
+                name
+            )
+        })
+        .collect()
+}
+
+fn render_cfd_svg(data: &DailyCounts) -> String {
+    let count = data.days.len();
+    let max = data
+        .days
+        .iter()
+        .map(|(_, counts)| counts.iter().sum::<usize>())
+        .max()
+        .unwrap_or(0);
+
+    let (first, last) = (data.days[0].0, data.days[count - 1].0);
+    let mut svg = svg_frame("Cumulative flow", first, last, max);
+
+    // Running total below the current band, per day
+    let mut lower = vec![0; count];
+    let mut legend = Vec::new();
+
+    for (i, col) in (0..data.columns.len()).rev().enumerate() {
+        let color = SVG_COLORS[i % SVG_COLORS.len()];
+        let upper: Vec<usize> = data
+            .days
+            .iter()
+            .zip(&lower)
+            .map(|((_, counts), below)| below + counts[col])
+            .collect();
+
+        let mut points: Vec<String> = upper
+            .iter()
+            .enumerate()
+            .map(|(d, v)| format!("{:.1},{:.1}", svg_x(d, count), svg_y(*v, max)))
+            .collect();
+        points.extend(
+            lower
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(d, v)| format!("{:.1},{:.1}", svg_x(d, count), svg_y(*v, max))),
+        );
+
+        svg.push_str(&format!(
+            "<polygon points=\"{}\" fill=\"{}\" stroke=\"{}\"><title>{}</title></polygon>\n",
+            points.join(" "),
+            color,
+            color,
+            data.columns[col]
+        ));
+        legend.push((data.columns[col].as_str(), color));
+        lower = upper;
+    }
+
+    legend.reverse();
+    svg.push_str(&svg_legend(&legend));
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn render_burnup_svg(series: &[(NaiveDate, usize, usize)]) -> String {
+    let count = series.len();
+    let max = series.iter().map(|(_, _, scope)| *scope).max().unwrap_or(0);
+
+    let (first, last) = (series[0].0, series[count - 1].0);
+    let mut svg = svg_frame("Burn-up", first, last, max);
+
+    let line = |value: fn(&(NaiveDate, usize, usize)) -> usize, color: &str, name: &str| {
+        let points: Vec<String> = series
+            .iter()
+            .enumerate()
+            .map(|(d, point)| format!("{:.1},{:.1}", svg_x(d, count), svg_y(value(point), max)))
+            .collect();
+        format!(
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"><title>{}</title></polyline>\n",
+            points.join(" "),
+            color,
+            name
+        )
+    };
+
+    svg.push_str(&line(|p| p.2, "#4e79a7", "scope"));
+    svg.push_str(&line(|p| p.1, "#59a14f", "done"));
+    svg.push_str(&svg_legend(&[("scope", "#4e79a7"), ("done", "#59a14f")]));
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::testing::commit;
+    use tempfile::TempDir;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn sample() -> DailyCounts {
+        DailyCounts {
+            columns: vec!["ready".to_string(), "doing".to_string(), "done".to_string()],
+            days: vec![
+                (date("2025-01-01"), vec![4, 0, 0]),
+                (date("2025-01-02"), vec![2, 1, 1]),
+                (date("2025-01-03"), vec![1, 1, 2]),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_fill_days_carries_counts_forward() {
+        let columns = vec!["ready".to_string(), "done".to_string()];
+        let mut by_day = BTreeMap::new();
+        by_day.insert(
+            date("2025-01-01"),
+            HashMap::from([("ready".to_string(), 2)]),
+        );
+        by_day.insert(
+            date("2025-01-04"),
+            HashMap::from([("ready".to_string(), 1), ("done".to_string(), 1)]),
+        );
+
+        let days = fill_days(&by_day, &columns);
+        assert_eq!(days.len(), 4);
+        assert_eq!(days[1], (date("2025-01-02"), vec![2, 0]));
+        assert_eq!(days[2], (date("2025-01-03"), vec![2, 0]));
+        assert_eq!(days[3], (date("2025-01-04"), vec![1, 1]));
+    }
+
+    #[test]
+    fn test_daily_counts_follow_the_mainline() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let ready = ".moth/ready/abc12-med-first.md";
+        let done = ".moth/done/abc12-med-first.md";
+        let other = ".moth/ready/def34-low-other.md";
+
+        // The issue is finished on a branch on day 1 but only merged on day 3
+        let base = commit(&repo, &[ready], &[], 0, true);
+        let branch = commit(&repo, &[done], &[base], 1, false);
+        let main = commit(&repo, &[ready, other], &[base], 2, true);
+        commit(&repo, &[done, other], &[main, branch], 3, true);
+
+        let data = daily_counts(&repo, Path::new(".moth"), None, None).unwrap();
+        let done = data.column_index("done").unwrap();
+        let counts: Vec<usize> = data.days.iter().map(|(_, counts)| counts[done]).collect();
+        assert_eq!(counts, vec![0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_burnup_series() {
+        let series = burnup_series(&sample(), "done");
+        assert_eq!(series[0], (date("2025-01-01"), 0, 4));
+        assert_eq!(series[2], (date("2025-01-03"), 2, 4));
+    }
+
+    #[test]
+    fn test_render_cfd_ascii_stacks_last_status_first() {
+        let out = render_cfd_ascii(&sample(), 8);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "Cumulative flow (# done  = doing  + ready)");
+        assert_eq!(lines[1], "2025-01-01 |++++++++| 4");
+        assert_eq!(lines[3], "2025-01-03 |####==++| 4");
+    }
+
+    #[test]
+    fn test_render_burnup_ascii() {
+        let series = burnup_series(&sample(), "done");
+        let out = render_burnup_ascii(&series, 4);
+        assert!(out.contains("2025-01-02 |#...| 1/4"));
+        assert!(out.contains("2025-01-03 |##..| 2/4"));
+    }
+
+    #[test]
+    fn test_render_svg_contains_series() {
+        let cfd = render_cfd_svg(&sample());
+        assert!(cfd.starts_with("<svg"));
+        assert_eq!(cfd.matches("<polygon").count(), 3);
+        assert!(cfd.contains("<title>doing</title>"));
+
+        let burnup = render_burnup_svg(&burnup_series(&sample(), "done"));
+        assert_eq!(burnup.matches("<polyline").count(), 2);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::config::Config;
+use crate::history::board_path;
+use crate::issue::add_reference;
+use crate::store::{INTERNAL_FILES, Store};
+use anyhow::{Context, Result, anyhow};
+use git2::{IndexAddOption, IndexMatchedPath, Oid, Repository};
+use std::path::{Path, PathBuf};
+
+/// Options for `moth commit`.
+#[derive(Debug, Default)]
+pub struct CommitOptions<'a> {
+    /// Commit message; the issue's title when not given.
+    pub message: Option<&'a str>,
+    /// Issue to reference instead of the current one.
+    pub issue: Option<&'a str>,
+    /// Move the issue to the last status in the same commit.
+    pub done: bool,
+    /// Also stage changes to tracked files, as `git commit -a` does.
+    pub all: bool,
+}
+
+/// Commit the staged changes and the board with a message referencing the
+/// current issue. Git hooks are not run, as the message is already tagged.
+pub fn run(options: &CommitOptions) -> Result<()> {
+    let config = Config::load()?;
+    let store = Store::new(config)?;
+
+    let issue = match options.issue {
+        Some(id) => store.find(id)?,
+        None => store
+            .current()
+            .context("Failed to get current issue")?
+            .ok_or_else(|| anyhow!("No current issue"))?,
+    };
+
+    let subject = options
+        .message
+        .map(str::to_string)
+        .unwrap_or_else(|| issue.title());
+    if subject.trim().is_empty() {
+        return Err(anyhow!("Commit message cannot be empty"));
+    }
+    let message = add_reference(&subject, &issue.id, store.config());
+
+    let repo = Repository::discover(".").context("Failed to open git repository")?;
+    let signature = repo.signature().context("Failed to get git signature")?;
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+
+    // Make sure there is something to commit before moving the issue
+    let staged = stage(&repo, store.config(), options.all)?;
+    if parent.as_ref().is_some_and(|p| p.tree_id() == staged) {
+        return Err(anyhow!("Nothing to commit"));
+    }
+
+    let tree = if options.done {
+        let target_status = &store.config().last_status().name;
+        store.move_issue(&issue, target_status)?;
+        store.clear_current(Some(&issue.id))?;
+        println!("Moved {} to {}", issue.id, target_status);
+        stage(&repo, store.config(), options.all)?
+    } else {
+        staged
+    };
+    let tree = repo.find_tree(tree)?;
+
+    let parents: Vec<_> = parent.iter().collect();
+    let oid = repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        &message,
+        &tree,
+        &parents,
+    )?;
+
+    println!(
+        "Committed {}: {}",
+        &oid.to_string()[..7],
+        message.trim_end()
+    );
+
+    Ok(())
+}
+
+/// Stage the board's issue files, including moved and deleted ones, and with
+/// `all` every tracked file, as `git commit -a` does. Other files under the
+/// board, such as moth's lock and index, are left alone. Returns the tree.
+fn stage(repo: &Repository, config: &Config, all: bool) -> Result<Oid> {
+    let board = board_path(repo);
+    let status_dirs: Vec<PathBuf> = config.statuses.iter().map(|s| board.join(&s.dir)).collect();
+    let mut issue_files = |path: &Path, _: &[u8]| -> i32 {
+        let is_issue = path.extension().and_then(|s| s.to_str()) == Some("md")
+            && path
+                .parent()
+                .is_some_and(|dir| status_dirs.iter().any(|s| s == dir));
+        if is_issue { 0 } else { 1 }
+    };
+    let mut skip_internal = |path: &Path, _: &[u8]| -> i32 {
+        let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+        if INTERNAL_FILES.contains(&name) { 1 } else { 0 }
+    };
+
+    let mut index = repo.index()?;
+    let board = board.to_string_lossy().into_owned();
+    index.add_all(
+        [&board],
+        IndexAddOption::DEFAULT,
+        Some(&mut issue_files as &mut IndexMatchedPath),
+    )?;
+    index.update_all([&board], Some(&mut skip_internal as &mut IndexMatchedPath))?;
+    if all {
+        index.update_all(["*"], Some(&mut skip_internal as &mut IndexMatchedPath))?;
+    }
+    index.write()?;
+
+    Ok(index.write_tree()?)
+}
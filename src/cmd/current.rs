@@ -0,0 +1,29 @@
+use crate::config::Config;
+use crate::store::Store;
+use anyhow::{Result, anyhow};
+
+/// Print this worktree's current issue ID, or set or clear it.
+pub fn run(id: Option<&str>, clear: bool) -> Result<()> {
+    let config = Config::load()?;
+    let store = Store::new(config)?;
+
+    if clear {
+        store.clear_current(None)?;
+        println!("Cleared current issue");
+        return Ok(());
+    }
+
+    if let Some(id) = id {
+        let issue = store.find(id)?;
+        store.set_current(&issue.id)?;
+        println!("Current issue: {}", issue.id);
+        return Ok(());
+    }
+
+    let id = store
+        .current_id()?
+        .ok_or_else(|| anyhow!("No current issue"))?;
+    println!("{}", id);
+
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use crate::config::Config;
+use crate::history::{
+    ChangeEvent, StoryState, detect_changes, extract_stories, history_board_path,
+};
+use anyhow::{Context, Result, anyhow};
+use colored::{ColoredString, Colorize};
+use git2::Repository;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+pub fn run(from: &str, to: &str) -> Result<()> {
+    write(from, to, &mut io::stdout().lock())
+}
+
+/// Write the board changes between revisions `from` and `to`, grouped by
+/// status in the board's status order.
+pub fn write(from: &str, to: &str, out: &mut impl Write) -> Result<()> {
+    let repo = Repository::discover(".").context("Failed to open git repository")?;
+    let board = history_board_path(&repo)?;
+
+    let read = |rev: &str| -> Result<HashMap<String, StoryState>> {
+        let commit = repo
+            .revparse_single(rev)
+            .and_then(|obj| obj.peel_to_commit())
+            .map_err(|_| anyhow!("Unknown revision: {}", rev))?;
+        Ok(extract_stories(&repo, &commit, &board)?)
+    };
+    let before = read(from)?;
+    let after = read(to)?;
+
+    let groups = summarise(&before, &after);
+    if groups.is_empty() {
+        writeln!(out, "No board changes between {} and {}", from, to)?;
+        return Ok(());
+    }
+
+    let mut columns: Vec<String> = match Config::load() {
+        Ok(config) => config.statuses.iter().map(|s| s.name.clone()).collect(),
+        Err(_) => Vec::new(),
+    };
+    for column in groups.keys() {
+        if !columns.contains(column) {
+            columns.push(column.clone());
+        }
+    }
+
+    for column in columns {
+        let Some(lines) = groups.get(&column) else {
+            continue;
+        };
+
+        writeln!(out, "{}", column)?;
+        for (symbol, line) in lines {
+            writeln!(out, "  {} {}", symbol, line)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A marker and a description of one change.
+type Line = (ColoredString, String);
+
+/// Describe the changes between two boards, grouped by the status each story
+/// ends up in (or was deleted from). Each line has a one-character marker:
+/// `+` created, `-` deleted, `>` moved in, `~` anything else.
+fn summarise(
+    before: &HashMap<String, StoryState>,
+    after: &HashMap<String, StoryState>,
+) -> BTreeMap<String, Vec<Line>> {
+    let mut groups: BTreeMap<String, Vec<_>> = BTreeMap::new();
+    let changes = detect_changes(before, after);
+
+    let mut push = |story: &StoryState, symbol: ColoredString, what: String| {
+        groups.entry(story.column.clone()).or_default().push((
+            symbol,
+            format!(
+                "{} [{}] {}: {}",
+                story.key.id,
+                story.key.severity,
+                story.title(),
+                what
+            ),
+        ));
+    };
+
+    for change in &changes {
+        let story = &change.story;
+        let old = change.old_value().unwrap_or_default();
+        let new = change.new_value().unwrap_or_default();
+
+        match change.event {
+            ChangeEvent::Created => push(story, "+".green(), "created".to_string()),
+            ChangeEvent::Deleted => push(story, "-".red(), "deleted".to_string()),
+            ChangeEvent::Moved => push(story, ">".cyan(), format!("moved from {}", old)),
+            ChangeEvent::SeverityChanged => {
+                push(story, "~".yellow(), format!("severity {} -> {}", old, new))
+            }
+            ChangeEvent::Renamed => push(story, "~".yellow(), format!("renamed from \"{}\"", old)),
+            ChangeEvent::Edited => push(story, "~".yellow(), "edited".to_string()),
+            ChangeEvent::CodeCommit => {}
+        }
+    }
+
+    // Priority order only lives in the filename prefix, so it isn't one of
+    // the report's events; compare it for stories that stayed put
+    let mut ids: Vec<&String> = after.keys().collect();
+    ids.sort();
+    for id in ids {
+        let story = &after[id];
+        if let Some(prev) = before.get(id)
+            && prev.column == story.column
+            && prev.order != story.order
+        {
+            let what = format!(
+                "priority {} -> {}",
+                format_order(prev.order),
+                format_order(story.order)
+            );
+            push(story, "~".yellow(), what);
+        }
+    }
+
+    groups
+}
+
+fn format_order(order: Option<u32>) -> String {
+    order.map_or_else(|| "none".to_string(), |n| n.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::StoryKey;
+    use git2::Oid;
+
+    fn story(id: &str, column: &str, severity: &str, order: Option<u32>) -> StoryState {
+        StoryState {
+            key: StoryKey {
+                id: id.to_string(),
+                severity: severity.to_string(),
+                slug: "some_story".to_string(),
+            },
+            column: column.to_string(),
+            order,
+            blob: Oid::zero(),
+        }
+    }
+
+    fn lines(groups: &BTreeMap<String, Vec<Line>>, column: &str) -> Vec<String> {
+        groups[column]
+            .iter()
+            .map(|(symbol, line)| format!("{} {}", symbol.clone().clear(), line))
+            .collect()
+    }
+
+    #[test]
+    fn test_summarise_groups_by_final_status() {
+        let before = HashMap::from([
+            ("aaa11".to_string(), story("aaa11", "ready", "med", None)),
+            ("bbb22".to_string(), story("bbb22", "ready", "low", None)),
+        ]);
+        let after = HashMap::from([
+            ("aaa11".to_string(), story("aaa11", "doing", "crit", None)),
+            ("ccc33".to_string(), story("ccc33", "ready", "high", None)),
+        ]);
+
+        let groups = summarise(&before, &after);
+        assert_eq!(
+            lines(&groups, "doing"),
+            vec![
+                "> aaa11 [crit] Some Story: moved from ready",
+                "~ aaa11 [crit] Some Story: severity med -> crit",
+            ]
+        );
+        assert_eq!(
+            lines(&groups, "ready"),
+            vec![
+                "- bbb22 [low] Some Story: deleted",
+                "+ ccc33 [high] Some Story: created",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summarise_reprioritised() {
+        let before =
+            HashMap::from([("aaa11".to_string(), story("aaa11", "ready", "med", Some(3)))]);
+        let after = HashMap::from([("aaa11".to_string(), story("aaa11", "ready", "med", Some(1)))]);
+
+        let groups = summarise(&before, &after);
+        assert_eq!(
+            lines(&groups, "ready"),
+            vec!["~ aaa11 [med] Some Story: priority 3 -> 1"]
+        );
+    }
+
+    #[test]
+    fn test_summarise_no_changes() {
+        let board = HashMap::from([("aaa11".to_string(), story("aaa11", "ready", "med", None))]);
+        assert!(summarise(&board, &board).is_empty());
+    }
+}
@@ -0,0 +1,48 @@
+use crate::config::Config;
+use crate::doctor::{self, Problem};
+use crate::store::Store;
+use anyhow::{Result, anyhow};
+
+/// Report problems with the board, repairing what can be with `fix`.
+pub fn run(fix: bool) -> Result<()> {
+    let config = Config::load()?;
+    let store = Store::new(config)?;
+
+    let problems = doctor::check(&store)?;
+    if problems.is_empty() {
+        println!("No problems found");
+        return Ok(());
+    }
+
+    let mut remaining: Vec<&Problem> = Vec::new();
+    let mut fixed = 0;
+    for problem in &problems {
+        if fix && doctor::fix(&store, problem)? {
+            println!("Fixed: {}", problem);
+            fixed += 1;
+        } else {
+            println!("{}", problem);
+            remaining.push(problem);
+        }
+    }
+
+    if fixed > 0 {
+        let dirs: Vec<_> = store
+            .config()
+            .statuses
+            .iter()
+            .map(|s| store.config().status_dir(s))
+            .collect();
+        let dirs: Vec<_> = dirs.iter().map(|d| d.as_path()).collect();
+        store.auto_commit(&dirs, "moth: doctor --fix")?;
+    }
+
+    if remaining.is_empty() {
+        return Ok(());
+    }
+    let fixable = remaining.iter().filter(|p| p.fixable()).count();
+    if fixable > 0 {
+        println!("Run moth doctor --fix to repair {} of them", fixable);
+    }
+    Err(anyhow!("{} problem(s) found", remaining.len()))
+}
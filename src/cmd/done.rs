@@ -1,9 +1,10 @@
+use crate::cmd::branch;
 use crate::config::Config;
+use crate::issue::recorded_branch;
 use crate::store::Store;
 use anyhow::{Context, Result, anyhow};
-use std::fs;
 
-pub fn run(id: Option<&str>) -> Result<()> {
+pub fn run(id: Option<&str>, delete_branch: bool) -> Result<()> {
     let config = Config::load()?;
     let store = Store::new(config)?;
 
@@ -18,18 +19,26 @@ pub fn run(id: Option<&str>) -> Result<()> {
     let last_status = store.config().last_status();
     let target_status = &last_status.name;
 
-    store.move_issue(&issue, target_status)?;
-
-    // Remove .moth/.current if it contains this story ID
-    let current_file = store.config().moth_dir.join(".current");
-    if current_file.exists()
-        && let Ok(current_id) = fs::read_to_string(&current_file)
-        && current_id.trim() == issue.id
-    {
-        let _ = fs::remove_file(&current_file);
-    }
+    let moved = store.move_issue(&issue, target_status)?;
+    store.auto_commit(
+        &[&issue.path, &moved.path],
+        &format!(
+            "moth: move {} {} -> {}",
+            issue.id, issue.status, target_status
+        ),
+    )?;
+    store.clear_current(Some(&issue.id))?;
 
     println!("Moved {} to {}", issue.id, target_status);
 
+    if delete_branch {
+        let issue = store.find(&issue.id)?;
+        let content = store.read(&issue)?;
+        match recorded_branch(&content) {
+            Some(name) => branch::delete_merged(name)?,
+            None => eprintln!("Warning: No branch recorded for {}", issue.id),
+        }
+    }
+
     Ok(())
 }
@@ -0,0 +1,282 @@
+use crate::config::Config;
+use crate::history::{BoardWalker, ChangeEvent, get_commits, history_board_path};
+use crate::store::Store;
+use anyhow::{Context, Result, anyhow};
+use chrono::{Datelike, Days, NaiveDate, Utc};
+use git2::Repository;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::path::Path;
+
+const CONFIDENCE_LEVELS: [u32; 3] = [50, 85, 95];
+
+/// Give up on a trial after this many simulated weeks (ten years).
+const MAX_WEEKS: usize = 520;
+
+pub fn run(
+    items: Option<usize>,
+    date: Option<&str>,
+    weeks: Option<usize>,
+    trials: usize,
+    seed: Option<u64>,
+) -> Result<()> {
+    let config = Config::load()?;
+    let store = Store::new(config)?;
+    let repo = Repository::discover(".").context("Failed to open git repository")?;
+
+    if trials == 0 {
+        return Err(anyhow!("Number of trials must be at least 1"));
+    }
+
+    let done_column = store.config().last_status().name.clone();
+    let completions = completion_dates(&repo, &history_board_path(&repo)?, &done_column)?;
+
+    let today = Utc::now().date_naive();
+    let mut samples = weekly_throughput(&completions, today);
+    if let Some(limit) = weeks {
+        let skip = samples.len().saturating_sub(limit);
+        samples.drain(..skip);
+    }
+
+    if samples.iter().all(|&n| n == 0) {
+        return Err(anyhow!(
+            "No issues reached '{}' in the selected history; cannot forecast",
+            store.config().last_status().name
+        ));
+    }
+
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let ready = &store.config().first_status().name;
+    let items = match items {
+        Some(n) => n,
+        None => store.issues_by_status(ready)?.len(),
+    };
+
+    println!(
+        "Throughput: {} weeks of history, {} issues done, {:.1} per week on average",
+        samples.len(),
+        samples.iter().sum::<usize>(),
+        samples.iter().sum::<usize>() as f64 / samples.len() as f64
+    );
+
+    if items == 0 {
+        println!("Nothing to forecast: no items in {}", ready);
+    } else {
+        let outcomes = simulate_weeks_to_complete(&samples, items, trials, &mut rng);
+        println!();
+        println!("When will {} items be done? ({} trials)", items, trials);
+        for level in CONFIDENCE_LEVELS {
+            let weeks = percentile(&outcomes, level);
+            if weeks > MAX_WEEKS {
+                println!("  {}%: not within {} weeks", level, MAX_WEEKS);
+            } else {
+                let finish = today + Days::new(weeks as u64 * 7);
+                println!("  {}%: by {} ({} weeks)", level, finish, weeks);
+            }
+        }
+    }
+
+    if let Some(date) = date {
+        let target = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .with_context(|| format!("Invalid date '{}'. Expected YYYY-MM-DD", date))?;
+        if target <= today {
+            return Err(anyhow!("Forecast date {} is not in the future", target));
+        }
+
+        // A partial week still gets a week's work
+        let weeks = ((target - today).num_days() as usize).div_ceil(7);
+        let totals = simulate_items_done(&samples, weeks, trials, &mut rng);
+        println!();
+        println!(
+            "How many items by {}? ({} weeks, {} trials)",
+            target, weeks, trials
+        );
+        for level in CONFIDENCE_LEVELS {
+            // At N% confidence we complete at least the (100 - N)th percentile
+            let count = percentile(&totals, 100 - level);
+            println!("  {}%: {} items or more", level, count);
+        }
+    }
+
+    Ok(())
+}
+
+/// Dates on which an issue was moved to the done column, oldest first.
+/// Only the mainline is walked, so work finished on a branch counts once,
+/// when the branch is merged, and issues created as done don't count.
+fn completion_dates(repo: &Repository, board: &Path, done_column: &str) -> Result<Vec<NaiveDate>> {
+    let commits = get_commits(repo, None, None, true)?;
+    let mut walker = BoardWalker::new(repo, board);
+    let mut dates = Vec::new();
+
+    for commit in commits {
+        let changes = walker.advance(&commit)?;
+        let date = chrono::DateTime::from_timestamp(commit.committer().when().seconds(), 0)
+            .unwrap_or_default()
+            .date_naive();
+
+        for change in changes {
+            if change.event == ChangeEvent::Moved && change.story.column == done_column {
+                dates.push(date);
+            }
+        }
+    }
+
+    walker.save_cache();
+    dates.sort();
+    Ok(dates)
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Days::new(date.weekday().num_days_from_monday() as u64)
+}
+
+/// Count completions per calendar week from the first completion up to the
+/// week before the one containing `today`, including weeks where nothing was
+/// finished. The current week isn't over, so it would understate throughput.
+fn weekly_throughput(completions: &[NaiveDate], today: NaiveDate) -> Vec<usize> {
+    let this_week = week_start(today);
+    let Some(first) = completions.first().filter(|first| **first < this_week) else {
+        return Vec::new();
+    };
+
+    let first_week = week_start(*first);
+    let num_weeks = ((this_week - first_week).num_days() / 7) as usize;
+
+    let mut counts = vec![0; num_weeks];
+    for date in completions.iter().filter(|date| **date < this_week) {
+        let week = ((week_start(*date) - first_week).num_days() / 7) as usize;
+        counts[week] += 1;
+    }
+
+    counts
+}
+
+/// For each trial, the number of weeks needed to finish `items` when each
+/// week's throughput is drawn from the historic samples.
+fn simulate_weeks_to_complete(
+    samples: &[usize],
+    items: usize,
+    trials: usize,
+    rng: &mut impl Rng,
+) -> Vec<usize> {
+    (0..trials)
+        .map(|_| {
+            let mut done = 0;
+            let mut weeks = 0;
+            while done < items && weeks <= MAX_WEEKS {
+                done += samples[rng.gen_range(0..samples.len())];
+                weeks += 1;
+            }
+            weeks
+        })
+        .collect()
+}
+
+/// For each trial, the number of items finished within `weeks`.
+fn simulate_items_done(
+    samples: &[usize],
+    weeks: usize,
+    trials: usize,
+    rng: &mut impl Rng,
+) -> Vec<usize> {
+    (0..trials)
+        .map(|_| {
+            (0..weeks)
+                .map(|_| samples[rng.gen_range(0..samples.len())])
+                .sum()
+        })
+        .collect()
+}
+
+/// Nearest-rank percentile of the outcomes.
+fn percentile(outcomes: &[usize], pct: u32) -> usize {
+    let mut sorted = outcomes.to_vec();
+    sorted.sort_unstable();
+    let rank = (pct as usize * sorted.len()).div_ceil(100);
+    sorted[rank.saturating_sub(1).min(sorted.len() - 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::testing::commit;
+    use tempfile::TempDir;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_completion_dates_follow_the_mainline() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let old = ".moth/done/def34-med-old.md";
+        let ready = ".moth/ready/abc12-med-first.md";
+        let done = ".moth/done/abc12-med-first.md";
+        let other = ".moth/ready/ghi56-low-other.md";
+
+        // The issue is finished on a branch while main moves on, then merged
+        let base = commit(&repo, &[old, ready], &[], 0, true);
+        let branch = commit(&repo, &[old, done], &[base], 1, false);
+        let main = commit(&repo, &[old, ready, other], &[base], 2, true);
+        commit(&repo, &[old, done, other], &[main, branch], 3, true);
+
+        let dates = completion_dates(&repo, Path::new(".moth"), "done").unwrap();
+        assert_eq!(dates, vec![date("2025-01-09")]);
+    }
+
+    #[test]
+    fn test_weekly_throughput_includes_empty_weeks() {
+        // 2025-01-06 is a Monday
+        let completions = vec![date("2025-01-06"), date("2025-01-08"), date("2025-01-22")];
+        let counts = weekly_throughput(&completions, date("2025-02-05"));
+        assert_eq!(counts, vec![2, 0, 1, 0]);
+    }
+
+    #[test]
+    fn test_weekly_throughput_leaves_out_current_week() {
+        let completions = vec![date("2025-01-06"), date("2025-01-27"), date("2025-01-29")];
+        let counts = weekly_throughput(&completions, date("2025-01-29"));
+        assert_eq!(counts, vec![1, 0, 0]);
+
+        // Nothing finished before this week gives nothing to sample
+        assert!(weekly_throughput(&completions[1..], date("2025-01-29")).is_empty());
+    }
+
+    #[test]
+    fn test_weekly_throughput_no_completions() {
+        assert!(weekly_throughput(&[], date("2025-01-29")).is_empty());
+    }
+
+    #[test]
+    fn test_percentile() {
+        let outcomes: Vec<usize> = (1..=100).collect();
+        assert_eq!(percentile(&outcomes, 50), 50);
+        assert_eq!(percentile(&outcomes, 85), 85);
+        assert_eq!(percentile(&outcomes, 5), 5);
+        assert_eq!(percentile(&[7], 95), 7);
+    }
+
+    #[test]
+    fn test_simulate_with_constant_throughput() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let weeks = simulate_weeks_to_complete(&[2], 5, 10, &mut rng);
+        assert!(weeks.iter().all(|&w| w == 3));
+
+        let totals = simulate_items_done(&[2], 4, 10, &mut rng);
+        assert!(totals.iter().all(|&t| t == 8));
+    }
+
+    #[test]
+    fn test_simulate_is_reproducible_with_seed() {
+        let samples = [0, 1, 3, 5];
+        let a = simulate_weeks_to_complete(&samples, 20, 50, &mut StdRng::seed_from_u64(7));
+        let b = simulate_weeks_to_complete(&samples, 20, 50, &mut StdRng::seed_from_u64(7));
+        assert_eq!(a, b);
+    }
+}
@@ -1,13 +1,20 @@
 use crate::config::Config;
+use crate::git::common_dir;
+use crate::history::walk_commits;
+use crate::issue::{Issue, closing_ids, referenced_ids};
+use crate::store::Store;
 use anyhow::{Context, Result, anyhow};
+use git2::Repository;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
 const HOOK_MARKER: &str = "# MOTH_HOOK_MARKER";
-const HOOK_SCRIPT: &str = r#"#!/bin/bash
+const PREPARE_COMMIT_MSG_SCRIPT: &str = r#"#!/bin/bash
 # MOTH_HOOK_MARKER - Do not edit this section manually
 
 COMMIT_MSG_FILE=$1
@@ -36,12 +43,8 @@ if [ -z "$MOTH_DIR" ]; then
     exit 0
 fi
 
-CURRENT_FILE="$MOTH_DIR/.current"
-if [ ! -f "$CURRENT_FILE" ]; then
-    exit 0
-fi
-
-STORY_ID=$(cat "$CURRENT_FILE" | tr -d '[:space:]')
+# The current issue for this worktree
+STORY_ID=$(moth current 2>/dev/null) || exit 0
 if [ -z "$STORY_ID" ]; then
     exit 0
 fi
@@ -54,31 +57,132 @@ if moth prefix "$MSG" >/dev/null 2>&1; then
     exit 0
 fi
 
-# Prepend story ID
-echo "[$STORY_ID] $MSG" > "$COMMIT_MSG_FILE"
+# Add the story ID in the configured reference format
+if TAGGED=$(moth prefix --add "$STORY_ID" "$MSG"); then
+    printf '%s\n' "$TAGGED" > "$COMMIT_MSG_FILE"
+fi
+"#;
+
+const COMMIT_MSG_SCRIPT: &str = r#"#!/bin/bash
+# MOTH_HOOK_MARKER - Do not edit this section manually
+
+# Reject commits without a valid issue prefix (exemptions live in config.yml)
+moth hook check "$1" || exit 1
+"#;
+
+const POST_COMMIT_SCRIPT: &str = r#"#!/bin/bash
+# MOTH_HOOK_MARKER - Do not edit this section manually
+
+# Move issues the new commit starts or closes
+moth hook transition HEAD || true
+"#;
+
+const POST_MERGE_SCRIPT: &str = r#"#!/bin/bash
+# MOTH_HOOK_MARKER - Do not edit this section manually
+
+# Move issues the merged commits start or close
+moth hook transition ORIG_HEAD..HEAD || true
 "#;
 
-fn find_git_dir() -> Result<PathBuf> {
-    let mut current = std::env::current_dir().context("Failed to get current directory")?;
+/// The git hooks moth can install.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookKind {
+    /// Prepends the current issue ID to commit messages.
+    PrepareCommitMsg,
+    /// Rejects commit messages without a valid issue prefix.
+    CommitMsg,
+    /// Moves issues referenced by a new commit.
+    PostCommit,
+    /// Moves issues referenced by merged commits.
+    PostMerge,
+}
+
+impl FromStr for HookKind {
+    type Err = anyhow::Error;
 
-    loop {
-        let git_dir = current.join(".git");
-        if git_dir.exists() {
-            return Ok(git_dir);
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "prepare-commit-msg" => Ok(HookKind::PrepareCommitMsg),
+            "commit-msg" => Ok(HookKind::CommitMsg),
+            "post-commit" => Ok(HookKind::PostCommit),
+            "post-merge" => Ok(HookKind::PostMerge),
+            _ => Err(anyhow!(
+                "Invalid hook: {}. Must be one of: prepare-commit-msg, commit-msg, post-commit, post-merge",
+                s
+            )),
         }
+    }
+}
 
-        if !current.pop() {
-            return Err(anyhow!(
-                "No .git directory found. Are you in a git repository?"
-            ));
+impl HookKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookKind::PrepareCommitMsg => "prepare-commit-msg",
+            HookKind::CommitMsg => "commit-msg",
+            HookKind::PostCommit => "post-commit",
+            HookKind::PostMerge => "post-merge",
+        }
+    }
+
+    fn script(&self) -> &'static str {
+        match self {
+            HookKind::PrepareCommitMsg => PREPARE_COMMIT_MSG_SCRIPT,
+            HookKind::CommitMsg => COMMIT_MSG_SCRIPT,
+            HookKind::PostCommit => POST_COMMIT_SCRIPT,
+            HookKind::PostMerge => POST_MERGE_SCRIPT,
         }
     }
 }
 
-pub fn install(force: bool, append: bool) -> Result<()> {
+/// Where git looks for hooks: `core.hooksPath` if set, otherwise the hooks
+/// directory shared by every worktree. The flag is true for a hooks path,
+/// which is managed by the user or a tool such as husky.
+fn hooks_dir() -> Result<(PathBuf, bool)> {
+    let repo = Repository::discover(".")
+        .map_err(|_| anyhow!("No .git directory found. Are you in a git repository?"))?;
+
+    match repo.config()?.get_path("core.hooksPath") {
+        // Git resolves a relative hooks path against the working tree
+        Ok(path) => {
+            let base = repo.workdir().unwrap_or(repo.path());
+            Ok((base.join(path), true))
+        }
+        Err(_) => Ok((common_dir(&repo).join("hooks"), false)),
+    }
+}
+
+/// A block that runs moth's hook from someone else's hook script, so it can
+/// be added without touching the rest of the script.
+fn snippet(kind: HookKind) -> String {
+    format!(
+        "{marker} - Do not edit this section manually\n\
+        if command -v moth >/dev/null 2>&1; then\n    \
+            moth hook run {kind} \"$@\" || exit $?\n\
+        fi\n\
+        {marker}\n",
+        marker = HOOK_MARKER,
+        kind = kind.as_str()
+    )
+}
+
+fn make_executable(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let mut perms = fs::metadata(path)?.permissions();
+        perms.set_mode(0o755);
+        fs::set_permissions(path, perms)?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}
+
+/// Install a moth hook. Hooks directories set with `core.hooksPath` get a
+/// snippet added to their hook scripts unless `force` is given.
+pub fn install(kind: HookKind, force: bool, append: bool, snippet: bool) -> Result<()> {
     let _config = Config::load()?;
-    let git_dir = find_git_dir()?;
-    let hooks_dir = git_dir.join("hooks");
+    let (hooks_dir, user_managed) = hooks_dir()?;
 
     if !hooks_dir.exists() {
         fs::create_dir_all(&hooks_dir).with_context(|| {
@@ -86,7 +190,12 @@ pub fn install(force: bool, append: bool) -> Result<()> {
         })?;
     }
 
-    let hook_path = hooks_dir.join("prepare-commit-msg");
+    let hook_path = hooks_dir.join(kind.as_str());
+    if snippet || (user_managed && !force) {
+        return install_snippet(kind, &hook_path);
+    }
+
+    let script = kind.script();
 
     // Check if hook already exists
     if hook_path.exists() {
@@ -107,40 +216,73 @@ pub fn install(force: bool, append: bool) -> Result<()> {
 
         if append {
             // Append our hook to existing content
-            let combined = format!("{}\n\n{}", existing_content, HOOK_SCRIPT);
+            let combined = format!("{}\n\n{}", existing_content, script);
             fs::write(&hook_path, combined)?;
-            println!("Appended moth hook to existing prepare-commit-msg");
+            println!("Appended moth hook to existing {}", kind.as_str());
         } else {
             // Force mode: replace the hook
-            fs::write(&hook_path, HOOK_SCRIPT)?;
+            fs::write(&hook_path, script)?;
             println!("Installed moth hook (replaced existing)");
         }
     } else {
         // No existing hook, just write ours
-        fs::write(&hook_path, HOOK_SCRIPT)?;
+        fs::write(&hook_path, script)?;
         println!("Installed moth hook");
     }
 
-    // Make the hook executable
-    #[cfg(unix)]
-    {
-        let mut perms = fs::metadata(&hook_path)?.permissions();
-        perms.set_mode(0o755);
-        fs::set_permissions(&hook_path, perms)?;
-    }
+    make_executable(&hook_path)?;
 
     println!("Hook installed at: {}", hook_path.display());
 
     Ok(())
 }
 
-pub fn uninstall() -> Result<()> {
+fn install_snippet(kind: HookKind, hook_path: &Path) -> Result<()> {
+    let mut content = if hook_path.exists() {
+        fs::read_to_string(hook_path)?
+    } else {
+        "#!/bin/sh\n".to_string()
+    };
+
+    if content.contains(HOOK_MARKER) {
+        println!("Moth hook is already installed");
+        return Ok(());
+    }
+
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push('\n');
+    content.push_str(&snippet(kind));
+    fs::write(hook_path, content)?;
+    make_executable(hook_path)?;
+
+    println!("Added moth snippet to {}", hook_path.display());
+
+    Ok(())
+}
+
+/// Run moth's script for a hook, as the installed snippet does. Returns the
+/// script's exit status.
+pub fn run(kind: HookKind, args: &[String]) -> Result<i32> {
+    let status = Command::new("bash")
+        .arg("-c")
+        .arg(kind.script())
+        .arg(kind.as_str())
+        .args(args)
+        .status()
+        .context("Failed to run bash")?;
+
+    Ok(status.code().unwrap_or(1))
+}
+
+pub fn uninstall(kind: HookKind) -> Result<()> {
     let _config = Config::load()?;
-    let git_dir = find_git_dir()?;
-    let hook_path = git_dir.join("hooks").join("prepare-commit-msg");
+    let (hooks_dir, _) = hooks_dir()?;
+    let hook_path = hooks_dir.join(kind.as_str());
 
     if !hook_path.exists() {
-        println!("No prepare-commit-msg hook found");
+        println!("No {} hook found", kind.as_str());
         return Ok(());
     }
 
@@ -148,13 +290,14 @@ pub fn uninstall() -> Result<()> {
 
     if !content.contains(HOOK_MARKER) {
         return Err(anyhow!(
-            "The prepare-commit-msg hook doesn't appear to be a moth hook. \
-            Remove it manually if needed."
+            "The {} hook doesn't appear to be a moth hook. \
+            Remove it manually if needed.",
+            kind.as_str()
         ));
     }
 
     // If the hook only contains our script, delete it
-    if content.trim() == HOOK_SCRIPT.trim() {
+    if content.trim() == kind.script().trim() {
         fs::remove_file(&hook_path)?;
         println!("Removed moth hook");
     } else {
@@ -173,10 +316,191 @@ pub fn uninstall() -> Result<()> {
             }
         }
 
+        // Nothing but a shebang left means we created the file
         let new_content = new_lines.join("\n");
-        fs::write(&hook_path, new_content)?;
-        println!("Removed moth hook section from prepare-commit-msg");
+        if new_content
+            .lines()
+            .all(|line| line.trim().is_empty() || line.starts_with("#!"))
+        {
+            fs::remove_file(&hook_path)?;
+            println!("Removed moth hook");
+        } else {
+            fs::write(&hook_path, new_content.trim_end().to_string() + "\n")?;
+            println!("Removed moth hook section from {}", kind.as_str());
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate a commit message file, as the `commit-msg` hook does. Fails
+/// unless the message references existing issues or one of the configured
+/// exemptions applies. Repositories without a board are ignored.
+pub fn check(message_file: &Path) -> Result<()> {
+    if Config::find_moth_dir().is_err() {
+        return Ok(());
+    }
+    let store = Store::new(Config::load()?)?;
+
+    let message = fs::read_to_string(message_file)
+        .with_context(|| format!("Failed to read commit message: {}", message_file.display()))?;
+    store.validate_message(&message)?;
+
+    Ok(())
+}
+
+/// Move the issues referenced by the commits in `range` (a revision, or
+/// `from..to`), as the `post-commit` and `post-merge` hooks do. A closing
+/// trailer such as `Closes: abc12` moves the issue to the last status, and
+/// any other reference starts an issue still in the first status.
+/// Repositories without a board are ignored.
+pub fn transition(range: &str) -> Result<()> {
+    if Config::find_moth_dir().is_err() {
+        return Ok(());
+    }
+    let config = Config::load()?;
+    let store = Store::new(config)?;
+
+    let repo = Repository::discover(".").context("Failed to open git repository")?;
+    let unknown = || anyhow!("Unknown revision: {}", range);
+    let commits = match range.split_once("..") {
+        Some((from, to)) => {
+            let end = repo
+                .revparse_single(to)
+                .and_then(|obj| obj.peel_to_commit())
+                .map_err(|_| unknown())?;
+            repo.revparse_single(from).map_err(|_| unknown())?;
+            walk_commits(&repo, Some(from), end.id(), false)?
+        }
+        None => vec![
+            repo.revparse_single(range)
+                .and_then(|obj| obj.peel_to_commit())
+                .map_err(|_| unknown())?,
+        ],
+    };
+
+    for commit in commits {
+        let message = commit.message().unwrap_or("");
+        let issues = store.all_issues()?;
+
+        for (issue, target_status) in transitions(message, store.config(), &issues) {
+            store.move_issue(&issue, &target_status)?;
+
+            // Closing the current issue clears it, as `moth done` does
+            if target_status == store.config().last_status().name {
+                store.clear_current(Some(&issue.id))?;
+            }
+
+            println!("Moved {} to {}", issue.id, target_status);
+        }
     }
 
     Ok(())
 }
+
+/// Where each issue a commit message references should move. Closed issues
+/// go to the last status; issues still in the first status go to the second.
+/// References to unknown issues are ignored.
+fn transitions(message: &str, config: &Config, issues: &[Issue]) -> Vec<(Issue, String)> {
+    let closed = closing_ids(message, config);
+    let mut ids = closed.clone();
+    for id in referenced_ids(message, config) {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+
+    let last_status = &config.last_status().name;
+    let first_status = &config.first_status().name;
+    let mut moves = Vec::new();
+
+    for id in ids {
+        let Some(issue) = issues.iter().find(|issue| issue.id == id) else {
+            continue;
+        };
+
+        let target_status = if closed.contains(&id) {
+            last_status
+        } else if &issue.status == first_status
+            && let Some(second_status) = config.second_status()
+        {
+            &second_status.name
+        } else {
+            continue;
+        };
+
+        if &issue.status != target_status {
+            moves.push((issue.clone(), target_status.clone()));
+        }
+    }
+
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issue::Severity;
+
+    #[test]
+    fn test_hook_kind_from_str() {
+        assert_eq!(
+            "prepare-commit-msg".parse::<HookKind>().unwrap(),
+            HookKind::PrepareCommitMsg
+        );
+        assert_eq!(
+            "commit-msg".parse::<HookKind>().unwrap(),
+            HookKind::CommitMsg
+        );
+        assert_eq!(
+            "post-merge".parse::<HookKind>().unwrap(),
+            HookKind::PostMerge
+        );
+        assert!("pre-push".parse::<HookKind>().is_err());
+    }
+
+    fn issue(id: &str, status: &str) -> Issue {
+        Issue {
+            id: id.to_string(),
+            severity: Severity::Med,
+            slug: "fix_it".to_string(),
+            status: status.to_string(),
+            path: PathBuf::from(format!("{}/{}-med-fix_it.md", status, id)),
+            order: None,
+        }
+    }
+
+    fn moves(message: &str, issues: &[Issue]) -> Vec<(String, String)> {
+        transitions(message, &Config::default(), issues)
+            .into_iter()
+            .map(|(issue, status)| (issue.id, status))
+            .collect()
+    }
+
+    #[test]
+    fn test_transitions_start_ready_issues() {
+        let issues = vec![issue("abc12", "ready"), issue("def34", "doing")];
+        assert_eq!(
+            moves("[abc12, def34] Fix", &issues),
+            vec![("abc12".to_string(), "doing".to_string())]
+        );
+        assert!(moves("[zzz99] Fix", &issues).is_empty());
+        assert!(moves("Fix", &issues).is_empty());
+    }
+
+    #[test]
+    fn test_transitions_close_from_trailers() {
+        let issues = vec![
+            issue("abc12", "ready"),
+            issue("def34", "doing"),
+            issue("ghi56", "done"),
+        ];
+        assert_eq!(
+            moves("[abc12] Fix\n\nCloses: abc12, def34, ghi56\n", &issues),
+            vec![
+                ("abc12".to_string(), "done".to_string()),
+                ("def34".to_string(), "done".to_string()),
+            ]
+        );
+    }
+}
@@ -1,5 +1,8 @@
 use crate::config::Config;
+use crate::history::board_path;
+use crate::store::ignore_board_files;
 use anyhow::{Context, Result, anyhow};
+use git2::{ConfigLevel, Repository};
 use std::env;
 use std::fs;
 
@@ -17,7 +20,10 @@ pub fn run() -> Result<()> {
     fs::create_dir(&moth_dir)
         .with_context(|| format!("Failed to create directory: {}", moth_dir.display()))?;
 
-    let config = Config::default();
+    let config = Config {
+        moth_dir: moth_dir.clone(),
+        ..Config::default()
+    };
     let config_path = moth_dir.join("config.yml");
     let yaml = serde_yaml::to_string(&config).context("Failed to serialize config")?;
     fs::write(&config_path, yaml)
@@ -32,8 +38,45 @@ pub fn run() -> Result<()> {
             )
         })?;
     }
+    ignore_board_files(&config)?;
 
     println!("Initialized moth in {}", moth_dir.display());
 
     Ok(())
 }
+
+/// Register moth's merge driver for the board's issue files, creating the
+/// board first if there isn't one here yet.
+pub fn git() -> Result<()> {
+    let cwd = env::current_dir().context("Failed to get current directory")?;
+    if !cwd.join(".moth").exists() {
+        run()?;
+    }
+
+    let repo = Repository::discover(".")
+        .map_err(|_| anyhow!("No .git directory found. Are you in a git repository?"))?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Cannot set up a bare repository"))?;
+
+    let attributes_path = workdir.join(".gitattributes");
+    let attribute = format!("{}/**/*.md merge=moth", board_path(&repo).display());
+    let mut attributes = fs::read_to_string(&attributes_path).unwrap_or_default();
+    if !attributes.lines().any(|line| line.trim() == attribute) {
+        if !attributes.is_empty() && !attributes.ends_with('\n') {
+            attributes.push('\n');
+        }
+        attributes.push_str(&attribute);
+        attributes.push('\n');
+        fs::write(&attributes_path, attributes)
+            .with_context(|| format!("Failed to write {}", attributes_path.display()))?;
+    }
+
+    let mut git_config = repo.config()?.open_level(ConfigLevel::Local)?;
+    git_config.set_str("merge.moth.name", "moth issue merge driver")?;
+    git_config.set_str("merge.moth.driver", "moth merge-driver %O %A %B %P")?;
+
+    println!("Registered the moth merge driver in .gitattributes and git config");
+
+    Ok(())
+}
@@ -1,32 +1,65 @@
 use crate::config::Config;
-use crate::issue::Severity;
+use crate::history::Snapshot;
+use crate::issue::{Issue, Severity};
 use crate::store::Store;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::Colorize;
+use git2::Repository;
 
-pub fn run(status: Option<&str>, show_all: bool, severity_filter: Option<Severity>) -> Result<()> {
-    let config = Config::load()?;
-    let store = Store::new(config)?;
+pub fn run(
+    status: Option<&str>,
+    show_all: bool,
+    severity_filter: Option<Severity>,
+    at: Option<&str>,
+) -> Result<()> {
+    match at {
+        Some(rev) => {
+            let repo = Repository::discover(".").context("Failed to open git repository")?;
+            let snapshot = Snapshot::open(&repo, rev)?;
+            list(snapshot.config(), status, show_all, severity_filter, |s| {
+                Ok(snapshot.issues_by_status(s)?)
+            })
+        }
+        None => {
+            let config = Config::load()?;
+            let store = Store::new(config)?;
+            list(store.config(), status, show_all, severity_filter, |s| {
+                Ok(store.issues_by_status(s)?)
+            })
+        }
+    }
+}
 
+fn list(
+    config: &Config,
+    status: Option<&str>,
+    show_all: bool,
+    severity_filter: Option<Severity>,
+    issues_by_status: impl Fn(&str) -> Result<Vec<Issue>>,
+) -> Result<()> {
     if let Some(status_name) = status {
-        list_status(&store, status_name, severity_filter)?;
+        list_status(&issues_by_status, status_name, severity_filter)?;
     } else if show_all {
-        for status_config in &store.config().statuses {
-            list_status(&store, &status_config.name, severity_filter)?;
+        for status_config in &config.statuses {
+            list_status(&issues_by_status, &status_config.name, severity_filter)?;
         }
     } else {
-        let num_statuses = store.config().statuses.len();
+        let num_statuses = config.statuses.len();
         for i in 0..num_statuses - 1 {
-            let status_name = &store.config().statuses[i].name;
-            list_status(&store, status_name, severity_filter)?;
+            let status_name = &config.statuses[i].name;
+            list_status(&issues_by_status, status_name, severity_filter)?;
         }
     }
 
     Ok(())
 }
 
-fn list_status(store: &Store, status: &str, severity_filter: Option<Severity>) -> Result<()> {
-    let issues = store.issues_by_status(status)?;
+fn list_status(
+    issues_by_status: &impl Fn(&str) -> Result<Vec<Issue>>,
+    status: &str,
+    severity_filter: Option<Severity>,
+) -> Result<()> {
+    let issues = issues_by_status(status)?;
 
     let filtered_issues: Vec<_> = if let Some(sev) = severity_filter {
         issues.into_iter().filter(|i| i.severity == sev).collect()
@@ -0,0 +1,230 @@
+use crate::config::Config;
+use crate::history::{
+    BoardWalker, Change, ChangeEvent, StoryState, get_commits, history_board_path,
+};
+use crate::issue::{referenced_ids, strip_reference};
+use anyhow::{Context, Result, anyhow};
+use colored::Colorize;
+use git2::{Patch, Repository};
+use std::collections::BTreeSet;
+
+/// Something that happened to the issue in one commit.
+struct Entry {
+    sha: String,
+    date: String,
+    author: String,
+    change: Change,
+    /// Commit message without the issue prefix, for code commits.
+    message: String,
+}
+
+pub fn run(id: &str, patch: bool) -> Result<()> {
+    let repo = Repository::discover(".").context("Failed to open git repository")?;
+    let (id, entries) = issue_history(&repo, id)?;
+
+    let Some(last) = entries.last() else {
+        return Err(anyhow!("No history found for issue: {}", id));
+    };
+    println!("{}: {}", id, last.change.story.title());
+
+    for entry in &entries {
+        println!(
+            "{} {} {}  {}",
+            entry.sha[..7].yellow(),
+            entry.date,
+            entry.author,
+            describe(&entry.change, &entry.message)
+        );
+
+        if patch
+            && entry.change.event == ChangeEvent::Edited
+            && let Some(previous) = &entry.change.previous
+        {
+            print!("{}", story_diff(&repo, previous, &entry.change.story)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Walk the history from HEAD and collect every change to the issue whose
+/// ID starts with `partial_id`, along with code commits that reference it.
+fn issue_history(repo: &Repository, partial_id: &str) -> Result<(String, Vec<Entry>)> {
+    let commits = get_commits(repo, None, None, false)?;
+    let mut walker = BoardWalker::new(repo, &history_board_path(repo)?);
+    let config = Config::load().unwrap_or_default();
+    let mut entries = Vec::new();
+
+    for commit in commits {
+        let changes = walker.advance(&commit)?;
+        let sha = commit.id().to_string();
+        let date = chrono::DateTime::from_timestamp(commit.committer().when().seconds(), 0)
+            .unwrap_or_default()
+            .format("%Y-%m-%d")
+            .to_string();
+        let author = commit.author().name().unwrap_or("").to_string();
+
+        let entry = |change: Change, message: String| Entry {
+            sha: sha.clone(),
+            date: date.clone(),
+            author: author.clone(),
+            change,
+            message,
+        };
+
+        for change in &changes {
+            if change.id.starts_with(partial_id) {
+                entries.push(entry(change.clone(), String::new()));
+            }
+        }
+
+        let message = commit.message().unwrap_or("");
+        for issue_id in referenced_ids(message, &config) {
+            if !issue_id.starts_with(partial_id) {
+                continue;
+            }
+            let changed = changes.iter().find(|c| c.id == issue_id);
+            if let Some(story) = walker.state().get(&issue_id).or(changed.map(|c| &c.story)) {
+                let change = Change {
+                    id: issue_id,
+                    event: ChangeEvent::CodeCommit,
+                    story: story.clone(),
+                    previous: None,
+                };
+                entries.push(entry(change, strip_reference(message, &config)));
+            }
+        }
+    }
+
+    walker.save_cache();
+
+    let ids: BTreeSet<&str> = entries.iter().map(|e| e.change.id.as_str()).collect();
+    let id = match ids.len() {
+        0 => return Err(anyhow!("No issue found with ID: {}", partial_id)),
+        1 => ids.into_iter().next().unwrap().to_string(),
+        _ => {
+            return Err(anyhow!(
+                "Ambiguous ID '{}'. Matches: {}",
+                partial_id,
+                ids.into_iter().collect::<Vec<_>>().join(", ")
+            ));
+        }
+    };
+
+    Ok((id, entries))
+}
+
+/// One-line summary of a change, e.g. `moved ready -> doing`.
+fn describe(change: &Change, message: &str) -> String {
+    let old = change.old_value().unwrap_or_default();
+    let new = change.new_value().unwrap_or_default();
+
+    match change.event {
+        ChangeEvent::Created => format!(
+            "created in {} [{}] {}",
+            change.story.column,
+            change.story.key.severity,
+            change.story.title()
+        ),
+        ChangeEvent::Moved => format!("moved {} -> {}", old, new),
+        ChangeEvent::SeverityChanged => format!("severity {} -> {}", old, new),
+        ChangeEvent::Renamed => format!("renamed \"{}\" -> \"{}\"", old, new),
+        ChangeEvent::Edited => "edited".to_string(),
+        ChangeEvent::Deleted => format!("deleted from {}", change.story.column),
+        ChangeEvent::CodeCommit => format!("commit: {}", message),
+    }
+}
+
+/// Unified diff of the story's markdown between two states.
+fn story_diff(repo: &Repository, old: &StoryState, new: &StoryState) -> Result<String> {
+    let old_blob = repo.find_blob(old.blob)?;
+    let new_blob = repo.find_blob(new.blob)?;
+    let old_path = story_path(old);
+    let new_path = story_path(new);
+
+    let mut patch = Patch::from_blobs(
+        &old_blob,
+        Some(old_path.as_ref()),
+        &new_blob,
+        Some(new_path.as_ref()),
+        None,
+    )?;
+    let buf = patch.to_buf()?;
+
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+fn story_path(story: &StoryState) -> String {
+    format!(
+        "{}/{}-{}-{}.md",
+        story.column, story.key.id, story.key.severity, story.key.slug
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::StoryKey;
+    use git2::Oid;
+
+    fn story(column: &str, severity: &str, slug: &str) -> StoryState {
+        StoryState {
+            key: StoryKey {
+                id: "x7k2m".to_string(),
+                severity: severity.to_string(),
+                slug: slug.to_string(),
+            },
+            column: column.to_string(),
+            order: None,
+            blob: Oid::zero(),
+        }
+    }
+
+    fn change(event: ChangeEvent, previous: Option<StoryState>, story: StoryState) -> Change {
+        Change {
+            id: "x7k2m".to_string(),
+            event,
+            story,
+            previous,
+        }
+    }
+
+    #[test]
+    fn test_describe_created_and_moved() {
+        let created = change(ChangeEvent::Created, None, story("ready", "high", "fix_it"));
+        assert_eq!(describe(&created, ""), "created in ready [high] Fix It");
+
+        let moved = change(
+            ChangeEvent::Moved,
+            Some(story("ready", "high", "fix_it")),
+            story("doing", "high", "fix_it"),
+        );
+        assert_eq!(describe(&moved, ""), "moved ready -> doing");
+    }
+
+    #[test]
+    fn test_describe_field_changes() {
+        let severity = change(
+            ChangeEvent::SeverityChanged,
+            Some(story("ready", "low", "fix_it")),
+            story("ready", "crit", "fix_it"),
+        );
+        assert_eq!(describe(&severity, ""), "severity low -> crit");
+
+        let renamed = change(
+            ChangeEvent::Renamed,
+            Some(story("ready", "low", "fix_it")),
+            story("ready", "low", "fix_it_properly"),
+        );
+        assert_eq!(
+            describe(&renamed, ""),
+            "renamed \"Fix It\" -> \"Fix It Properly\""
+        );
+    }
+
+    #[test]
+    fn test_describe_code_commit() {
+        let commit = change(ChangeEvent::CodeCommit, None, story("doing", "med", "x"));
+        assert_eq!(describe(&commit, "Fix the bug"), "commit: Fix the bug");
+    }
+}
@@ -0,0 +1,334 @@
+use crate::config::Config;
+use crate::git::{stage_paths, workdir_relative};
+use crate::issue::Issue;
+use crate::store::Store;
+use anyhow::{Context, Result, anyhow};
+use git2::{Repository, RepositoryState};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// Merge driver for issue files, registered by `moth init --git`. Git passes
+/// the common ancestor, our version (which receives the result) and theirs.
+pub fn driver(base: &Path, ours: &Path, theirs: &Path) -> Result<()> {
+    let read = |path: &Path| {
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+    };
+    let merged = merge_lines(&read(base)?, &read(ours)?, &read(theirs)?);
+    fs::write(ours, merged).with_context(|| format!("Failed to write {}", ours.display()))?;
+    Ok(())
+}
+
+/// Three-way merge by line. A region only one side changed takes that side,
+/// so deleted lines stay deleted. Where both sides changed the same region
+/// the result keeps both, ours first and shared lines once, like git's
+/// `union` merge: the driver never leaves conflict markers in an issue.
+fn merge_lines(base: &str, ours: &str, theirs: &str) -> String {
+    if ours == theirs || theirs == base {
+        return ours.to_string();
+    }
+    if ours == base {
+        return theirs.to_string();
+    }
+
+    let base: Vec<&str> = base.lines().collect();
+    let ours: Vec<&str> = ours.lines().collect();
+    let theirs: Vec<&str> = theirs.lines().collect();
+
+    // Base lines both sides kept split the files into regions merged apart
+    let in_ours: BTreeMap<usize, usize> = common_lines(&base, &ours).into_iter().collect();
+    let in_theirs: BTreeMap<usize, usize> = common_lines(&base, &theirs).into_iter().collect();
+    let mut anchors: Vec<(usize, usize, usize)> = in_ours
+        .iter()
+        .filter_map(|(b, o)| in_theirs.get(b).map(|t| (*b, *o, *t)))
+        .collect();
+    anchors.push((base.len(), ours.len(), theirs.len()));
+
+    let mut merged = Vec::new();
+    let (mut b, mut o, mut t) = (0, 0, 0);
+    for (next_b, next_o, next_t) in anchors {
+        let (base_region, our_region, their_region) =
+            (&base[b..next_b], &ours[o..next_o], &theirs[t..next_t]);
+        if our_region == base_region {
+            merged.extend_from_slice(their_region);
+        } else if their_region == base_region || our_region == their_region {
+            merged.extend_from_slice(our_region);
+        } else {
+            merged.extend(union(our_region, their_region));
+        }
+        if let Some(line) = base.get(next_b) {
+            merged.push(line);
+        }
+        (b, o, t) = (next_b + 1, next_o + 1, next_t + 1);
+    }
+
+    if merged.is_empty() {
+        return String::new();
+    }
+    merged.join("\n") + "\n"
+}
+
+/// Both sides' lines in order, with the lines they share only once.
+fn union<'a>(ours: &[&'a str], theirs: &[&'a str]) -> Vec<&'a str> {
+    let mut lines = Vec::new();
+    let (mut o, mut t) = (0, 0);
+    for (next_o, next_t) in common_lines(ours, theirs)
+        .into_iter()
+        .chain([(ours.len(), theirs.len())])
+    {
+        lines.extend_from_slice(&ours[o..next_o]);
+        lines.extend_from_slice(&theirs[t..next_t]);
+        if let Some(line) = ours.get(next_o) {
+            lines.push(*line);
+        }
+        (o, t) = (next_o + 1, next_t + 1);
+    }
+    lines
+}
+
+/// Index pairs of a longest common subsequence of `a` and `b`. Issue files
+/// are short, so the quadratic table is fine.
+fn common_lines(a: &[&str], b: &[&str]) -> Vec<(usize, usize)> {
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// Find issues that ended up in more than one file, e.g. after a merge where
+/// both branches moved the same issue, and keep one copy of each. Without
+/// `auto`, asks which copy to keep, suggesting the one furthest along. The
+/// result is staged, settling git's conflicts on those files, and committed
+/// with `auto_commit` unless a merge is still to be concluded.
+pub fn resolve(auto: bool) -> Result<()> {
+    let config = Config::load()?;
+    let store = Store::new(config)?;
+    let stdin = io::stdin();
+    resolve_with(&store, auto, &mut stdin.lock())
+}
+
+fn resolve_with(store: &Store, auto: bool, input: &mut impl BufRead) -> Result<()> {
+    let mut by_id: BTreeMap<String, Vec<Issue>> = BTreeMap::new();
+    for issue in store.all_issues()? {
+        by_id.entry(issue.id.clone()).or_default().push(issue);
+    }
+    by_id.retain(|_, copies| copies.len() > 1);
+
+    if by_id.is_empty() {
+        println!("No duplicate issues found");
+        return Ok(());
+    }
+
+    let board = &store.config().moth_dir;
+    let relative = |issue: &Issue| {
+        issue
+            .path
+            .strip_prefix(board)
+            .unwrap_or(&issue.path)
+            .display()
+            .to_string()
+    };
+
+    let ids: Vec<String> = by_id.keys().cloned().collect();
+    let mut touched: Vec<PathBuf> = Vec::new();
+    for (id, copies) in by_id {
+        let suggested = furthest_along(store.config(), &copies);
+
+        let keep = if auto {
+            suggested
+        } else {
+            println!("{} appears in {} places:", id, copies.len());
+            for (i, copy) in copies.iter().enumerate() {
+                println!("  {}) {}", i + 1, relative(copy));
+            }
+            print!("Keep which? [{}] ", suggested + 1);
+            io::stdout().flush()?;
+
+            let mut answer = String::new();
+            input.read_line(&mut answer)?;
+            match answer.trim() {
+                "" => suggested,
+                choice => choice
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| (1..=copies.len()).contains(n))
+                    .map(|n| n - 1)
+                    .ok_or_else(|| anyhow!("Invalid choice: {}", choice))?,
+            }
+        };
+
+        // Nothing written in either copy is lost
+        let kept = &copies[keep];
+        let _lock = store.lock()?;
+        let mut content = store.read(kept)?;
+        for (i, copy) in copies.iter().enumerate() {
+            if i != keep {
+                let other = store.read(copy)?;
+                content = merge_lines("", &content, &other);
+                store.delete_issue(copy)?;
+            }
+        }
+        if !content.trim().is_empty() {
+            store.write(kept, &content)?;
+        }
+
+        println!("Kept {}", relative(kept));
+        touched.extend(copies.iter().map(|copy| copy.path.clone()));
+    }
+
+    let paths: Vec<&Path> = touched.iter().map(PathBuf::as_path).collect();
+    let message = format!("moth: resolve {}", ids.join(" "));
+    if let Ok(repo) = Repository::discover(&store.config().moth_dir) {
+        stage_resolution(&repo, store.config(), &ids, &touched)?;
+        // The merge in progress commits the resolution
+        if repo.state() != RepositoryState::Clean {
+            return Ok(());
+        }
+    }
+    store.auto_commit(&paths, &message)?;
+
+    Ok(())
+}
+
+/// Stage the resolved copies, along with any other conflicted path of the
+/// same issues, such as the status both branches moved it out of.
+fn stage_resolution(
+    repo: &Repository,
+    config: &Config,
+    ids: &[String],
+    touched: &[PathBuf],
+) -> Result<()> {
+    let board = workdir_relative(repo, &config.moth_dir)?;
+    let mut paths = Vec::new();
+    for path in touched {
+        // A board stored on a ref keeps its files out of git
+        let path = workdir_relative(repo, path)?;
+        if !repo.status_should_ignore(&path)? {
+            paths.push(path);
+        }
+    }
+
+    for conflict in repo.index()?.conflicts()? {
+        let conflict = conflict?;
+        for entry in [conflict.ancestor, conflict.our, conflict.their]
+            .into_iter()
+            .flatten()
+        {
+            let path = PathBuf::from(String::from_utf8_lossy(&entry.path).into_owned());
+            if path.starts_with(&board)
+                && Issue::from_path(&path, "").is_ok_and(|issue| ids.contains(&issue.id))
+            {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    paths.dedup();
+
+    Ok(stage_paths(repo, &paths)?)
+}
+
+/// Index of the copy in the latest status, preferring the first of a tie.
+fn furthest_along(config: &Config, copies: &[Issue]) -> usize {
+    let position = |issue: &Issue| {
+        config
+            .statuses
+            .iter()
+            .position(|s| s.name == issue.status)
+            .unwrap_or(0)
+    };
+
+    let mut best = 0;
+    for (i, copy) in copies.iter().enumerate() {
+        if position(copy) > position(&copies[best]) {
+            best = i;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_lines_one_side_changed() {
+        assert_eq!(merge_lines("a\n", "a\nb\n", "a\n"), "a\nb\n");
+        assert_eq!(merge_lines("a\n", "a\n", "a\nc\n"), "a\nc\n");
+        assert_eq!(merge_lines("a\n", "b\n", "b\n"), "b\n");
+    }
+
+    #[test]
+    fn test_merge_lines_both_changed() {
+        assert_eq!(
+            merge_lines("Notes\n", "Notes\nOurs\n", "Notes\nTheirs\n"),
+            "Notes\nOurs\nTheirs\n"
+        );
+        assert_eq!(merge_lines("", "", "Branch: x\n"), "Branch: x\n");
+    }
+
+    #[test]
+    fn test_merge_lines_keeps_deletions_and_repeats() {
+        // They deleted b while we appended d
+        assert_eq!(
+            merge_lines("a\nb\nc\n", "a\nb\nc\nd\n", "a\nc\n"),
+            "a\nc\nd\n"
+        );
+        assert_eq!(
+            merge_lines("- x\n", "- x\n- y\n- x\n", "- x\n- z\n"),
+            "- x\n- y\n- x\n- z\n"
+        );
+        assert_eq!(
+            merge_lines("", "Notes\nOurs\n", "Notes\nTheirs\n"),
+            "Notes\nOurs\nTheirs\n"
+        );
+    }
+
+    #[test]
+    fn test_resolve_keeps_chosen_copy() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let config = Config {
+            moth_dir: temp.path().to_path_buf(),
+            ..Config::default()
+        };
+        let store = Store::new(config).unwrap();
+        let doing = temp.path().join("doing/abc12-med-fix.md");
+        let done = temp.path().join("done/abc12-med-fix.md");
+        fs::write(&doing, "Notes\n").unwrap();
+        fs::write(&done, "").unwrap();
+
+        resolve_with(&store, false, &mut "1\n".as_bytes()).unwrap();
+        assert!(doing.exists());
+        assert!(!done.exists());
+
+        fs::write(&done, "More notes\n").unwrap();
+        assert!(resolve_with(&store, false, &mut "3\n".as_bytes()).is_err());
+
+        // The default is the copy furthest along, with both copies' notes
+        resolve_with(&store, false, &mut "\n".as_bytes()).unwrap();
+        assert!(!doing.exists());
+        assert_eq!(fs::read_to_string(&done).unwrap(), "More notes\nNotes\n");
+    }
+}
@@ -1,10 +1,19 @@
+pub mod branch;
+pub mod chart;
 pub mod claude;
+pub mod commit;
+pub mod current;
+pub mod diff;
+pub mod doctor;
 pub mod done;
 pub mod edit;
+pub mod forecast;
 pub mod hook;
 pub mod init;
 pub mod lifecycle_hooks;
 pub mod list;
+pub mod log;
+pub mod merge;
 pub mod mv;
 pub mod new;
 pub mod prefix;
@@ -14,3 +23,4 @@ pub mod rm;
 pub mod severity;
 pub mod show;
 pub mod start;
+pub mod sync;
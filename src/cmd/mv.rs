@@ -11,7 +11,14 @@ pub fn run(id: &str, target_status: &str) -> Result<()> {
     }
 
     let issue = store.find(id)?;
-    store.move_issue(&issue, target_status)?;
+    let moved = store.move_issue(&issue, target_status)?;
+    store.auto_commit(
+        &[&issue.path, &moved.path],
+        &format!(
+            "moth: move {} {} -> {}",
+            issue.id, issue.status, target_status
+        ),
+    )?;
 
     println!("Moved {} to {}", issue.id, target_status);
 
@@ -3,7 +3,6 @@ use crate::config::Config;
 use crate::issue::Severity;
 use crate::store::Store;
 use anyhow::{Context, Result};
-use std::fs;
 use std::process::Command;
 use std::str::FromStr;
 
@@ -24,8 +23,7 @@ pub fn run(
 
     // Write body if provided
     if let Some(content) = body {
-        fs::write(&issue.path, content)
-            .with_context(|| format!("Failed to write issue body: {}", issue.path.display()))?;
+        store.write(&issue, &content)?;
     }
 
     println!(
@@ -35,10 +33,6 @@ pub fn run(
         issue.severity
     );
 
-    if start {
-        start::run(&issue.id)?;
-    }
-
     // If user did not explicitly skip editor AND no_edit is false, open editor.
     if !skip_editor && !store.config().no_edit {
         let editor = &store.config().editor;
@@ -48,5 +42,15 @@ pub fn run(
             .with_context(|| format!("Failed to open editor: {}", editor))?;
     }
 
+    // Committed once edited, so the commit has what was written
+    store.auto_commit(
+        &[&issue.path],
+        &format!("moth: new {} {}", issue.id, issue.title()),
+    )?;
+
+    if start {
+        start::run(&issue.id)?;
+    }
+
     Ok(())
 }
@@ -1,16 +1,28 @@
-use crate::issue::extract_issue_id;
+use crate::config::Config;
+use crate::issue::{add_reference, referenced_ids};
 use anyhow::Result;
 
-/// Check if a message has an issue prefix, output the ID if found.
-/// Exit code 0 if prefix found, 1 if not.
-pub fn check(message: &str) -> Result<()> {
-    match extract_issue_id(message) {
-        Some(id) => {
-            println!("{}", id);
-            Ok(())
-        }
-        None => {
-            std::process::exit(1);
-        }
+/// Check if a message references any issues, output their IDs if found.
+/// Returns the exit status: 0 if a reference is found, 1 if not.
+///
+/// With `add`, print the message with a reference to that issue added in the
+/// first configured format instead (unchanged if it already references it).
+pub fn check(message: &str, add: Option<&str>) -> Result<i32> {
+    // Outside a board the default reference format and ID length apply
+    let config = Config::load().unwrap_or_default();
+
+    if let Some(id) = add {
+        print!("{}", add_reference(message, id, &config));
+        return Ok(0);
+    }
+
+    let ids = referenced_ids(message, &config);
+    if ids.is_empty() {
+        return Ok(1);
+    }
+
+    for id in ids {
+        println!("{}", id);
     }
+    Ok(0)
 }
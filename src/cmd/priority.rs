@@ -1,13 +1,14 @@
 use crate::config::Config;
 use crate::store::Store;
 use anyhow::{Result, anyhow};
-use std::fs;
 
 pub fn run(id: &str, position: &str, other_id: Option<&str>, compact: Option<bool>) -> Result<()> {
     let config = Config::load()?;
     let store = Store::new(config)?;
+    // The new position depends on the other issues' orders
+    let _lock = store.lock()?;
 
-    let mut issue = store.find(id)?;
+    let issue = store.find(id)?;
 
     // Get the status config to check if it's prioritized
     let status_config = store
@@ -73,14 +74,11 @@ pub fn run(id: &str, position: &str, other_id: Option<&str>, compact: Option<boo
         }
     };
 
-    // Update the issue's order
-    issue.order = new_order;
-
-    // Rename the file
+    // Update the issue's order, renaming its file
+    let mut updated = issue.clone();
+    updated.order = new_order;
+    let issue = store.update_issue(&issue, updated)?;
     let status_dir = store.config().status_dir(status_config);
-    let new_path = status_dir.join(issue.filename());
-
-    fs::rename(&issue.path, &new_path)?;
 
     if let Some(order) = new_order {
         println!("Set priority of {} to {}", issue.id, order);
@@ -94,6 +92,12 @@ pub fn run(id: &str, position: &str, other_id: Option<&str>, compact: Option<boo
         compact_status(&issue.status, &store)?;
     }
 
+    let order = new_order.map_or_else(|| "none".to_string(), |n| n.to_string());
+    store.auto_commit(
+        &[&status_dir],
+        &format!("moth: priority {} {}", issue.id, order),
+    )?;
+
     Ok(())
 }
 
@@ -110,25 +114,26 @@ fn compact_status(status: &str, store: &Store) -> Result<()> {
         ));
     }
 
-    let mut issues = store.issues_by_status(status)?;
+    let _lock = store.lock()?;
+    let issues = store.issues_by_status(status)?;
 
     // Filter to only ordered issues and sort them
-    let mut ordered: Vec<_> = issues.iter_mut().filter(|i| i.order.is_some()).collect();
+    let mut ordered: Vec<_> = issues.iter().filter(|i| i.order.is_some()).collect();
 
     ordered.sort_by_key(|i| i.order.unwrap());
 
-    // Renumber sequentially
-    let status_dir = store.config().status_dir(status_config);
-    for (idx, issue) in ordered.iter_mut().enumerate() {
-        let new_order = (idx + 1) as u32;
-        if issue.order != Some(new_order) {
-            let old_path = issue.path.clone();
-            issue.order = Some(new_order);
-            let new_path = status_dir.join(issue.filename());
-            fs::rename(&old_path, &new_path)?;
-            issue.path = new_path;
-        }
-    }
+    // Renumber sequentially, as one journaled batch
+    let updates = ordered
+        .iter()
+        .enumerate()
+        .filter(|(idx, issue)| issue.order != Some(*idx as u32 + 1))
+        .map(|(idx, issue)| {
+            let mut updated = (*issue).clone();
+            updated.order = Some(idx as u32 + 1);
+            ((*issue).clone(), updated)
+        })
+        .collect();
+    store.update_issues(updates)?;
 
     println!(
         "Compacted {} prioritized issues in {}",
@@ -143,14 +148,26 @@ pub fn compact(status: Option<&str>) -> Result<()> {
     let config = Config::load()?;
     let store = Store::new(config)?;
 
-    if let Some(status_name) = status {
-        compact_status(status_name, &store)?;
-    } else {
+    let statuses: Vec<String> = match status {
+        Some(status_name) => vec![status_name.to_string()],
         // Compact all prioritized statuses
-        for status_config in &store.config().statuses {
-            if status_config.prioritized {
-                compact_status(&status_config.name, &store)?;
-            }
+        None => store
+            .config()
+            .statuses
+            .iter()
+            .filter(|s| s.prioritized)
+            .map(|s| s.name.clone())
+            .collect(),
+    };
+
+    for status_name in &statuses {
+        compact_status(status_name, &store)?;
+
+        if let Some(status_config) = store.config().get_status(status_name) {
+            store.auto_commit(
+                &[&store.config().status_dir(status_config)],
+                &format!("moth: compact {}", status_name),
+            )?;
         }
     }
 
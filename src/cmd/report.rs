@@ -1,273 +1,434 @@
-use crate::issue::parse_issue_prefix;
+use crate::config::Config;
+use crate::history::{
+    BoardWalker, Change, ChangeEvent, StoryState, history_board_path, walk_commits,
+};
+use crate::issue::{referenced_ids, strip_reference};
 use anyhow::{Context, Result, anyhow};
-use git2::{Commit, Repository};
-use std::collections::HashMap;
+use git2::{BranchType, Oid, Repository};
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+    Ndjson,
+    Sqlite,
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "csv" => Ok(ReportFormat::Csv),
+            "json" => Ok(ReportFormat::Json),
+            "ndjson" => Ok(ReportFormat::Ndjson),
+            "sqlite" => Ok(ReportFormat::Sqlite),
+            _ => Err(anyhow!(
+                "Invalid report format: {}. Must be one of: csv, json, ndjson, sqlite",
+                s
+            )),
+        }
+    }
+}
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct StoryKey {
-    id: String,
+/// One line of the report: a change to a story observed in a commit.
+#[derive(Debug, Clone, Serialize)]
+struct ReportRow {
+    commit_sha: String,
+    commit_date: String,
+    committer_name: String,
+    committer_email: String,
+    story_id: String,
     severity: String,
-    slug: String,
+    column: String,
+    event: String,
+    message: String,
+    title: String,
+    previous_column: String,
+    old_value: String,
+    new_value: String,
+    /// The branch the change was first observed on.
+    branch: String,
 }
 
 #[derive(Debug, Clone)]
-struct StoryState {
-    key: StoryKey,
-    column: String,
-    content: String,
+struct CommitRecord {
+    sha: String,
+    date: String,
+    committer_name: String,
+    committer_email: String,
+    message: String,
 }
 
-#[derive(Debug)]
-enum ChangeEvent {
-    Created,
-    Moved,
-    Edited,
-    Deleted,
-    CodeCommit,
+/// Everything collected from walking the history, used by all formats.
+#[derive(Debug, Default)]
+struct Report {
+    commits: Vec<CommitRecord>,
+    rows: Vec<ReportRow>,
+    /// Last known state of every story seen, and whether it was deleted.
+    stories: BTreeMap<String, (StoryState, bool)>,
 }
 
-impl ChangeEvent {
-    fn as_str(&self) -> &'static str {
-        match self {
-            ChangeEvent::Created => "created",
-            ChangeEvent::Moved => "moved",
-            ChangeEvent::Edited => "edited",
-            ChangeEvent::Deleted => "deleted",
-            ChangeEvent::CodeCommit => "code_commit",
-        }
-    }
+/// Which branches the report walks, and how.
+#[derive(Debug, Clone, Default)]
+pub struct BranchOptions {
+    /// Follow only the first parent of merges.
+    pub first_parent: bool,
+    /// Branches to walk, in order. Empty means HEAD (or `--until`).
+    pub branches: Vec<String>,
+    /// Walk every local branch, starting with the current one.
+    pub all: bool,
 }
 
-pub fn run(since: Option<&str>, until: Option<&str>) -> Result<()> {
-    let repo = Repository::open(".").context("Failed to open git repository")?;
-
-    // Get the commit range
-    let commits = get_commits(&repo, since, until)?;
-
-    // Print CSV header
-    println!(
-        "commit_sha,commit_date,committer_name,committer_email,story_id,severity,column,event,message"
-    );
-
-    // Track previous state
-    let mut prev_state: HashMap<String, StoryState> = HashMap::new();
-
-    for commit in commits {
-        let current_state = extract_stories(&repo, &commit)?;
-        let changes = detect_changes(&prev_state, &current_state);
-
-        let commit_time = commit.committer().when();
-        let timestamp = chrono::DateTime::from_timestamp(commit_time.seconds(), 0)
-            .unwrap_or_default()
-            .format("%Y-%m-%dT%H:%M:%SZ");
-
-        // Output .moth file changes
-        for (story_id, event, story) in &changes {
-            println!(
-                "{},{},{},{},{},{},{},{},",
-                commit.id(),
-                timestamp,
-                escape_csv(commit.committer().name().unwrap_or("")),
-                escape_csv(commit.committer().email().unwrap_or("")),
-                escape_csv(story_id),
-                escape_csv(&story.key.severity),
-                escape_csv(&story.column),
-                event.as_str()
-            );
-        }
+pub fn run(
+    since: Option<&str>,
+    until: Option<&str>,
+    branches: &BranchOptions,
+    format: ReportFormat,
+    out: Option<&str>,
+) -> Result<()> {
+    let repo = Repository::discover(".").context("Failed to open git repository")?;
+    let report = collect(&repo, since, until, branches)?;
+
+    if format == ReportFormat::Sqlite {
+        let path = out.ok_or_else(|| anyhow!("The sqlite format requires --out <file>"))?;
+        write_sqlite(Path::new(path), &report)?;
+        println!(
+            "Wrote {} events for {} stories to {}",
+            report.rows.len(),
+            report.stories.len(),
+            path
+        );
+        return Ok(());
+    }
 
-        // Check for code commit referencing an issue
-        if let Some((issue_id, message)) = parse_issue_prefix(commit.message().unwrap_or("")) {
-            // Look up issue state (try current, fall back to prev)
-            if let Some(story) = current_state
-                .get(&issue_id)
-                .or_else(|| prev_state.get(&issue_id))
-            {
-                println!(
-                    "{},{},{},{},{},{},{},{},{}",
-                    commit.id(),
-                    timestamp,
-                    escape_csv(commit.committer().name().unwrap_or("")),
-                    escape_csv(commit.committer().email().unwrap_or("")),
-                    escape_csv(&issue_id),
-                    escape_csv(&story.key.severity),
-                    escape_csv(&story.column),
-                    ChangeEvent::CodeCommit.as_str(),
-                    escape_csv(&message)
-                );
-            }
-        }
+    let mut writer: Box<dyn Write> = match out {
+        Some(path) => Box::new(io::BufWriter::new(
+            fs::File::create(path)
+                .with_context(|| format!("Failed to create output file: {}", path))?,
+        )),
+        None => Box::new(io::stdout().lock()),
+    };
 
-        prev_state = current_state;
+    match format {
+        ReportFormat::Csv => write_csv(&mut writer, &report.rows)?,
+        ReportFormat::Json => write_json(&mut writer, &report.rows)?,
+        ReportFormat::Ndjson => write_ndjson(&mut writer, &report.rows)?,
+        ReportFormat::Sqlite => unreachable!("handled above"),
     }
 
+    writer.flush()?;
+
     Ok(())
 }
 
-fn get_commits<'a>(
-    repo: &'a Repository,
+fn collect(
+    repo: &Repository,
     since: Option<&str>,
     until: Option<&str>,
-) -> Result<Vec<Commit<'a>>> {
-    let mut revwalk = repo.revwalk()?;
-
-    // Start from the until commit if provided, otherwise HEAD
-    let end_oid = match until {
-        Some(rev) => repo.revparse_single(rev)?.id(),
-        None => repo
-            .head()?
-            .target()
-            .ok_or_else(|| anyhow!("HEAD has no target"))?,
-    };
-
-    revwalk.push(end_oid)?;
-
-    // Find the since commit OID if provided
-    let since_oid = if let Some(rev) = since {
-        Some(repo.revparse_single(rev)?.id())
-    } else {
-        None
-    };
+    options: &BranchOptions,
+) -> Result<Report> {
+    let board = history_board_path(repo)?;
+    let config = Config::load().unwrap_or_default();
+    let mut report = Report::default();
+    let mut reported = HashSet::new();
+
+    for (branch, head) in branch_heads(repo, until, options)? {
+        // Each branch is walked with its own board state, but commits shared
+        // with an earlier branch are only reported once, under that branch
+        let commits = walk_commits(repo, since, head, options.first_parent)?;
+        let mut walker = BoardWalker::new(repo, &board);
+
+        for commit in commits {
+            let changes = walker.advance(&commit)?;
+            if !reported.insert(commit.id()) {
+                continue;
+            }
 
-    let mut commits = Vec::new();
+            let commit_time = commit.committer().when();
+            let record = CommitRecord {
+                sha: commit.id().to_string(),
+                date: chrono::DateTime::from_timestamp(commit_time.seconds(), 0)
+                    .unwrap_or_default()
+                    .format("%Y-%m-%dT%H:%M:%SZ")
+                    .to_string(),
+                committer_name: commit.committer().name().unwrap_or("").to_string(),
+                committer_email: commit.committer().email().unwrap_or("").to_string(),
+                message: commit.message().unwrap_or("").to_string(),
+            };
+
+            let row = |change: &Change, message: &str| ReportRow {
+                commit_sha: record.sha.clone(),
+                commit_date: record.date.clone(),
+                committer_name: record.committer_name.clone(),
+                committer_email: record.committer_email.clone(),
+                story_id: change.id.clone(),
+                severity: change.story.key.severity.clone(),
+                column: change.story.column.clone(),
+                event: change.event.as_str().to_string(),
+                message: message.to_string(),
+                title: change.story.title(),
+                previous_column: change.previous_column().unwrap_or("").to_string(),
+                old_value: change.old_value().unwrap_or_default(),
+                new_value: change.new_value().unwrap_or_default(),
+                branch: branch.clone(),
+            };
+
+            // .moth file changes
+            for change in &changes {
+                report.rows.push(row(change, ""));
+                let deleted = change.event == ChangeEvent::Deleted;
+                report
+                    .stories
+                    .insert(change.id.clone(), (change.story.clone(), deleted));
+            }
 
-    for oid in revwalk {
-        let oid = oid?;
-        let commit = repo.find_commit(oid)?;
+            // Code commits referencing issues, one row per issue
+            let message = strip_reference(&record.message, &config);
+            for issue_id in referenced_ids(&record.message, &config) {
+                // Look up issue state (try current, fall back to a change in this
+                // commit, which covers stories deleted alongside the code)
+                let changed = changes.iter().find(|c| c.id == issue_id);
+                if let Some(story) = walker.state().get(&issue_id).or(changed.map(|c| &c.story)) {
+                    let change = Change {
+                        id: issue_id.clone(),
+                        event: ChangeEvent::CodeCommit,
+                        story: story.clone(),
+                        previous: match changed {
+                            Some(c) => c.previous.clone(),
+                            None => Some(story.clone()),
+                        },
+                    };
+                    report.rows.push(row(&change, &message));
+                }
+            }
 
-        // Stop if we reached the since commit (exclusive)
-        if let Some(since_id) = since_oid
-            && oid == since_id
-        {
-            break;
+            report.commits.push(record);
         }
 
-        commits.push(commit);
+        walker.save_cache();
     }
 
-    // Reverse to get chronological order (oldest first)
-    commits.reverse();
-
-    Ok(commits)
+    Ok(report)
 }
 
-fn extract_stories(repo: &Repository, commit: &Commit) -> Result<HashMap<String, StoryState>> {
-    let mut stories = HashMap::new();
-
-    let tree = commit.tree()?;
-
-    // Try to find the .moth directory
-    let moth_entry = match tree.get_name(".moth") {
-        Some(entry) => entry,
-        None => return Ok(stories), // No .moth directory, return empty
+/// The branches to walk, each with the commit it points at.
+fn branch_heads(
+    repo: &Repository,
+    until: Option<&str>,
+    options: &BranchOptions,
+) -> Result<Vec<(String, Oid)>> {
+    let resolve = |name: &str| -> Result<(String, Oid)> {
+        let commit = repo
+            .revparse_single(name)
+            .and_then(|obj| obj.peel_to_commit())
+            .map_err(|_| anyhow!("Unknown branch or revision: {}", name))?;
+        Ok((name.to_string(), commit.id()))
     };
 
-    let moth_tree = repo.find_tree(moth_entry.id())?;
+    let head = repo.head()?;
+    let current = head.shorthand().unwrap_or("HEAD").to_string();
 
-    // Iterate through status directories
-    for entry in moth_tree.iter() {
-        let name = entry.name().unwrap_or("");
-        if name.starts_with('.') {
-            continue; // Skip hidden files/directories
-        }
-
-        // This should be a directory (status column)
-        if entry.kind() != Some(git2::ObjectType::Tree) {
-            continue;
-        }
-
-        let column = name.to_string();
-        let status_tree = repo.find_tree(entry.id())?;
-
-        // Iterate through story files in this status
-        for story_entry in status_tree.iter() {
-            let filename = story_entry.name().unwrap_or("");
+    if !options.all && options.branches.is_empty() {
+        return match until {
+            Some(rev) => Ok(vec![resolve(rev)?]),
+            None => Ok(vec![(current, head.peel_to_commit()?.id())]),
+        };
+    }
 
-            if !filename.ends_with(".md") {
-                continue;
+    let mut names = options.branches.clone();
+    if options.all {
+        let mut local = Vec::new();
+        for branch in repo.branches(Some(BranchType::Local))? {
+            if let Some(name) = branch?.0.name()? {
+                local.push(name.to_string());
             }
+        }
+        local.sort();
 
-            // Parse the filename: {id}-{severity}-{slug}.md
-            if let Some(story) = parse_story_filename(filename) {
-                // Get file content
-                let blob = match repo.find_blob(story_entry.id()) {
-                    Ok(blob) => blob,
-                    Err(_) => continue, // Skip if we can't read it
-                };
-
-                let content = String::from_utf8_lossy(blob.content()).to_string();
-
-                let state = StoryState {
-                    key: story,
-                    column: column.clone(),
-                    content,
-                };
+        // The current branch first, so shared history is attributed to it
+        if let Some(pos) = local.iter().position(|name| *name == current) {
+            let name = local.remove(pos);
+            local.insert(0, name);
+        }
 
-                stories.insert(state.key.id.clone(), state);
+        for name in local {
+            if !names.contains(&name) {
+                names.push(name);
             }
         }
     }
 
-    Ok(stories)
+    names.iter().map(|name| resolve(name)).collect()
 }
 
-fn parse_story_filename(filename: &str) -> Option<StoryKey> {
-    // Remove .md extension
-    let name = filename.strip_suffix(".md")?;
+fn write_csv(writer: &mut dyn Write, rows: &[ReportRow]) -> Result<()> {
+    writeln!(
+        writer,
+        "commit_sha,commit_date,committer_name,committer_email,story_id,severity,column,event,message,title,previous_column,old_value,new_value,branch"
+    )?;
+
+    for row in rows {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            row.commit_sha,
+            row.commit_date,
+            escape_csv(&row.committer_name),
+            escape_csv(&row.committer_email),
+            escape_csv(&row.story_id),
+            escape_csv(&row.severity),
+            escape_csv(&row.column),
+            row.event,
+            escape_csv(&row.message),
+            escape_csv(&row.title),
+            escape_csv(&row.previous_column),
+            escape_csv(&row.old_value),
+            escape_csv(&row.new_value),
+            escape_csv(&row.branch)
+        )?;
+    }
 
-    // Split by hyphen
-    let parts: Vec<&str> = name.splitn(3, '-').collect();
+    Ok(())
+}
 
-    if parts.len() < 3 {
-        return None;
+fn write_json(writer: &mut dyn Write, rows: &[ReportRow]) -> Result<()> {
+    serde_json::to_writer_pretty(&mut *writer, rows)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+fn write_ndjson(writer: &mut dyn Write, rows: &[ReportRow]) -> Result<()> {
+    for row in rows {
+        serde_json::to_writer(&mut *writer, row)?;
+        writeln!(writer)?;
     }
+    Ok(())
+}
 
-    Some(StoryKey {
-        id: parts[0].to_string(),
-        severity: parts[1].to_string(),
-        slug: parts[2].to_string(),
-    })
+/// Write the report as normalised tables: one row per commit, one per story
+/// (its last known state) and one per event referencing both.
+/// Whether `--out` may be deleted to write a fresh database: only empty files
+/// and earlier SQLite reports are.
+fn is_replaceable(path: &Path) -> Result<bool> {
+    let mut header = Vec::new();
+    fs::File::open(path)
+        .and_then(|file| file.take(16).read_to_end(&mut header))
+        .with_context(|| format!("Failed to read existing file: {}", path.display()))?;
+    Ok(header.is_empty() || header == b"SQLite format 3\0")
 }
 
-fn detect_changes(
-    prev: &HashMap<String, StoryState>,
-    current: &HashMap<String, StoryState>,
-) -> Vec<(String, ChangeEvent, StoryState)> {
-    let mut changes = Vec::new();
-
-    // Check for new and modified stories
-    for (id, curr_story) in current {
-        match prev.get(id) {
-            None => {
-                // New story
-                changes.push((id.clone(), ChangeEvent::Created, curr_story.clone()));
-            }
-            Some(prev_story) => {
-                if prev_story.column != curr_story.column {
-                    // Story moved to different column
-                    changes.push((id.clone(), ChangeEvent::Moved, curr_story.clone()));
-                } else if prev_story.key.severity != curr_story.key.severity
-                    || prev_story.key.slug != curr_story.key.slug
-                    || prev_story.content != curr_story.content
-                {
-                    // Story edited (severity, slug, or content changed)
-                    changes.push((id.clone(), ChangeEvent::Edited, curr_story.clone()));
-                }
-            }
+fn write_sqlite(path: &Path, report: &Report) -> Result<()> {
+    if path.exists() {
+        if !is_replaceable(path)? {
+            return Err(anyhow!(
+                "Refusing to overwrite {}: it is not a SQLite database",
+                path.display()
+            ));
         }
+        fs::remove_file(path)
+            .with_context(|| format!("Failed to replace existing file: {}", path.display()))?;
     }
 
-    // Check for deleted stories
-    for (id, prev_story) in prev {
-        if !current.contains_key(id) {
-            changes.push((id.clone(), ChangeEvent::Deleted, prev_story.clone()));
+    let mut conn = Connection::open(path)
+        .with_context(|| format!("Failed to create database: {}", path.display()))?;
+
+    conn.execute_batch(
+        "CREATE TABLE commits (
+            sha TEXT PRIMARY KEY,
+            committed_at TEXT NOT NULL,
+            committer_name TEXT NOT NULL,
+            committer_email TEXT NOT NULL,
+            message TEXT NOT NULL
+        );
+        CREATE TABLE stories (
+            id TEXT PRIMARY KEY,
+            severity TEXT NOT NULL,
+            slug TEXT NOT NULL,
+            title TEXT NOT NULL,
+            column_name TEXT NOT NULL,
+            deleted INTEGER NOT NULL
+        );
+        CREATE TABLE events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            commit_sha TEXT NOT NULL REFERENCES commits(sha),
+            story_id TEXT NOT NULL REFERENCES stories(id),
+            event TEXT NOT NULL,
+            severity TEXT NOT NULL,
+            column_name TEXT NOT NULL,
+            previous_column TEXT NOT NULL,
+            title TEXT NOT NULL,
+            old_value TEXT NOT NULL,
+            new_value TEXT NOT NULL,
+            message TEXT NOT NULL,
+            branch TEXT NOT NULL
+        );
+        CREATE INDEX events_story_id ON events(story_id);
+        CREATE INDEX events_commit_sha ON events(commit_sha);",
+    )?;
+
+    let tx = conn.transaction()?;
+    {
+        let mut insert_commit = tx.prepare(
+            "INSERT INTO commits (sha, committed_at, committer_name, committer_email, message)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for commit in &report.commits {
+            insert_commit.execute(params![
+                commit.sha,
+                commit.date,
+                commit.committer_name,
+                commit.committer_email,
+                commit.message
+            ])?;
         }
-    }
 
-    // Sort by story ID for consistent output
-    changes.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut insert_story = tx.prepare(
+            "INSERT INTO stories (id, severity, slug, title, column_name, deleted)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for (id, (story, deleted)) in &report.stories {
+            insert_story.execute(params![
+                id,
+                story.key.severity,
+                story.key.slug,
+                story.title(),
+                story.column,
+                deleted
+            ])?;
+        }
+
+        let mut insert_event = tx.prepare(
+            "INSERT INTO events (commit_sha, story_id, event, severity, column_name,
+                                 previous_column, title, old_value, new_value, message, branch)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        )?;
+        for row in &report.rows {
+            insert_event.execute(params![
+                row.commit_sha,
+                row.story_id,
+                row.event,
+                row.severity,
+                row.column,
+                row.previous_column,
+                row.title,
+                row.old_value,
+                row.new_value,
+                row.message,
+                row.branch
+            ])?;
+        }
+    }
+    tx.commit()?;
 
-    changes
+    Ok(())
 }
 
 fn escape_csv(s: &str) -> String {
@@ -282,20 +443,23 @@ fn escape_csv(s: &str) -> String {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_parse_story_filename() {
-        let key = parse_story_filename("rxj8y-med-report.md").unwrap();
-        assert_eq!(key.id, "rxj8y");
-        assert_eq!(key.severity, "med");
-        assert_eq!(key.slug, "report");
-    }
-
-    #[test]
-    fn test_parse_story_filename_with_hyphenated_slug() {
-        let key = parse_story_filename("abc123-high-fix-login-bug.md").unwrap();
-        assert_eq!(key.id, "abc123");
-        assert_eq!(key.severity, "high");
-        assert_eq!(key.slug, "fix-login-bug");
+    fn sample_row(event: &str, message: &str) -> ReportRow {
+        ReportRow {
+            commit_sha: "abc".to_string(),
+            commit_date: "2025-01-01T00:00:00Z".to_string(),
+            committer_name: "Test, User".to_string(),
+            committer_email: "test@test.com".to_string(),
+            story_id: "x7k2m".to_string(),
+            severity: "high".to_string(),
+            column: "ready".to_string(),
+            event: event.to_string(),
+            message: message.to_string(),
+            title: "Fix Login Bug".to_string(),
+            previous_column: String::new(),
+            old_value: String::new(),
+            new_value: String::new(),
+            branch: "main".to_string(),
+        }
     }
 
     #[test]
@@ -307,243 +471,52 @@ mod tests {
     }
 
     #[test]
-    fn test_detect_changes_created() {
-        let prev = HashMap::new();
-        let mut current = HashMap::new();
-
-        let story = StoryState {
-            key: StoryKey {
-                id: "abc123".to_string(),
-                severity: "high".to_string(),
-                slug: "test".to_string(),
-            },
-            column: "ready".to_string(),
-            content: "Test content".to_string(),
-        };
-
-        current.insert("abc123".to_string(), story);
-
-        let changes = detect_changes(&prev, &current);
-        assert_eq!(changes.len(), 1);
-        assert!(matches!(changes[0].1, ChangeEvent::Created));
-    }
-
-    #[test]
-    fn test_detect_changes_moved() {
-        let mut prev = HashMap::new();
-        let mut current = HashMap::new();
-
-        let story_prev = StoryState {
-            key: StoryKey {
-                id: "abc123".to_string(),
-                severity: "high".to_string(),
-                slug: "test".to_string(),
-            },
-            column: "ready".to_string(),
-            content: "Test content".to_string(),
-        };
-
-        let mut story_curr = story_prev.clone();
-        story_curr.column = "doing".to_string();
-
-        prev.insert("abc123".to_string(), story_prev);
-        current.insert("abc123".to_string(), story_curr);
-
-        let changes = detect_changes(&prev, &current);
-        assert_eq!(changes.len(), 1);
-        assert!(matches!(changes[0].1, ChangeEvent::Moved));
-    }
-
-    #[test]
-    fn test_detect_changes_edited() {
-        let mut prev = HashMap::new();
-        let mut current = HashMap::new();
-
-        let story_prev = StoryState {
-            key: StoryKey {
-                id: "abc123".to_string(),
-                severity: "high".to_string(),
-                slug: "test".to_string(),
-            },
-            column: "ready".to_string(),
-            content: "Test content".to_string(),
-        };
-
-        let mut story_curr = story_prev.clone();
-        story_curr.content = "Updated content".to_string();
-
-        prev.insert("abc123".to_string(), story_prev);
-        current.insert("abc123".to_string(), story_curr);
-
-        let changes = detect_changes(&prev, &current);
-        assert_eq!(changes.len(), 1);
-        assert!(matches!(changes[0].1, ChangeEvent::Edited));
-    }
-
-    #[test]
-    fn test_detect_changes_deleted() {
-        let mut prev = HashMap::new();
-        let current = HashMap::new();
-
-        let story = StoryState {
-            key: StoryKey {
-                id: "abc123".to_string(),
-                severity: "high".to_string(),
-                slug: "test".to_string(),
-            },
-            column: "ready".to_string(),
-            content: "Test content".to_string(),
-        };
-
-        prev.insert("abc123".to_string(), story);
-
-        let changes = detect_changes(&prev, &current);
-        assert_eq!(changes.len(), 1);
-        assert!(matches!(changes[0].1, ChangeEvent::Deleted));
-    }
-
-    #[test]
-    fn test_change_event_as_str() {
-        assert_eq!(ChangeEvent::Created.as_str(), "created");
-        assert_eq!(ChangeEvent::Moved.as_str(), "moved");
-        assert_eq!(ChangeEvent::Edited.as_str(), "edited");
-        assert_eq!(ChangeEvent::Deleted.as_str(), "deleted");
-        assert_eq!(ChangeEvent::CodeCommit.as_str(), "code_commit");
-    }
-
-    #[test]
-    fn test_parse_story_filename_invalid() {
-        // Too few parts
-        assert!(parse_story_filename("invalid.md").is_none());
-        assert!(parse_story_filename("only-two.md").is_none());
-        // No .md extension
-        assert!(parse_story_filename("abc-med-slug").is_none());
+    fn test_report_format_from_str() {
+        assert_eq!("csv".parse::<ReportFormat>().unwrap(), ReportFormat::Csv);
+        assert_eq!("json".parse::<ReportFormat>().unwrap(), ReportFormat::Json);
+        assert_eq!(
+            "ndjson".parse::<ReportFormat>().unwrap(),
+            ReportFormat::Ndjson
+        );
+        assert_eq!(
+            "sqlite".parse::<ReportFormat>().unwrap(),
+            ReportFormat::Sqlite
+        );
+        assert!("xml".parse::<ReportFormat>().is_err());
     }
 
     #[test]
-    fn test_detect_changes_severity_edit() {
-        let mut prev = HashMap::new();
-        let mut current = HashMap::new();
-
-        let story_prev = StoryState {
-            key: StoryKey {
-                id: "abc123".to_string(),
-                severity: "med".to_string(),
-                slug: "test".to_string(),
-            },
-            column: "ready".to_string(),
-            content: "Test content".to_string(),
-        };
-
-        let mut story_curr = story_prev.clone();
-        story_curr.key.severity = "high".to_string();
-
-        prev.insert("abc123".to_string(), story_prev);
-        current.insert("abc123".to_string(), story_curr);
-
-        let changes = detect_changes(&prev, &current);
-        assert_eq!(changes.len(), 1);
-        assert!(matches!(changes[0].1, ChangeEvent::Edited));
+    fn test_write_csv() {
+        let mut out = Vec::new();
+        write_csv(&mut out, &[sample_row("created", "")]).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[1],
+            "abc,2025-01-01T00:00:00Z,\"Test, User\",test@test.com,x7k2m,high,ready,created,,Fix Login Bug,,,,main"
+        );
     }
 
     #[test]
-    fn test_detect_changes_slug_edit() {
-        let mut prev = HashMap::new();
-        let mut current = HashMap::new();
-
-        let story_prev = StoryState {
-            key: StoryKey {
-                id: "abc123".to_string(),
-                severity: "med".to_string(),
-                slug: "old_slug".to_string(),
-            },
-            column: "ready".to_string(),
-            content: "Test content".to_string(),
-        };
-
-        let mut story_curr = story_prev.clone();
-        story_curr.key.slug = "new_slug".to_string();
-
-        prev.insert("abc123".to_string(), story_prev);
-        current.insert("abc123".to_string(), story_curr);
-
-        let changes = detect_changes(&prev, &current);
-        assert_eq!(changes.len(), 1);
-        assert!(matches!(changes[0].1, ChangeEvent::Edited));
+    fn test_write_ndjson_one_object_per_line() {
+        let mut out = Vec::new();
+        let rows = [sample_row("created", ""), sample_row("code_commit", "Fix")];
+        write_ndjson(&mut out, &rows).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let value: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(value["event"], "code_commit");
+        assert_eq!(value["message"], "Fix");
     }
 
     #[test]
-    fn test_detect_changes_no_change() {
-        let mut prev = HashMap::new();
-        let mut current = HashMap::new();
-
-        let story = StoryState {
-            key: StoryKey {
-                id: "abc123".to_string(),
-                severity: "med".to_string(),
-                slug: "test".to_string(),
-            },
-            column: "ready".to_string(),
-            content: "Test content".to_string(),
-        };
-
-        prev.insert("abc123".to_string(), story.clone());
-        current.insert("abc123".to_string(), story);
-
-        let changes = detect_changes(&prev, &current);
-        assert_eq!(changes.len(), 0);
-    }
-
-    #[test]
-    fn test_detect_changes_multiple() {
-        let mut prev = HashMap::new();
-        let mut current = HashMap::new();
-
-        // Story 1: will be deleted
-        let story1 = StoryState {
-            key: StoryKey {
-                id: "aaa111".to_string(),
-                severity: "low".to_string(),
-                slug: "deleted".to_string(),
-            },
-            column: "ready".to_string(),
-            content: "Will be deleted".to_string(),
-        };
-        prev.insert("aaa111".to_string(), story1);
-
-        // Story 2: will be moved
-        let story2_prev = StoryState {
-            key: StoryKey {
-                id: "bbb222".to_string(),
-                severity: "med".to_string(),
-                slug: "moved".to_string(),
-            },
-            column: "ready".to_string(),
-            content: "Will be moved".to_string(),
-        };
-        let mut story2_curr = story2_prev.clone();
-        story2_curr.column = "doing".to_string();
-        prev.insert("bbb222".to_string(), story2_prev);
-        current.insert("bbb222".to_string(), story2_curr);
-
-        // Story 3: will be created
-        let story3 = StoryState {
-            key: StoryKey {
-                id: "ccc333".to_string(),
-                severity: "high".to_string(),
-                slug: "created".to_string(),
-            },
-            column: "ready".to_string(),
-            content: "Newly created".to_string(),
-        };
-        current.insert("ccc333".to_string(), story3);
-
-        let changes = detect_changes(&prev, &current);
-        assert_eq!(changes.len(), 3);
-
-        // Changes are sorted by ID
-        assert!(matches!(changes[0].1, ChangeEvent::Deleted)); // aaa111
-        assert!(matches!(changes[1].1, ChangeEvent::Moved)); // bbb222
-        assert!(matches!(changes[2].1, ChangeEvent::Created)); // ccc333
+    fn test_write_json_array() {
+        let mut out = Vec::new();
+        write_json(&mut out, &[sample_row("moved", "")]).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(value.as_array().unwrap().len(), 1);
+        assert_eq!(value[0]["column"], "ready");
     }
 }
@@ -8,6 +8,7 @@ pub fn run(id: &str) -> Result<()> {
 
     let issue = store.find(id)?;
     store.delete_issue(&issue)?;
+    store.auto_commit(&[&issue.path], &format!("moth: rm {}", issue.id))?;
 
     println!("Deleted {}: {}", issue.id, issue.title());
 
@@ -1,9 +1,20 @@
 use crate::config::Config;
+use crate::history::Snapshot;
+use crate::issue::Issue;
 use crate::store::Store;
 use anyhow::{Context, Result, anyhow};
-use std::fs;
+use git2::Repository;
+
+pub fn run(id: Option<&str>, at: Option<&str>) -> Result<()> {
+    if let Some(rev) = at {
+        let id = id.ok_or_else(|| anyhow!("An issue ID is required with --at"))?;
+        let repo = Repository::discover(".").context("Failed to open git repository")?;
+        let snapshot = Snapshot::open(&repo, rev)?;
+        let issue = snapshot.find(id)?;
+        print_issue(&issue, &snapshot.read(&issue)?);
+        return Ok(());
+    }
 
-pub fn run(id: Option<&str>) -> Result<()> {
     let config = Config::load()?;
     let store = Store::new(config)?;
 
@@ -15,17 +26,19 @@ pub fn run(id: Option<&str>) -> Result<()> {
             .ok_or_else(|| anyhow!("No current issue"))?,
     };
 
+    let content = store.read(&issue)?;
+
+    print_issue(&issue, &content);
+
+    Ok(())
+}
+
+fn print_issue(issue: &Issue, content: &str) {
     println!(
         "ID: {} | Severity: {} | Status: {}",
         issue.id, issue.severity, issue.status
     );
     println!("Title: {}", issue.title());
     println!("---");
-
-    let content = fs::read_to_string(&issue.path)
-        .with_context(|| format!("Failed to read issue file: {}", issue.path.display()))?;
-
     println!("{}", content);
-
-    Ok(())
 }
@@ -1,7 +1,6 @@
 use crate::config::Config;
 use crate::store::Store;
 use anyhow::{Result, anyhow};
-use std::fs;
 
 pub fn run(id: &str) -> Result<()> {
     let config = Config::load()?;
@@ -15,11 +14,16 @@ pub fn run(id: &str) -> Result<()> {
     let issue = store.find(id)?;
     let target_status = &second_status.name;
 
-    store.move_issue(&issue, target_status)?;
+    let moved = store.move_issue(&issue, target_status)?;
+    store.auto_commit(
+        &[&issue.path, &moved.path],
+        &format!(
+            "moth: move {} {} -> {}",
+            issue.id, issue.status, target_status
+        ),
+    )?;
 
-    // Write the current story ID to .moth/.current
-    let current_file = store.config().moth_dir.join(".current");
-    fs::write(&current_file, &issue.id)?;
+    store.set_current(&issue.id)?;
 
     println!("Moved {} to {}", issue.id, target_status);
 
@@ -0,0 +1,33 @@
+use crate::board_ref::BoardRef;
+use crate::config::{Config, StorageBackend};
+use crate::store::Store;
+use anyhow::{Result, anyhow};
+
+pub fn run(remote: &str) -> Result<()> {
+    let config = Config::load()?;
+    if config.storage.backend != StorageBackend::GitRef {
+        return Err(anyhow!("moth sync needs storage.backend: git_ref"));
+    }
+
+    let store = Store::new(config)?;
+    let git_ref = &store.config().storage.git_ref;
+    let _lock = store.lock()?;
+    match BoardRef::open(store.config())?.sync(remote)? {
+        Some(_) => println!("Synced {} with {}", git_ref, remote),
+        None => println!("Nothing to sync, the board is empty"),
+    }
+
+    // Both sides moving an issue leaves it in two statuses
+    let mut ids: Vec<String> = store.all_issues()?.into_iter().map(|i| i.id).collect();
+    let total = ids.len();
+    ids.sort();
+    ids.dedup();
+    if ids.len() < total {
+        println!(
+            "{} issue(s) changed on both sides now appear more than once; run moth resolve",
+            total - ids.len()
+        );
+    }
+
+    Ok(())
+}
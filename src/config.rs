@@ -1,4 +1,4 @@
-use anyhow::{Context, Result, anyhow};
+use crate::error::{Context, Error, Result};
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::fs;
@@ -18,6 +18,117 @@ pub struct PriorityConfig {
     pub auto_compact: bool,
 }
 
+/// Ways a commit message can reference an issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferenceFormat {
+    /// `[abc12] Fix the bug` at the start of the subject
+    Bracket,
+    /// Conventional commit scope, `feat(abc12): Fix the bug`
+    Scope,
+    /// Trailers such as `Refs: abc12` or `Closes: abc12`
+    Trailer,
+    /// `#abc12` anywhere in the message
+    Hash,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceConfig {
+    /// Recognised formats; the first is used when moth tags a message.
+    #[serde(default = "default_reference_formats")]
+    pub formats: Vec<ReferenceFormat>,
+    /// Trailer keys that reference an issue.
+    #[serde(default = "default_trailers")]
+    pub trailers: Vec<String>,
+    /// Trailer keys that close an issue when the `post-commit` or
+    /// `post-merge` hook runs. Recognised whatever `formats` says.
+    #[serde(default = "default_closes")]
+    pub closes: Vec<String>,
+}
+
+impl Default for ReferenceConfig {
+    fn default() -> Self {
+        ReferenceConfig {
+            formats: default_reference_formats(),
+            trailers: default_trailers(),
+            closes: default_closes(),
+        }
+    }
+}
+
+/// Exemptions for the `commit-msg` hook, which otherwise rejects commits
+/// without an issue prefix.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitMsgConfig {
+    #[serde(default = "default_true")]
+    pub allow_merge: bool,
+    #[serde(default)]
+    pub allow_empty: bool,
+    #[serde(default = "default_exempt_prefixes")]
+    pub exempt_prefixes: Vec<String>,
+}
+
+impl Default for CommitMsgConfig {
+    fn default() -> Self {
+        CommitMsgConfig {
+            allow_merge: true,
+            allow_empty: false,
+            exempt_prefixes: default_exempt_prefixes(),
+        }
+    }
+}
+
+/// Where issues are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// Files in the status directories, committed with the code.
+    #[default]
+    Worktree,
+    /// Commits on a git ref of their own, shared by every branch.
+    GitRef,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub backend: StorageBackend,
+    /// The ref holding the board, for the `git_ref` backend.
+    #[serde(default = "default_git_ref")]
+    pub git_ref: String,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            backend: StorageBackend::default(),
+            git_ref: default_git_ref(),
+        }
+    }
+}
+
+/// How moth relates git branches to issues.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchConfig {
+    /// Use the issue named in the branch, e.g. `abc12-fix-login`, as the
+    /// current issue.
+    #[serde(default)]
+    pub infer_current: bool,
+    /// Name for branches made by `moth branch`, from `{id}`, `{slug}` and
+    /// `{severity}`.
+    #[serde(default = "default_branch_pattern")]
+    pub pattern: String,
+}
+
+impl Default for BranchConfig {
+    fn default() -> Self {
+        BranchConfig {
+            infer_current: false,
+            pattern: default_branch_pattern(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub statuses: Vec<StatusConfig>,
@@ -30,7 +141,17 @@ pub struct Config {
     #[serde(default, alias = "no_edit_on_new")]
     pub no_edit: bool,
     #[serde(default)]
+    pub auto_commit: bool,
+    #[serde(default)]
     pub priority: PriorityConfig,
+    #[serde(default)]
+    pub commit_msg: CommitMsgConfig,
+    #[serde(default)]
+    pub references: ReferenceConfig,
+    #[serde(default)]
+    pub branches: BranchConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
     #[serde(skip)]
     pub moth_dir: PathBuf,
 }
@@ -43,6 +164,38 @@ fn default_id_length() -> usize {
     5
 }
 
+fn default_true() -> bool {
+    true
+}
+
+fn default_reference_formats() -> Vec<ReferenceFormat> {
+    vec![ReferenceFormat::Bracket]
+}
+
+fn default_trailers() -> Vec<String> {
+    vec![
+        "Refs".to_string(),
+        "Closes".to_string(),
+        "Fixes".to_string(),
+    ]
+}
+
+fn default_closes() -> Vec<String> {
+    vec!["Closes".to_string(), "Fixes".to_string()]
+}
+
+fn default_branch_pattern() -> String {
+    "{id}-{slug}".to_string()
+}
+
+fn default_git_ref() -> String {
+    "refs/moth/board".to_string()
+}
+
+fn default_exempt_prefixes() -> Vec<String> {
+    vec!["fixup!".to_string(), "squash!".to_string()]
+}
+
 impl Default for Config {
     fn default() -> Self {
         Config {
@@ -67,7 +220,12 @@ impl Default for Config {
             editor: default_editor(),
             id_length: 5,
             no_edit: false,
+            auto_commit: false,
             priority: PriorityConfig::default(),
+            commit_msg: CommitMsgConfig::default(),
+            references: ReferenceConfig::default(),
+            branches: BranchConfig::default(),
+            storage: StorageConfig::default(),
             moth_dir: PathBuf::new(),
         }
     }
@@ -79,10 +237,10 @@ impl Config {
         let config_path = moth_dir.join("config.yml");
 
         if !config_path.exists() {
-            return Err(anyhow!(
+            return Err(Error::Config(format!(
                 "Config file not found at {}. Try running 'moth init' first.",
                 config_path.display()
-            ));
+            )));
         }
 
         let content = fs::read_to_string(&config_path)
@@ -97,7 +255,16 @@ impl Config {
         Ok(config)
     }
 
-    fn find_moth_dir() -> Result<PathBuf> {
+    /// Parse and validate a config that isn't read from disk, such as the
+    /// `config.yml` in a git tree.
+    pub fn from_yaml(content: &[u8]) -> Result<Self> {
+        let config: Config =
+            serde_yaml::from_slice(content).context("Failed to parse config file")?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn find_moth_dir() -> Result<PathBuf> {
         let mut current = env::current_dir().context("Failed to get current directory")?;
 
         loop {
@@ -107,8 +274,8 @@ impl Config {
             }
 
             if !current.pop() {
-                return Err(anyhow!(
-                    "No .moth directory found. Try running 'moth init' first."
+                return Err(Error::Config(
+                    "No .moth directory found. Try running 'moth init' first.".to_string(),
                 ));
             }
         }
@@ -116,25 +283,43 @@ impl Config {
 
     fn validate(&self) -> Result<()> {
         if self.statuses.len() < 2 {
-            return Err(anyhow!(
+            return Err(Error::Config(format!(
                 "Config must have at least 2 statuses, found {}",
                 self.statuses.len()
-            ));
+            )));
         }
 
         let valid_severities = ["crit", "high", "med", "low"];
         if !valid_severities.contains(&self.default_severity.as_str()) {
-            return Err(anyhow!(
+            return Err(Error::Config(format!(
                 "Invalid default_severity: {}. Must be one of: crit, high, med, low",
                 self.default_severity
+            )));
+        }
+
+        if self.references.formats.is_empty() {
+            return Err(Error::Config(
+                "references.formats must list at least one format".to_string(),
+            ));
+        }
+
+        if !self.branches.pattern.contains("{id}") {
+            return Err(Error::Config(
+                "branches.pattern must contain {id}".to_string(),
+            ));
+        }
+
+        if !self.storage.git_ref.starts_with("refs/") {
+            return Err(Error::Config(
+                "storage.git_ref must be a full ref name, e.g. refs/moth/board".to_string(),
             ));
         }
 
         if self.id_length < 3 || self.id_length > 10 {
-            return Err(anyhow!(
+            return Err(Error::Config(format!(
                 "id_length must be between 3 and 10, found {}",
                 self.id_length
-            ));
+            )));
         }
 
         Ok(())
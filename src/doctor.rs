@@ -0,0 +1,335 @@
+//! Consistency checks for a board on disk, and repairs for the problems that
+//! can be fixed without losing anything.
+
+use crate::error::{Context, Result};
+use crate::issue::Issue;
+use crate::store::{INTERNAL_FILES, Store, ignore_board_files, unignored_board_files};
+use git2::Repository;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Something wrong with the board. Paths are relative to the directory
+/// holding `.moth/`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// An issue file whose name `Issue::from_path` can't parse.
+    Unparseable { path: PathBuf, reason: String },
+    /// The same ID in several files, usually after a merge.
+    DuplicateId { id: String, paths: Vec<PathBuf> },
+    /// Issues sharing a position in a prioritized status.
+    DuplicateOrder {
+        status: String,
+        order: u32,
+        ids: Vec<String>,
+    },
+    /// A priority number on an issue in a status that isn't prioritized.
+    UnexpectedOrder { status: String, id: String },
+    /// A file or directory in a status directory that isn't an issue.
+    StrayFile { path: PathBuf },
+    /// The current issue no longer exists.
+    StaleCurrent { id: String },
+    /// A directory in `.moth/` that no status uses.
+    UnknownStatusDir { path: PathBuf },
+    /// Lines `.moth/.gitignore` needs so git doesn't pick up moth's lock,
+    /// journal and index, or the working copy of a board on a git ref.
+    UnignoredFiles { path: PathBuf, lines: Vec<String> },
+}
+
+impl Problem {
+    /// Whether `fix` can repair it.
+    pub fn fixable(&self) -> bool {
+        matches!(
+            self,
+            Problem::DuplicateOrder { .. }
+                | Problem::UnexpectedOrder { .. }
+                | Problem::StaleCurrent { .. }
+                | Problem::UnignoredFiles { .. }
+        )
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Unparseable { path, reason } => {
+                write!(f, "{}: can't parse filename: {}", path.display(), reason)
+            }
+            Problem::DuplicateId { id, paths } => {
+                let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+                write!(
+                    f,
+                    "{}: duplicate ID in {} (run moth resolve)",
+                    id,
+                    paths.join(", ")
+                )
+            }
+            Problem::DuplicateOrder { status, order, ids } => {
+                write!(f, "{}: {} share priority {}", status, ids.join(", "), order)
+            }
+            Problem::UnexpectedOrder { status, id } => write!(
+                f,
+                "{}: has a priority but {} is not prioritized",
+                id, status
+            ),
+            Problem::StrayFile { path } => write!(f, "{}: not an issue file", path.display()),
+            Problem::StaleCurrent { id } => {
+                write!(f, "current issue {} no longer exists", id)
+            }
+            Problem::UnknownStatusDir { path } => {
+                write!(f, "{}: not a configured status", path.display())
+            }
+            Problem::UnignoredFiles { path, lines } => {
+                write!(f, "{}: missing {}", path.display(), lines.join(", "))
+            }
+        }
+    }
+}
+
+/// Scan the board's directories for problems.
+pub fn check(store: &Store) -> Result<Vec<Problem>> {
+    let config = store.config();
+    let root = config.moth_dir.parent().unwrap_or(Path::new(""));
+    let relative = |path: &Path| path.strip_prefix(root).unwrap_or(path).to_path_buf();
+
+    let mut problems = Vec::new();
+    let mut issues: Vec<Issue> = Vec::new();
+
+    for status in &config.statuses {
+        let dir = config.status_dir(status);
+        if !dir.is_dir() {
+            continue;
+        }
+
+        let mut status_issues = Vec::new();
+        for entry in read_dir(&dir)? {
+            let is_issue_file =
+                entry.is_file() && entry.extension().and_then(|s| s.to_str()) == Some("md");
+            if !is_issue_file {
+                if entry.file_name().and_then(|s| s.to_str()) != Some(".gitkeep") {
+                    problems.push(Problem::StrayFile {
+                        path: relative(&entry),
+                    });
+                }
+                continue;
+            }
+
+            match Issue::from_path(&entry, &status.name) {
+                Ok(issue) => status_issues.push(issue),
+                Err(e) => problems.push(Problem::Unparseable {
+                    path: relative(&entry),
+                    reason: e.to_string(),
+                }),
+            }
+        }
+
+        let mut by_order: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+        for issue in &status_issues {
+            match issue.order {
+                Some(order) if status.prioritized => {
+                    by_order.entry(order).or_default().push(issue.id.clone())
+                }
+                Some(_) => problems.push(Problem::UnexpectedOrder {
+                    status: status.name.clone(),
+                    id: issue.id.clone(),
+                }),
+                None => {}
+            }
+        }
+        for (order, mut ids) in by_order {
+            if ids.len() > 1 {
+                ids.sort();
+                problems.push(Problem::DuplicateOrder {
+                    status: status.name.clone(),
+                    order,
+                    ids,
+                });
+            }
+        }
+
+        issues.extend(status_issues);
+    }
+
+    let mut by_id: BTreeMap<&str, Vec<PathBuf>> = BTreeMap::new();
+    for issue in &issues {
+        by_id
+            .entry(&issue.id)
+            .or_default()
+            .push(relative(&issue.path));
+    }
+    for (id, paths) in by_id {
+        if paths.len() > 1 {
+            problems.push(Problem::DuplicateId {
+                id: id.to_string(),
+                paths,
+            });
+        }
+    }
+
+    if let Some(id) = store.recorded_current_id()
+        && !issues.iter().any(|issue| issue.id == id)
+    {
+        problems.push(Problem::StaleCurrent { id });
+    }
+
+    let status_dirs: HashSet<&str> = config.statuses.iter().map(|s| s.dir.as_str()).collect();
+    for entry in read_dir(&config.moth_dir)? {
+        let Some(name) = entry.file_name().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if entry.is_dir() && !status_dirs.contains(name) && name != "hooks" {
+            problems.push(Problem::UnknownStatusDir {
+                path: relative(&entry),
+            });
+        }
+    }
+
+    // Only matters when git could pick the files up
+    if Repository::discover(&config.moth_dir).is_ok() {
+        let lines = unignored_board_files(config);
+        if !lines.is_empty() {
+            problems.push(Problem::UnignoredFiles {
+                path: relative(&config.moth_dir.join(".gitignore")),
+                lines,
+            });
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Repair `problem` if it is `fixable`. Returns whether it was fixed.
+pub fn fix(store: &Store, problem: &Problem) -> Result<bool> {
+    let _lock = store.lock()?;
+
+    match problem {
+        Problem::DuplicateOrder { status, .. } => {
+            // Renumber the status, keeping the existing order and breaking
+            // ties by ID
+            let mut ordered: Vec<Issue> = store
+                .issues_by_status(status)?
+                .into_iter()
+                .filter(|issue| issue.order.is_some())
+                .collect();
+            ordered.sort_by(|a, b| a.order.cmp(&b.order).then_with(|| a.id.cmp(&b.id)));
+
+            let updates = ordered
+                .into_iter()
+                .enumerate()
+                .filter(|(idx, issue)| issue.order != Some(*idx as u32 + 1))
+                .map(|(idx, issue)| {
+                    let mut updated = issue.clone();
+                    updated.order = Some(idx as u32 + 1);
+                    (issue, updated)
+                })
+                .collect();
+            store.update_issues(updates)?;
+        }
+        Problem::UnexpectedOrder { status, id } => {
+            let issues = store.issues_by_status(status)?;
+            let Some(issue) = issues.iter().find(|issue| issue.id == *id) else {
+                return Ok(false);
+            };
+            let mut updated = issue.clone();
+            updated.order = None;
+            store.update_issue(issue, updated)?;
+        }
+        Problem::StaleCurrent { id } => store.clear_current(Some(id))?,
+        Problem::UnignoredFiles { .. } => ignore_board_files(store.config())?,
+        _ => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+/// Entries of `dir`, skipping moth's own files, sorted for stable output.
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+        if !INTERNAL_FILES.contains(&name) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_check_and_fix() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let moth_dir = temp.path().join(".moth");
+        let config = Config {
+            moth_dir: moth_dir.clone(),
+            ..Config::default()
+        };
+        git2::Repository::init(temp.path()).unwrap();
+        let store = Store::new(config).unwrap();
+        for file in [
+            "ready/001-aaa11-med-first.md",
+            "ready/001-bbb22-med-second.md",
+            "ready/notes.txt",
+            "ready/broken.md",
+            "doing/003-ccc33-low-started.md",
+            "done/aaa11-med-first.md",
+        ] {
+            fs::write(moth_dir.join(file), "").unwrap();
+        }
+        fs::create_dir(moth_dir.join("archive")).unwrap();
+        store.set_current("gone1").unwrap();
+
+        let problems = check(&store).unwrap();
+        let path = |p: &str| Path::new(".moth").join(p);
+        assert!(problems.contains(&Problem::DuplicateOrder {
+            status: "ready".to_string(),
+            order: 1,
+            ids: vec!["aaa11".to_string(), "bbb22".to_string()],
+        }));
+        assert!(problems.contains(&Problem::UnexpectedOrder {
+            status: "doing".to_string(),
+            id: "ccc33".to_string(),
+        }));
+        assert!(problems.contains(&Problem::StrayFile {
+            path: path("ready/notes.txt"),
+        }));
+        assert!(problems.iter().any(
+            |p| matches!(p, Problem::Unparseable { path: found, .. } if *found == path("ready/broken.md"))
+        ));
+        assert!(problems.contains(&Problem::DuplicateId {
+            id: "aaa11".to_string(),
+            paths: vec![
+                path("ready/001-aaa11-med-first.md"),
+                path("done/aaa11-med-first.md")
+            ],
+        }));
+        assert!(problems.contains(&Problem::StaleCurrent {
+            id: "gone1".to_string(),
+        }));
+        assert!(problems.contains(&Problem::UnknownStatusDir {
+            path: path("archive"),
+        }));
+        assert!(problems.contains(&Problem::UnignoredFiles {
+            path: path(".gitignore"),
+            lines: INTERNAL_FILES.iter().map(|f| format!("/{}", f)).collect(),
+        }));
+        assert_eq!(problems.len(), 8);
+
+        for problem in &problems {
+            assert_eq!(fix(&store, problem).unwrap(), problem.fixable());
+        }
+        assert!(moth_dir.join("ready/002-bbb22-med-second.md").exists());
+        assert!(moth_dir.join("doing/ccc33-low-started.md").exists());
+        assert_eq!(store.recorded_current_id(), None);
+        assert_eq!(check(&store).unwrap().len(), 4);
+    }
+}
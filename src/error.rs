@@ -0,0 +1,207 @@
+//! Errors returned by the library. The `cmd` layer wraps these in `anyhow`.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{PoisonError, RwLock};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// No `.moth` directory, or a `config.yml` that is missing or invalid.
+    #[error("{0}")]
+    Config(String),
+
+    #[error("No issue found with ID: {0}")]
+    IssueNotFound(String),
+
+    #[error("Ambiguous ID '{id}'. Matches: {}", matches.join(", "))]
+    AmbiguousId { id: String, matches: Vec<String> },
+
+    #[error("Unknown status: {0}")]
+    UnknownStatus(String),
+
+    /// An argument or issue filename moth can't use, e.g. an unknown severity.
+    #[error("{0}")]
+    Invalid(String),
+
+    /// Another moth process held the board's lock file for too long.
+    #[error("Timed out waiting for {}; is another moth command running?", .0.display())]
+    Locked(PathBuf),
+
+    /// The git repository can't do what was asked, e.g. a push was rejected
+    /// or board changes conflict.
+    #[error("{0}")]
+    Repository(String),
+
+    #[error("{}", describe(context, source))]
+    Io {
+        context: Option<String>,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("{}", describe(context, source))]
+    Git {
+        context: Option<String>,
+        #[source]
+        source: git2::Error,
+    },
+
+    /// Reading or writing YAML or JSON, e.g. `config.yml` or the history cache.
+    #[error("{}", describe(context, source))]
+    Serialization {
+        context: Option<String>,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+/// Show the context if there is one, like `anyhow` does, else the source.
+fn describe(context: &Option<String>, source: &dyn fmt::Display) -> String {
+    match context {
+        Some(context) => context.clone(),
+        None => source.to_string(),
+    }
+}
+
+/// An error that can be given context, turning it into an `Error`.
+pub(crate) trait Source: Sized {
+    fn into_error(self, context: Option<String>) -> Error;
+}
+
+impl Source for io::Error {
+    fn into_error(self, context: Option<String>) -> Error {
+        Error::Io {
+            context,
+            source: self,
+        }
+    }
+}
+
+impl Source for git2::Error {
+    fn into_error(self, context: Option<String>) -> Error {
+        Error::Git {
+            context,
+            source: self,
+        }
+    }
+}
+
+impl Source for serde_yaml::Error {
+    fn into_error(self, context: Option<String>) -> Error {
+        Error::Serialization {
+            context,
+            source: Box::new(self),
+        }
+    }
+}
+
+impl Source for serde_json::Error {
+    fn into_error(self, context: Option<String>) -> Error {
+        Error::Serialization {
+            context,
+            source: Box::new(self),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        e.into_error(None)
+    }
+}
+
+impl From<git2::Error> for Error {
+    fn from(e: git2::Error) -> Self {
+        e.into_error(None)
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(e: serde_yaml::Error) -> Self {
+        e.into_error(None)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        e.into_error(None)
+    }
+}
+
+/// `anyhow::Context` for the library's own error sources.
+pub(crate) trait Context<T> {
+    fn context(self, context: &str) -> Result<T>;
+    fn with_context<F: FnOnce() -> String>(self, f: F) -> Result<T>;
+}
+
+impl<T, E: Source> Context<T> for std::result::Result<T, E> {
+    fn context(self, context: &str) -> Result<T> {
+        self.map_err(|e| e.into_error(Some(context.to_string())))
+    }
+
+    fn with_context<F: FnOnce() -> String>(self, f: F) -> Result<T> {
+        self.map_err(|e| e.into_error(Some(f())))
+    }
+}
+
+type WarningSink = Box<dyn Fn(&str) + Send + Sync>;
+
+static WARNING_SINK: RwLock<Option<WarningSink>> = RwLock::new(None);
+
+/// Receive the library's warnings: problems it works around instead of
+/// failing, such as an issue file whose name doesn't parse or an index that
+/// couldn't be saved. Without a sink, warnings are dropped. The CLI prints
+/// them to stderr.
+pub fn set_warning_sink(sink: impl Fn(&str) + Send + Sync + 'static) {
+    *WARNING_SINK.write().unwrap_or_else(PoisonError::into_inner) = Some(Box::new(sink));
+}
+
+/// Pass a warning to the sink, if one is set.
+pub(crate) fn warn(message: fmt::Arguments) {
+    let sink = WARNING_SINK.read().unwrap_or_else(PoisonError::into_inner);
+    if let Some(sink) = sink.as_ref() {
+        sink(&message.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_is_displayed_over_source() {
+        let err = std::fs::read_to_string("/nonexistent/moth")
+            .context("Failed to read issue file")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Failed to read issue file");
+        assert!(std::error::Error::source(&err).is_some());
+        assert!(matches!(err, Error::Io { .. }));
+    }
+
+    #[test]
+    fn test_ambiguous_id_message() {
+        let err = Error::AmbiguousId {
+            id: "a".to_string(),
+            matches: vec!["abc".to_string(), "abd".to_string()],
+        };
+        assert_eq!(err.to_string(), "Ambiguous ID 'a'. Matches: abc, abd");
+    }
+
+    #[test]
+    fn test_warnings_go_to_the_sink() {
+        static RECEIVED: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+        set_warning_sink(|message| RECEIVED.lock().unwrap().push(message.to_string()));
+
+        warn(format_args!("Failed to save index: {}", "disk full"));
+        assert!(
+            RECEIVED
+                .lock()
+                .unwrap()
+                .contains(&"Failed to save index: disk full".to_string())
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use crate::error::{Context, Error, Result, warn};
+use crate::store::INTERNAL_FILES;
+use git2::build::TreeUpdateBuilder;
+use git2::{
+    FileMode, Index, ObjectType, Oid, Repository, RepositoryState, TreeWalkMode, TreeWalkResult,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The git directory shared by all worktrees of a repository. For linked
+/// worktrees `repo.path()` is the per-worktree directory, which points at
+/// the shared one through its `commondir` file.
+pub fn common_dir(repo: &Repository) -> PathBuf {
+    let git_dir = repo.path();
+    match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(content) => git_dir.join(content.trim()),
+        Err(_) => git_dir.to_path_buf(),
+    }
+}
+
+/// `path` relative to the repository's work tree. The path itself may no
+/// longer exist, e.g. after a file was moved away.
+pub fn workdir_relative(repo: &Repository, path: &Path) -> Result<PathBuf> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| Error::Repository("Cannot commit in a bare repository".to_string()))?
+        .canonicalize()?;
+
+    let absolute = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if !path.exists() => parent.canonicalize()?.join(name),
+        _ => path.canonicalize()?,
+    };
+
+    absolute
+        .strip_prefix(&workdir)
+        .map(Path::to_path_buf)
+        .map_err(|_| Error::Invalid(format!("{} is outside the repository", path.display())))
+}
+
+/// Add `paths` (relative to the work tree) to the repository's
+/// `info/exclude`, so git ignores them whatever the `.gitignore` files say.
+/// Lines already there are left alone.
+pub fn exclude_paths(repo: &Repository, paths: &[PathBuf]) -> Result<()> {
+    let path = common_dir(repo).join("info").join("exclude");
+    let mut content = fs::read_to_string(&path).unwrap_or_default();
+
+    let missing: Vec<String> = paths
+        .iter()
+        .map(|p| format!("/{}", p.display()))
+        .filter(|line| !content.lines().any(|l| l.trim() == line))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    for line in missing {
+        content.push_str(&line);
+        content.push('\n');
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Commit the work tree's version of `paths` (relative to the work tree) on
+/// top of HEAD, leaving every other change, staged or not, alone. A
+/// directory stands for all the files under it. Returns `None` if the paths
+/// match HEAD already, or with a warning if a merge, rebase or similar is in
+/// progress, since a commit then would get in its way.
+pub fn commit_paths(repo: &Repository, paths: &[PathBuf], message: &str) -> Result<Option<Oid>> {
+    if repo.state() != RepositoryState::Clean {
+        warn(format_args!(
+            "Not committing \"{}\" while git is in the middle of a {:?}",
+            message,
+            repo.state()
+        ));
+        return Ok(None);
+    }
+
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| Error::Repository("Cannot commit in a bare repository".to_string()))?;
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let base = match &parent {
+        Some(commit) => commit.tree()?,
+        None => repo.find_tree(repo.treebuilder(None)?.write()?)?,
+    };
+
+    let mut update = TreeUpdateBuilder::new();
+    let mut index = repo.index()?;
+
+    for path in paths {
+        let committed = tree_files(repo, &base, path)?;
+        let current = workdir_files(repo, workdir, path)?;
+
+        for file in committed.iter().filter(|file| !current.contains(file)) {
+            update.remove(file);
+            let _ = index.remove_path(file);
+        }
+        for file in &current {
+            let blob = repo.blob_path(&workdir.join(file))?;
+            update.upsert(file, blob, FileMode::Blob);
+            index.add_path(file)?;
+        }
+    }
+
+    let tree_id = update.create_updated(repo, &base)?;
+    if parent.as_ref().is_some_and(|p| p.tree_id() == tree_id) {
+        return Ok(None);
+    }
+    index.write()?;
+
+    let tree = repo.find_tree(tree_id)?;
+    let signature = repo.signature()?;
+    let parents: Vec<_> = parent.iter().collect();
+    let oid = repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )?;
+
+    Ok(Some(oid))
+}
+
+/// Stage the work tree's version of `paths` (relative to the work tree),
+/// removing those that no longer exist. Conflicts on them are marked
+/// resolved, as `git add` and `git rm` would.
+pub fn stage_paths(repo: &Repository, paths: &[PathBuf]) -> Result<()> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| Error::Repository("Cannot stage in a bare repository".to_string()))?;
+    let mut index = repo.index()?;
+
+    for path in paths {
+        if workdir.join(path).is_file() {
+            index.add_path(path)?;
+        } else {
+            index.remove_path(path)?;
+        }
+    }
+
+    index.write()?;
+    Ok(())
+}
+
+/// Whether the commit being made has no changes, as with `--allow-empty`.
+///
+/// `git commit -a` and `git commit <paths>` stage into a temporary index
+/// named by `GIT_INDEX_FILE`, so read that one when it is set.
+pub fn nothing_staged(repo: &Repository) -> Result<bool> {
+    let head_tree = match repo.head() {
+        Ok(head) => Some(head.peel_to_tree()?),
+        Err(_) => None,
+    };
+    let index = match std::env::var_os("GIT_INDEX_FILE") {
+        Some(path) => {
+            let path = Path::new(&path);
+            // Relative to the directory git runs the hook in, the work tree
+            let path = match repo.workdir() {
+                Some(workdir) if path.is_relative() => workdir.join(path),
+                _ => path.to_path_buf(),
+            };
+            Index::open(&path)?
+        }
+        None => repo.index()?,
+    };
+    let diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), None)?;
+    Ok(diff.deltas().len() == 0)
+}
+
+/// Files at or under `path` in a tree.
+fn tree_files(repo: &Repository, tree: &git2::Tree, path: &Path) -> Result<Vec<PathBuf>> {
+    let Ok(entry) = tree.get_path(path) else {
+        return Ok(Vec::new());
+    };
+    if entry.kind() != Some(ObjectType::Tree) {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    repo.find_tree(entry.id())?
+        .walk(TreeWalkMode::PreOrder, |root, entry| {
+            if entry.kind() == Some(ObjectType::Blob)
+                && let Some(name) = entry.name()
+            {
+                files.push(path.join(root).join(name));
+            }
+            TreeWalkResult::Ok
+        })?;
+
+    Ok(files)
+}
+
+/// Files at or under `path` in the work tree, skipping ignored ones and
+/// moth's internal files.
+fn workdir_files(repo: &Repository, workdir: &Path, path: &Path) -> Result<Vec<PathBuf>> {
+    let full = workdir.join(path);
+    let mut files = Vec::new();
+
+    if full.is_file() {
+        files.push(path.to_path_buf());
+    } else if full.is_dir() {
+        for entry in fs::read_dir(&full)? {
+            let name = entry?.file_name();
+            files.extend(workdir_files(repo, workdir, &path.join(name))?);
+        }
+    }
+
+    let mut kept = Vec::new();
+    for file in files {
+        let name = file.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if !INTERNAL_FILES.contains(&name) && !repo.status_should_ignore(&file)? {
+            kept.push(file);
+        }
+    }
+    Ok(kept)
+}
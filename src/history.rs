@@ -0,0 +1,1360 @@
+use crate::config::{Config, StatusConfig, StorageBackend};
+use crate::error::{Context, Error, Result, warn};
+use crate::git::common_dir;
+use crate::issue::{Issue, slug_to_title};
+use crate::store::{find_issue, sort_issues};
+use git2::{Commit, Oid, Repository, Tree};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Bump when the shape of cached changes changes, to discard old caches.
+const CACHE_VERSION: u32 = 4;
+
+/// Past this many entries, a walk only keeps the entries it used.
+const MAX_CACHE_ENTRIES: usize = 20_000;
+
+/// Where the board lives when no `.moth` directory can be found on disk.
+const DEFAULT_BOARD: &str = ".moth";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct StoryKey {
+    pub id: String,
+    pub severity: String,
+    pub slug: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoryState {
+    pub key: StoryKey,
+    pub column: String,
+    /// Priority order within the column, from the filename prefix.
+    #[serde(default)]
+    pub order: Option<u32>,
+    /// Blob of the story file; equal blobs mean equal content.
+    #[serde(with = "oid_hex")]
+    pub blob: Oid,
+}
+
+impl StoryState {
+    pub fn title(&self) -> String {
+        slug_to_title(&self.key.slug)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeEvent {
+    Created,
+    Moved,
+    SeverityChanged,
+    Renamed,
+    Edited,
+    Deleted,
+    CodeCommit,
+}
+
+impl ChangeEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeEvent::Created => "created",
+            ChangeEvent::Moved => "moved",
+            ChangeEvent::SeverityChanged => "severity_changed",
+            ChangeEvent::Renamed => "renamed",
+            ChangeEvent::Edited => "edited",
+            ChangeEvent::Deleted => "deleted",
+            ChangeEvent::CodeCommit => "code_commit",
+        }
+    }
+}
+
+/// A single change to a story between two board states.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Change {
+    pub id: String,
+    pub event: ChangeEvent,
+    /// State after the change (the last known state for deletions).
+    pub story: StoryState,
+    /// State before the change, if the story existed.
+    pub previous: Option<StoryState>,
+}
+
+impl Change {
+    pub fn previous_column(&self) -> Option<&str> {
+        self.previous.as_ref().map(|p| p.column.as_str())
+    }
+
+    /// The value this change replaced, for events that change one field.
+    pub fn old_value(&self) -> Option<String> {
+        let prev = self.previous.as_ref()?;
+        match self.event {
+            ChangeEvent::Moved => Some(prev.column.clone()),
+            ChangeEvent::SeverityChanged => Some(prev.key.severity.clone()),
+            ChangeEvent::Renamed => Some(prev.title()),
+            _ => None,
+        }
+    }
+
+    /// The value this change introduced, for events that change one field.
+    pub fn new_value(&self) -> Option<String> {
+        match self.event {
+            ChangeEvent::Moved => Some(self.story.column.clone()),
+            ChangeEvent::SeverityChanged => Some(self.story.key.severity.clone()),
+            ChangeEvent::Renamed => Some(self.story.title()),
+            _ => None,
+        }
+    }
+}
+
+/// Commits after `since` up to `until` (HEAD by default), oldest first,
+/// following only first parents with `first_parent` as `walk_commits` does.
+pub fn get_commits<'a>(
+    repo: &'a Repository,
+    since: Option<&str>,
+    until: Option<&str>,
+    first_parent: bool,
+) -> Result<Vec<Commit<'a>>> {
+    // Start from the until commit if provided, otherwise HEAD
+    let end_oid = match until {
+        Some(rev) => repo.revparse_single(rev)?.peel_to_commit()?.id(),
+        None => repo
+            .head()?
+            .target()
+            .ok_or_else(|| Error::Repository("HEAD has no target".to_string()))?,
+    };
+
+    walk_commits(repo, since, end_oid, first_parent)
+}
+
+/// Commits reachable from `end` but not from `since`, oldest first. With
+/// `first_parent` only the mainline is followed, so a merge shows up as a
+/// single commit carrying everything the merged branch changed.
+pub fn walk_commits<'a>(
+    repo: &'a Repository,
+    since: Option<&str>,
+    end: Oid,
+    first_parent: bool,
+) -> Result<Vec<Commit<'a>>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(end)?;
+
+    if first_parent {
+        revwalk.simplify_first_parent()?;
+    }
+
+    // Exclude the since commit and everything before it
+    if let Some(rev) = since {
+        revwalk.hide(repo.revparse_single(rev)?.peel_to_commit()?.id())?;
+    }
+
+    let mut commits = Vec::new();
+
+    for oid in revwalk {
+        commits.push(repo.find_commit(oid?)?);
+    }
+
+    // Reverse to get chronological order (oldest first)
+    commits.reverse();
+
+    Ok(commits)
+}
+
+/// Path of the board inside the repository's work tree, based on the
+/// `.moth` directory `Config::find_moth_dir` finds from the current directory.
+/// Falls back to `.moth` at the root, e.g. when the board no longer exists.
+pub fn board_path(repo: &Repository) -> PathBuf {
+    let found = Config::find_moth_dir()
+        .ok()
+        .and_then(|dir| dir.canonicalize().ok());
+    let workdir = repo.workdir().and_then(|dir| dir.canonicalize().ok());
+
+    match (found, workdir) {
+        (Some(dir), Some(workdir)) => dir
+            .strip_prefix(&workdir)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|_| PathBuf::from(DEFAULT_BOARD)),
+        _ => PathBuf::from(DEFAULT_BOARD),
+    }
+}
+
+/// `board_path`, for reading the board's history from branch commits. A
+/// board stored on a git ref (`storage.backend: git_ref`) isn't in branch
+/// commits, so rather than show it as empty this fails.
+pub fn history_board_path(repo: &Repository) -> Result<PathBuf> {
+    if let Ok(config) = Config::load()
+        && config.storage.backend == StorageBackend::GitRef
+    {
+        return Err(Error::Config(format!(
+            "History commands read the board from branch commits, but it is \
+             stored on {} (storage.backend: git_ref)",
+            config.storage.git_ref
+        )));
+    }
+    Ok(board_path(repo))
+}
+
+pub fn extract_stories(
+    repo: &Repository,
+    commit: &Commit,
+    board: &Path,
+) -> Result<HashMap<String, StoryState>> {
+    let mut stories = HashMap::new();
+
+    // No .moth directory, return empty
+    if let Some(moth_tree) = moth_tree_id(&commit.tree()?, board) {
+        let moth_tree = repo.find_tree(moth_tree)?;
+        let columns = read_columns(repo, &moth_tree);
+        for (_, state) in read_board(repo, &moth_tree, &columns)? {
+            stories.insert(state.key.id.clone(), state);
+        }
+    }
+
+    Ok(stories)
+}
+
+fn moth_tree_id(tree: &Tree, board: &Path) -> Option<Oid> {
+    tree.get_path(board)
+        .ok()
+        .filter(|entry| entry.kind() == Some(git2::ObjectType::Tree))
+        .map(|entry| entry.id())
+}
+
+fn config_blob_id(repo: &Repository, moth_tree: Option<Oid>) -> Result<Option<Oid>> {
+    let Some(tree_id) = moth_tree else {
+        return Ok(None);
+    };
+    Ok(repo
+        .find_tree(tree_id)?
+        .get_name("config.yml")
+        .map(|entry| entry.id()))
+}
+
+#[derive(Deserialize)]
+struct BoardConfig {
+    #[serde(default)]
+    statuses: Vec<StatusConfig>,
+}
+
+/// Map status directories to status names using the board's `config.yml`
+/// as it was in this tree. Directories missing from the config (or a config
+/// that doesn't parse) keep their directory name.
+fn read_columns(repo: &Repository, moth_tree: &Tree) -> HashMap<String, String> {
+    moth_tree
+        .get_name("config.yml")
+        .and_then(|entry| repo.find_blob(entry.id()).ok())
+        .and_then(|blob| serde_yaml::from_slice::<BoardConfig>(blob.content()).ok())
+        .map(|config| {
+            config
+                .statuses
+                .into_iter()
+                .map(|status| (status.dir, status.name))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// List every story in a `.moth` tree along with its path inside the tree.
+fn read_board(
+    repo: &Repository,
+    moth_tree: &Tree,
+    columns: &HashMap<String, String>,
+) -> Result<Vec<(String, StoryState)>> {
+    let mut stories = Vec::new();
+
+    // Iterate through status directories
+    for entry in moth_tree.iter() {
+        let name = entry.name().unwrap_or("");
+
+        // This should be a directory (status column)
+        if entry.kind() != Some(git2::ObjectType::Tree) {
+            continue;
+        }
+
+        let status_tree = repo.find_tree(entry.id())?;
+
+        // Iterate through story files in this status
+        for story_entry in status_tree.iter() {
+            let path = format!("{}/{}", name, story_entry.name().unwrap_or(""));
+            if let Some(state) = story_from_path(&path, story_entry.id(), columns) {
+                stories.push((path, state));
+            }
+        }
+    }
+
+    Ok(stories)
+}
+
+/// Parse a path inside `.moth` like `ready/x7k2m-high-fix_login_bug.md`,
+/// reporting the column by its status name.
+fn story_from_path(path: &str, blob: Oid, columns: &HashMap<String, String>) -> Option<StoryState> {
+    let (dir, filename) = path.split_once('/')?;
+
+    // Skip hidden files/directories and anything nested deeper
+    if dir.starts_with('.') || filename.contains('/') || !filename.ends_with(".md") {
+        return None;
+    }
+
+    // Parse the filename: [{order}-]{id}-{severity}-{slug}.md
+    let key = parse_story_filename(filename)?;
+    let order = filename
+        .split_once('-')
+        .and_then(|(order, _)| order.parse().ok());
+
+    let column = columns.get(dir).map(String::as_str).unwrap_or(dir);
+
+    Some(StoryState {
+        key,
+        column: column.to_string(),
+        order,
+        blob,
+    })
+}
+
+pub fn parse_story_filename(filename: &str) -> Option<StoryKey> {
+    // Remove .md extension
+    let name = filename.strip_suffix(".md")?;
+
+    // Drop the priority order prefix so prioritised stories keep their ID
+    let name = match name.split_once('-') {
+        Some((order, rest)) if order.parse::<u32>().is_ok() => rest,
+        _ => name,
+    };
+
+    // Split by hyphen
+    let parts: Vec<&str> = name.splitn(3, '-').collect();
+
+    if parts.len() < 3 {
+        return None;
+    }
+
+    Some(StoryKey {
+        id: parts[0].to_string(),
+        severity: parts[1].to_string(),
+        slug: parts[2].to_string(),
+    })
+}
+
+pub fn detect_changes(
+    prev: &HashMap<String, StoryState>,
+    current: &HashMap<String, StoryState>,
+) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    // Check for new and modified stories
+    for (id, curr_story) in current {
+        let change = |event| Change {
+            id: id.clone(),
+            event,
+            story: curr_story.clone(),
+            previous: prev.get(id).cloned(),
+        };
+
+        match prev.get(id) {
+            None => {
+                // New story
+                changes.push(change(ChangeEvent::Created));
+            }
+            Some(prev_story) => {
+                // A single commit can change several things about a story,
+                // so each one is reported as its own event
+                if prev_story.column != curr_story.column {
+                    changes.push(change(ChangeEvent::Moved));
+                }
+                if prev_story.key.severity != curr_story.key.severity {
+                    changes.push(change(ChangeEvent::SeverityChanged));
+                }
+                if prev_story.key.slug != curr_story.key.slug {
+                    changes.push(change(ChangeEvent::Renamed));
+                }
+                if prev_story.blob != curr_story.blob {
+                    changes.push(change(ChangeEvent::Edited));
+                }
+            }
+        }
+    }
+
+    // Check for deleted stories
+    for (id, prev_story) in prev {
+        if !current.contains_key(id) {
+            changes.push(Change {
+                id: id.clone(),
+                event: ChangeEvent::Deleted,
+                story: prev_story.clone(),
+                previous: Some(prev_story.clone()),
+            });
+        }
+    }
+
+    // Sort by story ID for consistent output; events for the same story
+    // keep the order they were detected in
+    changes.sort_by(|a, b| a.id.cmp(&b.id));
+
+    changes
+}
+
+/// Walks commits in order, keeping the board state up to date by diffing
+/// each commit's `.moth` tree against the previous one instead of reading
+/// the whole board every time.
+pub struct BoardWalker<'r> {
+    repo: &'r Repository,
+    /// Path of the board inside each commit's tree
+    board: PathBuf,
+    prev_commit: Option<Oid>,
+    prev_tree: Option<Oid>,
+    /// The `config.yml` blob `columns` was read from
+    config_blob: Option<Oid>,
+    /// Status name for every status directory
+    columns: HashMap<String, String>,
+    state: HashMap<String, StoryState>,
+    /// Story id for every story file path inside `.moth`
+    paths: HashMap<String, String>,
+    /// The path whose contents `state` holds for each story id
+    active: HashMap<String, String>,
+    /// Set when `state` was advanced from the cache without tracking paths
+    paths_stale: bool,
+    cache: HistoryCache,
+}
+
+impl<'r> BoardWalker<'r> {
+    pub fn new(repo: &'r Repository, board: &Path) -> Self {
+        BoardWalker {
+            repo,
+            board: board.to_path_buf(),
+            prev_commit: None,
+            prev_tree: None,
+            config_blob: None,
+            columns: HashMap::new(),
+            state: HashMap::new(),
+            paths: HashMap::new(),
+            active: HashMap::new(),
+            paths_stale: false,
+            cache: HistoryCache::load(repo, board),
+        }
+    }
+
+    /// Board state as of the last commit passed to `advance`.
+    pub fn state(&self) -> &HashMap<String, StoryState> {
+        &self.state
+    }
+
+    /// Move to `commit`, returning what changed since the previous commit.
+    /// Commits that don't touch `.moth` are skipped without reading any trees.
+    pub fn advance(&mut self, commit: &Commit) -> Result<Vec<Change>> {
+        let moth_tree = moth_tree_id(&commit.tree()?, &self.board);
+        let prev_commit = self.prev_commit.replace(commit.id());
+
+        if moth_tree == self.prev_tree {
+            return Ok(Vec::new());
+        }
+
+        let config_blob = config_blob_id(self.repo, moth_tree)?;
+        if let Some(changes) = self.cache.get(commit.id(), prev_commit, config_blob) {
+            for change in &changes {
+                if change.event == ChangeEvent::Deleted {
+                    self.state.remove(&change.id);
+                } else {
+                    self.state.insert(change.id.clone(), change.story.clone());
+                }
+            }
+            // `columns` is read again from this commit's config on the next
+            // miss, since `paths_stale` forces a reload
+            self.config_blob = config_blob;
+            self.paths_stale = true;
+            self.prev_tree = moth_tree;
+            return Ok(changes);
+        }
+
+        let changes = if config_blob != self.config_blob {
+            // Statuses may have been renamed, so every story's column has to
+            // be worked out again from the whole board
+            let before = self.state.clone();
+            self.prev_tree = moth_tree;
+            self.reload()?;
+            detect_changes(&before, &self.state)
+        } else {
+            if self.paths_stale {
+                self.reload()?;
+            }
+            let changes = self.apply_diff(moth_tree)?;
+            self.prev_tree = moth_tree;
+            changes
+        };
+        self.cache
+            .insert(commit.id(), prev_commit, config_blob, &changes);
+
+        Ok(changes)
+    }
+
+    /// Persist newly computed changes so later walks can reuse them.
+    pub fn save_cache(&self) {
+        if let Err(e) = self.cache.save(self.repo) {
+            warn(format_args!("Failed to save history cache: {}", e));
+        }
+    }
+
+    /// Rebuild state and path tracking from the previous tree, after cache
+    /// hits or a change to the board's config.
+    fn reload(&mut self) -> Result<()> {
+        self.state.clear();
+        self.paths.clear();
+        self.active.clear();
+        self.columns.clear();
+        self.config_blob = config_blob_id(self.repo, self.prev_tree)?;
+
+        if let Some(tree_id) = self.prev_tree {
+            let tree = self.repo.find_tree(tree_id)?;
+            self.columns = read_columns(self.repo, &tree);
+            for (path, story) in read_board(self.repo, &tree, &self.columns)? {
+                self.add(path, story);
+            }
+        }
+
+        self.paths_stale = false;
+        Ok(())
+    }
+
+    fn apply_diff(&mut self, moth_tree: Option<Oid>) -> Result<Vec<Change>> {
+        let old_tree = self
+            .prev_tree
+            .map(|id| self.repo.find_tree(id))
+            .transpose()?;
+        let new_tree = moth_tree.map(|id| self.repo.find_tree(id)).transpose()?;
+        let diff = self
+            .repo
+            .diff_tree_to_tree(old_tree.as_ref(), new_tree.as_ref(), None)?;
+
+        let mut added = Vec::new();
+        let mut removed = Vec::new();
+
+        for delta in diff.deltas() {
+            let old_path = delta.old_file().path().and_then(|p| p.to_str());
+            let new_path = delta.new_file().path().and_then(|p| p.to_str());
+
+            if delta.status() != git2::Delta::Added
+                && let Some(path) = old_path
+                && (delta.status() == git2::Delta::Deleted || old_path != new_path)
+            {
+                removed.push(path.to_string());
+            }
+
+            if delta.status() != git2::Delta::Deleted
+                && let Some(path) = new_path
+                && let Some(story) = story_from_path(path, delta.new_file().id(), &self.columns)
+            {
+                added.push((path.to_string(), story));
+            }
+        }
+
+        // Snapshot the affected stories before applying the diff
+        let mut affected: HashSet<String> = added.iter().map(|(_, s)| s.key.id.clone()).collect();
+        affected.extend(removed.iter().filter_map(|p| self.paths.get(p).cloned()));
+
+        let before = self.snapshot(&affected);
+
+        // Add before removing so a move keeps the story's new location
+        for (path, story) in added {
+            self.add(path, story);
+        }
+        for path in removed {
+            self.remove(&path);
+        }
+
+        let after = self.snapshot(&affected);
+
+        Ok(detect_changes(&before, &after))
+    }
+
+    fn snapshot(&self, ids: &HashSet<String>) -> HashMap<String, StoryState> {
+        ids.iter()
+            .filter_map(|id| self.state.get(id).map(|s| (id.clone(), s.clone())))
+            .collect()
+    }
+
+    fn add(&mut self, path: String, story: StoryState) {
+        let id = story.key.id.clone();
+        self.paths.insert(path.clone(), id.clone());
+        self.active.insert(id.clone(), path);
+        self.state.insert(id, story);
+    }
+
+    fn remove(&mut self, path: &str) {
+        if let Some(id) = self.paths.remove(path)
+            && self.active.get(&id).map(String::as_str) == Some(path)
+        {
+            self.active.remove(&id);
+            self.state.remove(&id);
+        }
+    }
+}
+
+/// The board at a git revision, read straight from the tree without
+/// checking it out. Issue paths are relative to the board directory.
+pub struct Snapshot<'r> {
+    repo: &'r Repository,
+    tree: Tree<'r>,
+    config: Config,
+}
+
+impl<'r> Snapshot<'r> {
+    pub fn open(repo: &'r Repository, rev: &str) -> Result<Self> {
+        let commit = repo
+            .revparse_single(rev)
+            .and_then(|obj| obj.peel_to_commit())
+            .map_err(|_| Error::Invalid(format!("Unknown revision: {}", rev)))?;
+        let tree_id = moth_tree_id(&commit.tree()?, &history_board_path(repo)?)
+            .ok_or_else(|| Error::Config(format!("No .moth directory found at {}", rev)))?;
+        let tree = repo.find_tree(tree_id)?;
+
+        let config_blob = tree
+            .get_name("config.yml")
+            .map(|entry| entry.id())
+            .ok_or_else(|| Error::Config(format!("No config.yml found at {}", rev)))?;
+        let config = Config::from_yaml(repo.find_blob(config_blob)?.content())
+            .map_err(|e| Error::Config(format!("Invalid config.yml at {}: {}", rev, e)))?;
+
+        Ok(Snapshot { repo, tree, config })
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn find(&self, partial_id: &str) -> Result<Issue> {
+        find_issue(&self.all_issues()?, partial_id)
+    }
+
+    pub fn all_issues(&self) -> Result<Vec<Issue>> {
+        let mut issues = Vec::new();
+
+        for status in &self.config.statuses {
+            issues.extend(self.issues_by_status(&status.name)?);
+        }
+
+        Ok(issues)
+    }
+
+    pub fn issues_by_status(&self, status: &str) -> Result<Vec<Issue>> {
+        let status_config = self
+            .config
+            .get_status(status)
+            .ok_or_else(|| Error::UnknownStatus(status.to_string()))?;
+
+        let mut issues = Vec::new();
+
+        // Git doesn't store empty directories, so a missing one is empty
+        let Some(entry) = self.tree.get_name(&status_config.dir) else {
+            return Ok(issues);
+        };
+        let status_tree = self.repo.find_tree(entry.id())?;
+
+        for entry in status_tree.iter() {
+            let path = Path::new(&status_config.dir).join(entry.name().unwrap_or(""));
+
+            if path.extension().and_then(|s| s.to_str()) == Some("md") {
+                match Issue::from_path(&path, status) {
+                    Ok(issue) => issues.push(issue),
+                    Err(e) => warn(format_args!("Failed to parse {}: {}", path.display(), e)),
+                }
+            }
+        }
+
+        sort_issues(&mut issues);
+
+        Ok(issues)
+    }
+
+    /// Contents of an issue file in this snapshot.
+    pub fn read(&self, issue: &Issue) -> Result<String> {
+        let entry = self
+            .tree
+            .get_path(&issue.path)
+            .with_context(|| format!("Failed to read issue file: {}", issue.path.display()))?;
+        let blob = self.repo.find_blob(entry.id())?;
+        Ok(String::from_utf8_lossy(blob.content()).into_owned())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    /// The commit these changes were computed against
+    previous: Option<String>,
+    /// The board's `config.yml` blob at this commit
+    config: Option<String>,
+    changes: Vec<Change>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    /// Path of the board the changes were computed for
+    board: String,
+    entries: HashMap<String, CacheEntry>,
+}
+
+/// Changes per commit OID, stored in the git directory so repeated walks
+/// over the same history don't need to diff trees again.
+struct HistoryCache {
+    path: PathBuf,
+    file: CacheFile,
+    dirty: bool,
+    /// Commits whose entries this walk read or wrote
+    used: HashSet<String>,
+}
+
+impl HistoryCache {
+    fn load(repo: &Repository, board: &Path) -> Self {
+        let path = common_dir(repo).join("moth").join("history-cache.json");
+        let board = board.to_string_lossy().into_owned();
+
+        let file = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<CacheFile>(&content).ok())
+            .filter(|file| file.version == CACHE_VERSION && file.board == board)
+            .unwrap_or(CacheFile {
+                version: CACHE_VERSION,
+                board,
+                entries: HashMap::new(),
+            });
+
+        HistoryCache {
+            path,
+            file,
+            dirty: false,
+            used: HashSet::new(),
+        }
+    }
+
+    fn get(
+        &mut self,
+        commit: Oid,
+        previous: Option<Oid>,
+        config: Option<Oid>,
+    ) -> Option<Vec<Change>> {
+        let key = commit.to_string();
+        let entry = self.file.entries.get(&key)?;
+        if entry.previous != previous.map(|p| p.to_string())
+            || entry.config != config.map(|c| c.to_string())
+        {
+            return None;
+        }
+        let changes = entry.changes.clone();
+        self.used.insert(key);
+        Some(changes)
+    }
+
+    fn insert(
+        &mut self,
+        commit: Oid,
+        previous: Option<Oid>,
+        config: Option<Oid>,
+        changes: &[Change],
+    ) {
+        let key = commit.to_string();
+        self.file.entries.insert(
+            key.clone(),
+            CacheEntry {
+                previous: previous.map(|p| p.to_string()),
+                config: config.map(|c| c.to_string()),
+                changes: changes.to_vec(),
+            },
+        );
+        self.used.insert(key);
+        self.dirty = true;
+    }
+
+    /// Write the cache back, dropping entries for commits the repository no
+    /// longer has, and every entry this walk didn't use once it grows past
+    /// `MAX_CACHE_ENTRIES`.
+    fn save(&self, repo: &Repository) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let full = self.file.entries.len() > MAX_CACHE_ENTRIES;
+        let entries = self
+            .file
+            .entries
+            .iter()
+            .filter(|(key, _)| {
+                self.used.contains(*key)
+                    || (!full && Oid::from_str(key).is_ok_and(|oid| repo.find_commit(oid).is_ok()))
+            })
+            .map(|(key, entry)| (key.clone(), entry.clone()))
+            .collect();
+        let file = CacheFile {
+            version: self.file.version,
+            board: self.file.board.clone(),
+            entries,
+        };
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_string(&file)?)?;
+        Ok(())
+    }
+}
+
+mod oid_hex {
+    use git2::Oid;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(oid: &Oid, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&oid.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Oid, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        Oid::from_str(&hex).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Commits built from lists of empty files, for the commands' tests that
+/// walk history.
+#[cfg(all(test, feature = "cli"))]
+pub(crate) mod testing {
+    use git2::{IndexEntry, IndexTime, Oid, Repository, Signature, Time};
+
+    /// Commit a tree of empty `files` on `day` days after 2025-01-06.
+    pub fn commit(repo: &Repository, files: &[&str], parents: &[Oid], day: i64, head: bool) -> Oid {
+        let mut index = repo.index().unwrap();
+        index.clear().unwrap();
+        for path in files {
+            let entry = IndexEntry {
+                ctime: IndexTime::new(0, 0),
+                mtime: IndexTime::new(0, 0),
+                dev: 0,
+                ino: 0,
+                mode: 0o100644,
+                uid: 0,
+                gid: 0,
+                file_size: 0,
+                id: Oid::zero(),
+                flags: 0,
+                flags_extended: 0,
+                path: path.as_bytes().to_vec(),
+            };
+            index.add_frombuffer(&entry, b"").unwrap();
+        }
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let time = Time::new(1_736_150_400 + day * 86_400, 0);
+        let sig = Signature::new("Test", "test@test.com", &time).unwrap();
+        let parents: Vec<_> = parents
+            .iter()
+            .map(|p| repo.find_commit(*p).unwrap())
+            .collect();
+        let parents: Vec<_> = parents.iter().collect();
+        let update = head.then_some("HEAD");
+        repo.commit(update, &sig, &sig, "Board", &tree, &parents)
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn blob(content: &str) -> Oid {
+        Oid::hash_object(git2::ObjectType::Blob, content.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_story_filename() {
+        let key = parse_story_filename("rxj8y-med-report.md").unwrap();
+        assert_eq!(key.id, "rxj8y");
+        assert_eq!(key.severity, "med");
+        assert_eq!(key.slug, "report");
+    }
+
+    #[test]
+    fn test_parse_story_filename_with_hyphenated_slug() {
+        let key = parse_story_filename("abc123-high-fix-login-bug.md").unwrap();
+        assert_eq!(key.id, "abc123");
+        assert_eq!(key.severity, "high");
+        assert_eq!(key.slug, "fix-login-bug");
+    }
+
+    #[test]
+    fn test_detect_changes_created() {
+        let prev = HashMap::new();
+        let mut current = HashMap::new();
+
+        let story = StoryState {
+            key: StoryKey {
+                id: "abc123".to_string(),
+                severity: "high".to_string(),
+                slug: "test".to_string(),
+            },
+            column: "ready".to_string(),
+            order: None,
+            blob: blob("Test content"),
+        };
+
+        current.insert("abc123".to_string(), story);
+
+        let changes = detect_changes(&prev, &current);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(changes[0].event, ChangeEvent::Created));
+    }
+
+    #[test]
+    fn test_detect_changes_moved() {
+        let mut prev = HashMap::new();
+        let mut current = HashMap::new();
+
+        let story_prev = StoryState {
+            key: StoryKey {
+                id: "abc123".to_string(),
+                severity: "high".to_string(),
+                slug: "test".to_string(),
+            },
+            column: "ready".to_string(),
+            order: None,
+            blob: blob("Test content"),
+        };
+
+        let mut story_curr = story_prev.clone();
+        story_curr.column = "doing".to_string();
+
+        prev.insert("abc123".to_string(), story_prev);
+        current.insert("abc123".to_string(), story_curr);
+
+        let changes = detect_changes(&prev, &current);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(changes[0].event, ChangeEvent::Moved));
+    }
+
+    #[test]
+    fn test_detect_changes_edited() {
+        let mut prev = HashMap::new();
+        let mut current = HashMap::new();
+
+        let story_prev = StoryState {
+            key: StoryKey {
+                id: "abc123".to_string(),
+                severity: "high".to_string(),
+                slug: "test".to_string(),
+            },
+            column: "ready".to_string(),
+            order: None,
+            blob: blob("Test content"),
+        };
+
+        let mut story_curr = story_prev.clone();
+        story_curr.blob = blob("Updated content");
+
+        prev.insert("abc123".to_string(), story_prev);
+        current.insert("abc123".to_string(), story_curr);
+
+        let changes = detect_changes(&prev, &current);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(changes[0].event, ChangeEvent::Edited));
+    }
+
+    #[test]
+    fn test_detect_changes_deleted() {
+        let mut prev = HashMap::new();
+        let current = HashMap::new();
+
+        let story = StoryState {
+            key: StoryKey {
+                id: "abc123".to_string(),
+                severity: "high".to_string(),
+                slug: "test".to_string(),
+            },
+            column: "ready".to_string(),
+            order: None,
+            blob: blob("Test content"),
+        };
+
+        prev.insert("abc123".to_string(), story);
+
+        let changes = detect_changes(&prev, &current);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(changes[0].event, ChangeEvent::Deleted));
+    }
+
+    #[test]
+    fn test_change_event_as_str() {
+        assert_eq!(ChangeEvent::Created.as_str(), "created");
+        assert_eq!(ChangeEvent::Moved.as_str(), "moved");
+        assert_eq!(ChangeEvent::Edited.as_str(), "edited");
+        assert_eq!(ChangeEvent::Deleted.as_str(), "deleted");
+        assert_eq!(ChangeEvent::CodeCommit.as_str(), "code_commit");
+        assert_eq!(ChangeEvent::Renamed.as_str(), "renamed");
+        assert_eq!(ChangeEvent::SeverityChanged.as_str(), "severity_changed");
+    }
+
+    #[test]
+    fn test_parse_story_filename_with_order() {
+        let key = parse_story_filename("001-x7k2m-high-fix_login_bug.md").unwrap();
+        assert_eq!(key.id, "x7k2m");
+        assert_eq!(key.severity, "high");
+        assert_eq!(key.slug, "fix_login_bug");
+    }
+
+    #[test]
+    fn test_detect_changes_move_and_severity_in_one_commit() {
+        let story_prev = StoryState {
+            key: StoryKey {
+                id: "abc123".to_string(),
+                severity: "med".to_string(),
+                slug: "test".to_string(),
+            },
+            column: "ready".to_string(),
+            order: None,
+            blob: blob("Test content"),
+        };
+
+        let mut story_curr = story_prev.clone();
+        story_curr.column = "doing".to_string();
+        story_curr.key.severity = "crit".to_string();
+
+        let prev = HashMap::from([("abc123".to_string(), story_prev)]);
+        let current = HashMap::from([("abc123".to_string(), story_curr)]);
+
+        let changes = detect_changes(&prev, &current);
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(changes[0].event, ChangeEvent::Moved));
+        assert_eq!(changes[0].previous_column(), Some("ready"));
+        assert!(matches!(changes[1].event, ChangeEvent::SeverityChanged));
+        assert_eq!(changes[1].old_value().as_deref(), Some("med"));
+        assert_eq!(changes[1].new_value().as_deref(), Some("crit"));
+        assert_eq!(changes[1].story.column, "doing");
+    }
+
+    #[test]
+    fn test_parse_story_filename_invalid() {
+        // Too few parts
+        assert!(parse_story_filename("invalid.md").is_none());
+        assert!(parse_story_filename("only-two.md").is_none());
+        // No .md extension
+        assert!(parse_story_filename("abc-med-slug").is_none());
+    }
+
+    #[test]
+    fn test_detect_changes_severity_edit() {
+        let mut prev = HashMap::new();
+        let mut current = HashMap::new();
+
+        let story_prev = StoryState {
+            key: StoryKey {
+                id: "abc123".to_string(),
+                severity: "med".to_string(),
+                slug: "test".to_string(),
+            },
+            column: "ready".to_string(),
+            order: None,
+            blob: blob("Test content"),
+        };
+
+        let mut story_curr = story_prev.clone();
+        story_curr.key.severity = "high".to_string();
+
+        prev.insert("abc123".to_string(), story_prev);
+        current.insert("abc123".to_string(), story_curr);
+
+        let changes = detect_changes(&prev, &current);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(changes[0].event, ChangeEvent::SeverityChanged));
+        assert_eq!(changes[0].old_value().as_deref(), Some("med"));
+        assert_eq!(changes[0].new_value().as_deref(), Some("high"));
+    }
+
+    #[test]
+    fn test_detect_changes_slug_edit() {
+        let mut prev = HashMap::new();
+        let mut current = HashMap::new();
+
+        let story_prev = StoryState {
+            key: StoryKey {
+                id: "abc123".to_string(),
+                severity: "med".to_string(),
+                slug: "old_slug".to_string(),
+            },
+            column: "ready".to_string(),
+            order: None,
+            blob: blob("Test content"),
+        };
+
+        let mut story_curr = story_prev.clone();
+        story_curr.key.slug = "new_slug".to_string();
+
+        prev.insert("abc123".to_string(), story_prev);
+        current.insert("abc123".to_string(), story_curr);
+
+        let changes = detect_changes(&prev, &current);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(changes[0].event, ChangeEvent::Renamed));
+        assert_eq!(changes[0].old_value().as_deref(), Some("Old Slug"));
+        assert_eq!(changes[0].new_value().as_deref(), Some("New Slug"));
+    }
+
+    #[test]
+    fn test_detect_changes_no_change() {
+        let mut prev = HashMap::new();
+        let mut current = HashMap::new();
+
+        let story = StoryState {
+            key: StoryKey {
+                id: "abc123".to_string(),
+                severity: "med".to_string(),
+                slug: "test".to_string(),
+            },
+            column: "ready".to_string(),
+            order: None,
+            blob: blob("Test content"),
+        };
+
+        prev.insert("abc123".to_string(), story.clone());
+        current.insert("abc123".to_string(), story);
+
+        let changes = detect_changes(&prev, &current);
+        assert_eq!(changes.len(), 0);
+    }
+
+    #[test]
+    fn test_detect_changes_multiple() {
+        let mut prev = HashMap::new();
+        let mut current = HashMap::new();
+
+        // Story 1: will be deleted
+        let story1 = StoryState {
+            key: StoryKey {
+                id: "aaa111".to_string(),
+                severity: "low".to_string(),
+                slug: "deleted".to_string(),
+            },
+            column: "ready".to_string(),
+            order: None,
+            blob: blob("Will be deleted"),
+        };
+        prev.insert("aaa111".to_string(), story1);
+
+        // Story 2: will be moved
+        let story2_prev = StoryState {
+            key: StoryKey {
+                id: "bbb222".to_string(),
+                severity: "med".to_string(),
+                slug: "moved".to_string(),
+            },
+            column: "ready".to_string(),
+            order: None,
+            blob: blob("Will be moved"),
+        };
+        let mut story2_curr = story2_prev.clone();
+        story2_curr.column = "doing".to_string();
+        prev.insert("bbb222".to_string(), story2_prev);
+        current.insert("bbb222".to_string(), story2_curr);
+
+        // Story 3: will be created
+        let story3 = StoryState {
+            key: StoryKey {
+                id: "ccc333".to_string(),
+                severity: "high".to_string(),
+                slug: "created".to_string(),
+            },
+            column: "ready".to_string(),
+            order: None,
+            blob: blob("Newly created"),
+        };
+        current.insert("ccc333".to_string(), story3);
+
+        let changes = detect_changes(&prev, &current);
+        assert_eq!(changes.len(), 3);
+
+        // Changes are sorted by ID
+        assert!(matches!(changes[0].event, ChangeEvent::Deleted)); // aaa111
+        assert!(matches!(changes[1].event, ChangeEvent::Moved)); // bbb222
+        assert!(matches!(changes[2].event, ChangeEvent::Created)); // ccc333
+    }
+
+    struct TestRepo {
+        dir: TempDir,
+        repo: Repository,
+    }
+
+    impl TestRepo {
+        fn new() -> Self {
+            let dir = TempDir::new().unwrap();
+            let repo = Repository::init(dir.path()).unwrap();
+            TestRepo { dir, repo }
+        }
+
+        fn write(&self, path: &str, content: &str) {
+            let full = self.dir.path().join(path);
+            fs::create_dir_all(full.parent().unwrap()).unwrap();
+            fs::write(full, content).unwrap();
+        }
+
+        fn remove(&self, path: &str) {
+            fs::remove_file(self.dir.path().join(path)).unwrap();
+        }
+
+        fn commit(&self, message: &str) -> Oid {
+            let mut index = self.repo.index().unwrap();
+            index
+                .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+                .unwrap();
+            index.update_all(["*"].iter(), None).unwrap();
+            index.write().unwrap();
+            let tree = self.repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let sig = git2::Signature::now("Test", "test@test.com").unwrap();
+            let parents: Vec<Commit> = self
+                .repo
+                .head()
+                .ok()
+                .and_then(|h| h.peel_to_commit().ok())
+                .into_iter()
+                .collect();
+            let parent_refs: Vec<&Commit> = parents.iter().collect();
+            self.repo
+                .commit(Some("HEAD"), &sig, &sig, message, &tree, &parent_refs)
+                .unwrap()
+        }
+
+        fn walk(&self) -> Vec<Vec<Change>> {
+            let commits = get_commits(&self.repo, None, None, false).unwrap();
+            let mut walker = BoardWalker::new(&self.repo, Path::new(".moth"));
+            let changes = commits.iter().map(|c| walker.advance(c).unwrap()).collect();
+            walker.save_cache();
+            changes
+        }
+    }
+
+    #[test]
+    fn test_walker_tracks_moves_and_skips_unrelated_commits() {
+        let repo = TestRepo::new();
+        repo.write(".moth/ready/001-x7k2m-high-fix_login.md", "body");
+        repo.write(".moth/config.yml", "statuses: []");
+        repo.commit("Add story");
+        repo.write("src/main.rs", "fn main() {}");
+        repo.commit("Code only");
+        repo.remove(".moth/ready/001-x7k2m-high-fix_login.md");
+        repo.write(".moth/doing/x7k2m-high-fix_login.md", "body");
+        repo.commit("Start story");
+
+        let changes = repo.walk();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0].len(), 1);
+        assert_eq!(changes[0][0].event, ChangeEvent::Created);
+        assert!(changes[1].is_empty());
+        assert_eq!(changes[2].len(), 1);
+        assert_eq!(changes[2][0].event, ChangeEvent::Moved);
+        assert_eq!(changes[2][0].previous_column(), Some("ready"));
+        assert_eq!(changes[2][0].story.column, "doing");
+    }
+
+    #[test]
+    fn test_walker_reuses_cached_changes() {
+        let repo = TestRepo::new();
+        repo.write(".moth/ready/x7k2m-med-first.md", "one");
+        repo.commit("Add story");
+        repo.write(".moth/ready/x7k2m-med-first.md", "two");
+        repo.commit("Edit story");
+
+        let first = repo.walk();
+        let cache_path = common_dir(&repo.repo).join("moth/history-cache.json");
+        assert!(Path::new(&cache_path).exists());
+
+        // Add a commit after the cached ones, so the walker has to switch from
+        // cached changes back to diffing trees
+        repo.remove(".moth/ready/x7k2m-med-first.md");
+        repo.commit("Delete story");
+
+        let second = repo.walk();
+        assert_eq!(second.len(), 3);
+        assert_eq!(second[0][0].event, first[0][0].event);
+        assert_eq!(second[1][0].event, ChangeEvent::Edited);
+        assert_eq!(second[2].len(), 1);
+        assert_eq!(second[2][0].event, ChangeEvent::Deleted);
+    }
+
+    #[test]
+    fn test_walker_reads_config_changes_from_cached_commits() {
+        let repo = TestRepo::new();
+        let config = "statuses:\n  - name: backlog\n    dir: todo\n  - name: done\n    dir: done\n";
+        repo.write(".moth/config.yml", config);
+        repo.write(".moth/todo/abc12-med-first.md", "");
+        repo.commit("Add story");
+        repo.write(".moth/config.yml", &config.replace("backlog", "queue"));
+        repo.commit("Rename status");
+        repo.walk();
+
+        // Only the new commit misses the cache, and it has to use the
+        // statuses from the cached rename
+        repo.write(".moth/todo/def34-low-second.md", "");
+        repo.commit("Add another story");
+
+        let changes = repo.walk();
+        assert_eq!(changes[1][0].story.column, "queue");
+        assert_eq!(changes[2].len(), 1);
+        assert_eq!(changes[2][0].event, ChangeEvent::Created);
+        assert_eq!(changes[2][0].story.column, "queue");
+    }
+
+    #[test]
+    fn test_cache_drops_entries_for_missing_commits() {
+        let repo = TestRepo::new();
+        repo.write(".moth/ready/x7k2m-med-first.md", "");
+        repo.commit("Add story");
+        repo.walk();
+
+        let cache_path = common_dir(&repo.repo).join("moth/history-cache.json");
+        let mut file: CacheFile =
+            serde_json::from_str(&fs::read_to_string(&cache_path).unwrap()).unwrap();
+        let gone = "1111111111111111111111111111111111111111".to_string();
+        let entry = file.entries.values().next().unwrap().clone();
+        file.entries.insert(gone.clone(), entry);
+        fs::write(&cache_path, serde_json::to_string(&file).unwrap()).unwrap();
+
+        repo.remove(".moth/ready/x7k2m-med-first.md");
+        repo.commit("Delete story");
+        repo.walk();
+
+        let file: CacheFile =
+            serde_json::from_str(&fs::read_to_string(&cache_path).unwrap()).unwrap();
+        assert_eq!(file.entries.len(), 2);
+        assert!(!file.entries.contains_key(&gone));
+    }
+
+    #[test]
+    fn test_extract_stories_reads_tree() {
+        let repo = TestRepo::new();
+        repo.write(".moth/ready/002-abc12-low-later.md", "");
+        repo.write(".moth/done/def34-crit-shipped.md", "");
+        repo.write(".moth/ready/notes.txt", "");
+        let oid = repo.commit("Board");
+
+        let commit = repo.repo.find_commit(oid).unwrap();
+        let stories = extract_stories(&repo.repo, &commit, Path::new(".moth")).unwrap();
+        assert_eq!(stories.len(), 2);
+        assert_eq!(stories["abc12"].column, "ready");
+        assert_eq!(stories["def34"].key.severity, "crit");
+    }
+    #[test]
+    fn test_walker_maps_dirs_to_status_names() {
+        let repo = TestRepo::new();
+        let config = "statuses:\n  - name: backlog\n    dir: todo\n  - name: done\n    dir: done\n";
+        repo.write("docs/.moth/config.yml", config);
+        repo.write("docs/.moth/todo/abc12-med-nested.md", "");
+        repo.commit("Add story");
+        repo.write("docs/.moth/config.yml", &config.replace("backlog", "queue"));
+        repo.commit("Rename status");
+
+        let commits = get_commits(&repo.repo, None, None, false).unwrap();
+        let mut walker = BoardWalker::new(&repo.repo, Path::new("docs/.moth"));
+
+        let created = walker.advance(&commits[0]).unwrap();
+        assert_eq!(created[0].event, ChangeEvent::Created);
+        assert_eq!(created[0].story.column, "backlog");
+
+        // Renaming a status in config.yml moves its stories to the new name
+        let renamed = walker.advance(&commits[1]).unwrap();
+        assert_eq!(renamed.len(), 1);
+        assert_eq!(renamed[0].event, ChangeEvent::Moved);
+        assert_eq!(renamed[0].story.column, "queue");
+    }
+}
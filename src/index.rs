@@ -0,0 +1,259 @@
+//! `.moth/.index`: the issue files in each status directory, keyed by ID, so
+//! commands don't read every directory each time (slow with thousands of
+//! issues in `done` on a network filesystem). A directory is read again when
+//! its modification time changes, which adding, removing or renaming an
+//! issue file in it does. Only the holder of the board lock saves the index,
+//! through a temporary file, so readers never see half a file.
+
+use crate::backend::Backend;
+use crate::config::Config;
+use crate::error::{Result, warn};
+use crate::issue::Issue;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const INDEX_VERSION: u32 = 1;
+
+/// Directories changed this recently are read again next time, as another
+/// change within the filesystem's timestamp resolution wouldn't show.
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexFile {
+    version: u32,
+    /// By status name
+    dirs: BTreeMap<String, DirEntry>,
+    /// By issue ID; more than one entry is a duplicate
+    issues: BTreeMap<String, Vec<IssueEntry>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DirEntry {
+    dir: String,
+    /// Seconds and nanoseconds since the epoch, if recent enough to trust
+    modified: Option<(u64, u32)>,
+    /// Issue files whose names don't parse
+    unparseable: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IssueEntry {
+    status: String,
+    file: String,
+}
+
+pub struct Index<'c> {
+    config: &'c Config,
+    file: IndexFile,
+    /// Whether `file` differs from what was read
+    dirty: bool,
+}
+
+impl<'c> Index<'c> {
+    /// Read the index, bringing it up to date with any status directory
+    /// that changed since it was written.
+    pub fn load(config: &'c Config, backend: &dyn Backend) -> Result<Self> {
+        let mut file = backend
+            .read(&config.moth_dir.join(".index"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<IndexFile>(&content).ok())
+            .filter(|file| file.version == INDEX_VERSION)
+            .unwrap_or(IndexFile {
+                version: INDEX_VERSION,
+                ..Default::default()
+            });
+
+        let names: HashSet<&str> = config.statuses.iter().map(|s| s.name.as_str()).collect();
+        let mut dirty = file.dirs.keys().any(|name| !names.contains(name.as_str()));
+        file.dirs.retain(|name, _| names.contains(name.as_str()));
+
+        let now = SystemTime::now();
+        for status in &config.statuses {
+            let dir = config.status_dir(status);
+            let modified = backend
+                .modified(&dir)
+                .filter(|time| {
+                    now.duration_since(*time)
+                        .is_ok_and(|age| age >= RACY_WINDOW)
+                })
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|since| (since.as_secs(), since.subsec_nanos()));
+
+            let fresh = file.dirs.get(&status.name).is_some_and(|entry| {
+                entry.dir == status.dir && modified.is_some() && entry.modified == modified
+            });
+            if fresh {
+                continue;
+            }
+
+            let mut unparseable = Vec::new();
+            let mut found = Vec::new();
+            for path in backend.list(&dir)? {
+                let Some(name) = path.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+                    continue;
+                };
+                match Issue::from_path(&path, &status.name) {
+                    Ok(issue) => found.push((issue.id, name)),
+                    Err(_) => unparseable.push(name),
+                }
+            }
+
+            file.dirs.insert(
+                status.name.clone(),
+                DirEntry {
+                    dir: status.dir.clone(),
+                    modified,
+                    unparseable,
+                },
+            );
+            dirty = true;
+
+            for entries in file.issues.values_mut() {
+                entries.retain(|entry| entry.status != status.name);
+            }
+            for (id, name) in found {
+                file.issues.entry(id).or_default().push(IssueEntry {
+                    status: status.name.clone(),
+                    file: name,
+                });
+            }
+        }
+        file.issues.retain(|_, entries| !entries.is_empty());
+
+        Ok(Index {
+            config,
+            file,
+            dirty,
+        })
+    }
+
+    /// Write the index back if `load` changed it. Callers must hold the
+    /// board lock. Failures are only a warning, as the index is a cache.
+    pub fn save(&self, backend: &dyn Backend) {
+        // Without modification times the index would be read again in full
+        if !self.dirty
+            || self
+                .file
+                .dirs
+                .values()
+                .all(|entry| entry.modified.is_none())
+        {
+            return;
+        }
+
+        let path = self.config.moth_dir.join(".index");
+        let pending = self.config.moth_dir.join(".index.tmp");
+        let saved = serde_json::to_string(&self.file)
+            .map_err(Into::into)
+            .and_then(|content| backend.write(&pending, &content))
+            .and_then(|()| backend.rename(&pending, &path));
+        if let Err(e) = saved {
+            warn(format_args!("Failed to save index: {}", e));
+        }
+    }
+
+    /// IDs of all issues, sorted.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.file.issues.keys().map(String::as_str)
+    }
+
+    /// The issues in `status`.
+    pub fn status_issues(&self, status: &str) -> Vec<Issue> {
+        self.file
+            .issues
+            .values()
+            .flatten()
+            .filter(|entry| entry.status == status)
+            .filter_map(|entry| self.issue(entry))
+            .collect()
+    }
+
+    /// Issues whose ID starts with `partial_id`.
+    pub fn matching(&self, partial_id: &str) -> Vec<Issue> {
+        self.file
+            .issues
+            .range(partial_id.to_string()..)
+            .take_while(|(id, _)| id.starts_with(partial_id))
+            .flat_map(|(_, entries)| entries)
+            .filter_map(|entry| self.issue(entry))
+            .collect()
+    }
+
+    /// Issue files in `status` whose names don't parse.
+    pub fn unparseable(&self, status: &str) -> Vec<PathBuf> {
+        let (Some(entry), Some(config)) =
+            (self.file.dirs.get(status), self.config.get_status(status))
+        else {
+            return Vec::new();
+        };
+        let dir = self.config.status_dir(config);
+        entry
+            .unparseable
+            .iter()
+            .map(|name| dir.join(name))
+            .collect()
+    }
+
+    fn issue(&self, entry: &IssueEntry) -> Option<Issue> {
+        let status = self.config.get_status(&entry.status)?;
+        let path = self.config.status_dir(status).join(&entry.file);
+        Issue::from_path(&path, &entry.status).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::FsBackend;
+    use std::fs::{self, File};
+
+    #[test]
+    fn test_directories_are_read_again_when_changed() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let config = Config {
+            moth_dir: temp.path().to_path_buf(),
+            ..Config::default()
+        };
+        let ready = temp.path().join("ready");
+        for status in &config.statuses {
+            fs::create_dir(config.status_dir(status)).unwrap();
+        }
+        fs::write(ready.join("abc12-med-first.md"), "").unwrap();
+        fs::write(ready.join("not-an-issue.md"), "").unwrap();
+
+        let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+        let set_modified = |time| {
+            for status in &config.statuses {
+                let dir = File::open(config.status_dir(status)).unwrap();
+                dir.set_modified(time).unwrap();
+            }
+        };
+        set_modified(an_hour_ago);
+
+        let index = Index::load(&config, &FsBackend).unwrap();
+        assert_eq!(index.ids().collect::<Vec<_>>(), vec!["abc12"]);
+        assert_eq!(
+            index.unparseable("ready"),
+            vec![ready.join("not-an-issue.md")]
+        );
+        assert!(!temp.path().join(".index").exists());
+        index.save(&FsBackend);
+        assert!(temp.path().join(".index").exists());
+        assert!(!temp.path().join(".index.tmp").exists());
+
+        // An unchanged directory isn't read again
+        fs::write(ready.join("abd34-low-second.md"), "").unwrap();
+        set_modified(an_hour_ago);
+        let index = Index::load(&config, &FsBackend).unwrap();
+        assert_eq!(index.ids().count(), 1);
+
+        set_modified(an_hour_ago + Duration::from_secs(60));
+        let index = Index::load(&config, &FsBackend).unwrap();
+        assert_eq!(index.matching("ab").len(), 2);
+        assert_eq!(index.matching("abd")[0].title(), "Second");
+        assert_eq!(index.status_issues("ready").len(), 2);
+        assert!(index.status_issues("doing").is_empty());
+    }
+}
@@ -1,4 +1,5 @@
-use anyhow::{Result, anyhow};
+use crate::config::{Config, ReferenceFormat};
+use crate::error::{Error, Result};
 use rand::Rng;
 use std::fmt;
 use std::path::{Path, PathBuf};
@@ -13,7 +14,7 @@ pub enum Severity {
 }
 
 impl FromStr for Severity {
-    type Err = anyhow::Error;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self> {
         match s {
@@ -21,10 +22,10 @@ impl FromStr for Severity {
             "high" => Ok(Severity::High),
             "med" => Ok(Severity::Med),
             "low" => Ok(Severity::Low),
-            _ => Err(anyhow!(
+            _ => Err(Error::Invalid(format!(
                 "Invalid severity: {}. Must be one of: crit, high, med, low",
                 s
-            )),
+            ))),
         }
     }
 }
@@ -61,12 +62,13 @@ impl Issue {
         let filename = path
             .file_stem()
             .and_then(|s| s.to_str())
-            .ok_or_else(|| anyhow!("Invalid filename"))?;
+            .ok_or_else(|| Error::Invalid("Invalid filename".to_string()))?;
 
         let parts: Vec<&str> = filename.split('-').collect();
         if parts.len() < 3 {
-            return Err(anyhow!(
-                "Invalid filename format. Expected: [{{order}}-]{{id}}-{{severity}}-{{slug}}.md"
+            return Err(Error::Invalid(
+                "Invalid filename format. Expected: [{order}-]{id}-{severity}-{slug}.md"
+                    .to_string(),
             ));
         }
 
@@ -78,8 +80,9 @@ impl Issue {
         };
 
         if parts.len() < id_idx + 3 {
-            return Err(anyhow!(
-                "Invalid filename format. Expected: [{{order}}-]{{id}}-{{severity}}-{{slug}}.md"
+            return Err(Error::Invalid(
+                "Invalid filename format. Expected: [{order}-]{id}-{severity}-{slug}.md"
+                    .to_string(),
             ));
         }
 
@@ -115,20 +118,68 @@ impl Issue {
     }
 
     pub fn title(&self) -> String {
-        // Support both underscore (new) and hyphen (old) separators
-        let separator = if self.slug.contains('_') { '_' } else { '-' };
-        self.slug
-            .split(separator)
-            .map(|word| {
-                let mut chars = word.chars();
-                match chars.next() {
-                    None => String::new(),
-                    Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+        slug_to_title(&self.slug)
+    }
+
+    /// Branch name for the issue from a pattern such as `{id}-{slug}`. Words
+    /// in the slug are joined with hyphens.
+    pub fn branch_name(&self, pattern: &str) -> String {
+        pattern
+            .replace("{id}", &self.id)
+            .replace("{severity}", self.severity.as_str())
+            .replace("{slug}", &self.slug.replace('_', "-"))
+    }
+}
+
+/// The branch recorded in an issue's content by a `Branch: name` line.
+pub fn recorded_branch(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .rev()
+        .find_map(|line| line.strip_prefix("Branch: "))
+        .map(str::trim)
+        .filter(|branch| !branch.is_empty())
+}
+
+/// Issue content with its `Branch:` line set to `branch`, appended if the
+/// issue doesn't record one yet.
+pub fn record_branch(content: &str, branch: &str) -> String {
+    let line = format!("Branch: {}", branch);
+    if recorded_branch(content).is_some() {
+        let lines: Vec<&str> = content
+            .lines()
+            .map(|l| {
+                if l.starts_with("Branch: ") {
+                    line.as_str()
+                } else {
+                    l
                 }
             })
-            .collect::<Vec<_>>()
-            .join(" ")
+            .collect();
+        return lines.join("\n") + "\n";
+    }
+
+    let mut content = content.trim_end().to_string();
+    if !content.is_empty() {
+        content.push_str("\n\n");
     }
+    content + &line + "\n"
+}
+
+/// Turn a filename slug back into a display title.
+pub fn slug_to_title(slug: &str) -> String {
+    // Support both underscore (new) and hyphen (old) separators
+    let separator = if slug.contains('_') { '_' } else { '-' };
+    slug.split(separator)
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                None => String::new(),
+                Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 pub fn generate_id(length: usize) -> String {
@@ -149,44 +200,289 @@ pub fn generate_id(length: usize) -> String {
     format!("{}{}", first, rest)
 }
 
-/// Extract issue ID from a commit message prefix.
-/// Returns the issue ID if the message starts with [id] format, None otherwise.
-///
-/// # Format
-/// Messages must start with `[id]` where id is lowercase alphanumeric.
-/// Example: "[abc12] Fix the login bug" -> Some("abc12")
-pub fn extract_issue_id(message: &str) -> Option<String> {
-    let first_line = message.lines().next()?;
+/// An issue referenced from a commit message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub id: String,
+    /// The trailer key, for references made through a trailer.
+    pub trailer: Option<String>,
+}
 
-    if !first_line.starts_with('[') {
-        return None;
+/// Issue references in a commit message, in the order they appear and in
+/// every format enabled in `config`.
+pub fn extract_references(message: &str, config: &Config) -> Vec<Reference> {
+    let mut refs: Vec<Reference> = Vec::new();
+    let first_line = message.lines().next().unwrap_or("");
+    let length = config.id_length;
+
+    for format in &config.references.formats {
+        let found: Vec<Reference> = match format {
+            ReferenceFormat::Bracket => bracket_prefix(first_line, length)
+                .map(|(ids, _)| plain_refs(ids))
+                .unwrap_or_default(),
+            ReferenceFormat::Scope => conventional_scope(first_line, length)
+                .map(|(ids, _, _)| plain_refs(ids))
+                .unwrap_or_default(),
+            ReferenceFormat::Trailer => trailer_refs(message, &config.references.trailers, length),
+            ReferenceFormat::Hash => plain_refs(hash_refs(message, length)),
+        };
+
+        for reference in found {
+            if !refs.contains(&reference) {
+                refs.push(reference);
+            }
+        }
     }
 
-    let end_bracket = first_line.find(']')?;
-    let id = &first_line[1..end_bracket];
+    refs
+}
+
+/// Unique IDs referenced by a commit message, in order.
+pub fn referenced_ids(message: &str, config: &Config) -> Vec<String> {
+    let mut ids: Vec<String> = Vec::new();
+    for reference in extract_references(message, config) {
+        if !ids.contains(&reference.id) {
+            ids.push(reference.id);
+        }
+    }
+    ids
+}
 
-    // Validate: must be non-empty, lowercase alphanumeric only
-    if id.is_empty()
-        || !id
+/// Unique IDs a commit message closes with one of the `closes` trailers,
+/// e.g. `Closes: abc12`.
+pub fn closing_ids(message: &str, config: &Config) -> Vec<String> {
+    let mut ids: Vec<String> = Vec::new();
+    for reference in trailer_refs(message, &config.references.closes, config.id_length) {
+        if !ids.contains(&reference.id) {
+            ids.push(reference.id);
+        }
+    }
+    ids
+}
+
+/// The subject line of a commit message without a leading bracket or scope
+/// reference, e.g. `feat(abc12): Fix` becomes `feat: Fix`.
+pub fn strip_reference(message: &str, config: &Config) -> String {
+    let first_line = message.lines().next().unwrap_or("");
+
+    for format in &config.references.formats {
+        match format {
+            ReferenceFormat::Bracket => {
+                if let Some((_, rest)) = bracket_prefix(first_line, config.id_length) {
+                    return rest.trim_start().to_string();
+                }
+            }
+            ReferenceFormat::Scope => {
+                if let Some((_, kind, rest)) = conventional_scope(first_line, config.id_length) {
+                    return format!("{}: {}", kind, rest.trim_start());
+                }
+            }
+            ReferenceFormat::Trailer | ReferenceFormat::Hash => {}
+        }
+    }
+
+    first_line.to_string()
+}
+
+/// Add a reference to `id` using the first configured format, unless the
+/// message already references it. The result always references `id` under
+/// `config`, so adding the same reference again changes nothing.
+/// A subject without a commit type gets `chore` for the scope format.
+pub fn add_reference(message: &str, id: &str, config: &Config) -> String {
+    if referenced_ids(message, config).iter().any(|r| r == id) {
+        return message.to_string();
+    }
+
+    let (first_line, rest) = match message.split_once('\n') {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (message, None),
+    };
+    let with_rest = |first: String| match rest {
+        Some(rest) => format!("{}\n{}", first, rest),
+        None => first,
+    };
+
+    match config.references.formats.first() {
+        Some(ReferenceFormat::Scope) => match first_line.split_once(": ") {
+            Some((kind, subject)) if is_commit_type(kind) => {
+                with_rest(format!("{}({}): {}", kind, id, subject))
+            }
+            _ => with_rest(format!("chore({}): {}", id, first_line)),
+        },
+        Some(ReferenceFormat::Trailer) => {
+            let key = config
+                .references
+                .trailers
+                .first()
+                .map_or("Refs", String::as_str);
+            format!("{}\n\n{}: {}\n", message.trim_end(), key, id)
+        }
+        Some(ReferenceFormat::Hash) => with_rest(format!("{} #{}", first_line, id)),
+        Some(ReferenceFormat::Bracket) | None => with_rest(format!("[{}] {}", id, first_line)),
+    }
+}
+
+/// The issue IDs a commit message references, or none when the commit is
+/// exempt: a merge or empty commit (per `commit_msg`), or a message with
+/// one of the exempt prefixes. Fails if the message doesn't reference any
+/// issue.
+pub fn required_ids(
+    message: &str,
+    config: &Config,
+    is_merge: bool,
+    is_empty: bool,
+) -> Result<Vec<String>> {
+    let exemptions = &config.commit_msg;
+
+    // Git drops comment lines from the final message
+    let message = message
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .skip_while(|line| line.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if (is_merge && exemptions.allow_merge)
+        || (is_empty && exemptions.allow_empty)
+        || exemptions
+            .exempt_prefixes
+            .iter()
+            .any(|prefix| message.starts_with(prefix.as_str()))
+    {
+        return Ok(Vec::new());
+    }
+
+    let ids = referenced_ids(&message, config);
+    if ids.is_empty() {
+        return Err(Error::Invalid(format!(
+            "Commit message must reference an issue ID, e.g. \"{}\"",
+            example_message(config).trim_end()
+        )));
+    }
+
+    Ok(ids)
+}
+
+fn example_message(config: &Config) -> String {
+    let example = match config.references.formats.first() {
+        Some(ReferenceFormat::Scope) => "fix: Fix the bug",
+        _ => "Fix the bug",
+    };
+    let id: String = "abc1234567".chars().take(config.id_length).collect();
+    add_reference(example, &id, config).replace('\n', " ")
+}
+
+/// Whether `id` has the shape of the IDs `generate_id` makes: `length`
+/// characters, a lowercase letter followed by lowercase letters and digits.
+/// Keeps scopes like `feat(ui):` and tags like `[wip]` from passing as
+/// references.
+fn is_issue_id(id: &str, length: usize) -> bool {
+    id.len() == length
+        && id.starts_with(|c: char| c.is_ascii_lowercase())
+        && id
             .chars()
             .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
-    {
+}
+
+fn is_commit_type(kind: &str) -> bool {
+    !kind.is_empty() && kind.chars().all(|c| c.is_ascii_lowercase())
+}
+
+fn plain_refs(ids: Vec<String>) -> Vec<Reference> {
+    ids.into_iter()
+        .map(|id| Reference { id, trailer: None })
+        .collect()
+}
+
+/// Split a list like `abc12, def34` into IDs, or `None` if any isn't valid.
+fn id_list(list: &str, length: usize) -> Option<Vec<String>> {
+    let ids: Vec<String> = list
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .map(|id| id.trim_start_matches('#'))
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    (!ids.is_empty() && ids.iter().all(|id| is_issue_id(id, length))).then_some(ids)
+}
+
+/// `[abc12] Fix`, `[abc12, def34] Fix` or `[abc12][def34] Fix`.
+fn bracket_prefix(line: &str, length: usize) -> Option<(Vec<String>, &str)> {
+    let mut ids = Vec::new();
+    let mut rest = line;
+
+    while let Some(inner) = rest.strip_prefix('[') {
+        let end = inner.find(']')?;
+        ids.extend(id_list(&inner[..end], length)?);
+        rest = &inner[end + 1..];
+    }
+
+    (!ids.is_empty()).then_some((ids, rest))
+}
+
+/// `feat(abc12): Fix` or `fix(abc12,def34)!: Fix`, returning the IDs, the
+/// commit type and the subject.
+fn conventional_scope(line: &str, length: usize) -> Option<(Vec<String>, &str, &str)> {
+    let open = line.find('(')?;
+    let kind = &line[..open];
+    if !is_commit_type(kind) {
         return None;
     }
 
-    Some(id.to_string())
+    let close = open + line[open..].find(')')?;
+    let ids = id_list(&line[open + 1..close], length)?;
+    let after = line[close + 1..]
+        .strip_prefix('!')
+        .unwrap_or(&line[close + 1..]);
+    let subject = after.strip_prefix(':')?;
+
+    Some((ids, kind, subject))
 }
 
-/// Extract issue ID and remaining message from a commit message.
-/// Returns (id, message_without_prefix) if valid prefix found.
-pub fn parse_issue_prefix(message: &str) -> Option<(String, String)> {
-    let first_line = message.lines().next()?;
-    let id = extract_issue_id(message)?;
+/// `Refs: abc12` style lines after the subject, matching configured keys
+/// case-insensitively.
+fn trailer_refs(message: &str, keys: &[String], length: usize) -> Vec<Reference> {
+    let mut refs = Vec::new();
+
+    for line in message.lines().skip(1) {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let Some(key) = keys.iter().find(|k| k.eq_ignore_ascii_case(key.trim())) else {
+            continue;
+        };
+
+        for id in id_list(value, length).unwrap_or_default() {
+            refs.push(Reference {
+                id,
+                trailer: Some(key.clone()),
+            });
+        }
+    }
+
+    refs
+}
 
-    let prefix_end = first_line.find(']')? + 1;
-    let rest = first_line[prefix_end..].trim_start().to_string();
+/// `#abc12` anywhere in the message. IDs start with a letter, so numeric
+/// references like `#123` are left alone.
+fn hash_refs(message: &str, length: usize) -> Vec<String> {
+    let mut ids = Vec::new();
+    let mut prev = ' ';
+
+    for (i, c) in message.char_indices() {
+        if c == '#' && !prev.is_ascii_alphanumeric() {
+            let word: String = message[i + 1..]
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric())
+                .collect();
+            if is_issue_id(&word, length) {
+                ids.push(word);
+            }
+        }
+        prev = c;
+    }
 
-    Some((id, rest))
+    ids
 }
 
 #[cfg(test)]
@@ -251,76 +547,259 @@ mod tests {
         );
     }
 
+    fn references(formats: &[ReferenceFormat]) -> Config {
+        let mut config = Config::default();
+        config.references.formats = formats.to_vec();
+        config
+    }
+
     #[test]
-    fn test_extract_issue_id_valid() {
-        assert_eq!(
-            extract_issue_id("[abc12] Fix bug"),
-            Some("abc12".to_string())
-        );
+    fn test_extract_references_default_is_bracket_only() {
+        let config = Config::default();
+        assert_eq!(referenced_ids("[abc12] Fix", &config), vec!["abc12"]);
+        assert!(referenced_ids("feat(abc12): Fix", &config).is_empty());
+        assert!(referenced_ids("Fix\n\nRefs: abc12", &config).is_empty());
     }
 
     #[test]
-    fn test_extract_issue_id_no_space() {
+    fn test_extract_references_multiple_brackets() {
+        let config = Config::default();
         assert_eq!(
-            extract_issue_id("[abc12]Fix bug"),
-            Some("abc12".to_string())
+            referenced_ids("[abc12, def34] Fix", &config),
+            vec!["abc12", "def34"]
         );
+        assert_eq!(
+            referenced_ids("[abc12][def34] Fix", &config),
+            vec!["abc12", "def34"]
+        );
+        assert!(referenced_ids("[WIP] Fix", &config).is_empty());
+        assert!(referenced_ids("[wip] Fix", &config).is_empty());
+        assert!(referenced_ids("[1bc12] Fix", &config).is_empty());
     }
 
     #[test]
-    fn test_extract_issue_id_no_prefix() {
-        assert_eq!(extract_issue_id("No prefix here"), None);
+    fn test_extract_references_use_id_length() {
+        let config = Config {
+            id_length: 6,
+            ..Config::default()
+        };
+        assert!(referenced_ids("[abc12] Fix", &config).is_empty());
+        assert_eq!(referenced_ids("[abc123] Fix", &config), vec!["abc123"]);
     }
 
     #[test]
-    fn test_extract_issue_id_uppercase_rejected() {
-        assert_eq!(extract_issue_id("[ABC12] Upper"), None);
+    fn test_extract_references_conventional_scope() {
+        let config = references(&[ReferenceFormat::Scope]);
+        assert_eq!(referenced_ids("feat(abc12): Fix", &config), vec!["abc12"]);
+        assert_eq!(
+            referenced_ids("fix(abc12,def34)!: Fix", &config),
+            vec!["abc12", "def34"]
+        );
+        assert!(referenced_ids("feat: Fix", &config).is_empty());
+        assert!(referenced_ids("Feat(abc12): Fix", &config).is_empty());
+        assert!(referenced_ids("feat(ui): Fix", &config).is_empty());
+        assert_eq!(strip_reference("feat(ui): Fix", &config), "feat(ui): Fix");
     }
 
     #[test]
-    fn test_extract_issue_id_special_chars_rejected() {
-        assert_eq!(extract_issue_id("[abc-12] Hyphen"), None);
+    fn test_extract_references_trailers() {
+        let config = references(&[ReferenceFormat::Trailer]);
+        let refs = extract_references("Fix it\n\nBody\n\nRefs: abc12\ncloses: #def34\n", &config);
+        assert_eq!(
+            refs,
+            vec![
+                Reference {
+                    id: "abc12".to_string(),
+                    trailer: Some("Refs".to_string())
+                },
+                Reference {
+                    id: "def34".to_string(),
+                    trailer: Some("Closes".to_string())
+                },
+            ]
+        );
+        // Trailers are never on the subject line
+        assert!(referenced_ids("Refs: abc12", &config).is_empty());
     }
 
     #[test]
-    fn test_extract_issue_id_empty_brackets() {
-        assert_eq!(extract_issue_id("[] Empty"), None);
+    fn test_branch_name() {
+        let issue =
+            Issue::from_path(Path::new("ready/x7k2m-high-fix_login_bug.md"), "ready").unwrap();
+        assert_eq!(issue.branch_name("{id}-{slug}"), "x7k2m-fix-login-bug");
+        assert_eq!(issue.branch_name("{severity}/{id}"), "high/x7k2m");
     }
 
     #[test]
-    fn test_extract_issue_id_multiline() {
+    fn test_record_branch() {
+        let content = record_branch("", "x7k2m-fix");
+        assert_eq!(content, "Branch: x7k2m-fix\n");
+        assert_eq!(recorded_branch(&content), Some("x7k2m-fix"));
+
+        let content = record_branch("Some notes\n", "x7k2m-fix");
+        assert_eq!(content, "Some notes\n\nBranch: x7k2m-fix\n");
+
+        let content = record_branch(&content, "x7k2m-other");
+        assert_eq!(content, "Some notes\n\nBranch: x7k2m-other\n");
+        assert_eq!(recorded_branch("Some notes"), None);
+    }
+
+    #[test]
+    fn test_closing_ids() {
+        // Closing trailers work even when trailers aren't a reference format
+        let config = Config::default();
         assert_eq!(
-            extract_issue_id("[abc12] First line\nSecond line"),
-            Some("abc12".to_string())
+            closing_ids("[abc12] Fix\n\nFixes: abc12, def34\nRefs: ghi56\n", &config),
+            vec!["abc12", "def34"]
         );
+        assert!(closing_ids("Closes: abc12", &config).is_empty());
     }
 
     #[test]
-    fn test_parse_issue_prefix_valid() {
+    fn test_extract_references_hash() {
+        let config = references(&[ReferenceFormat::Hash]);
         assert_eq!(
-            parse_issue_prefix("[abc12] Fix bug"),
-            Some(("abc12".to_string(), "Fix bug".to_string()))
+            referenced_ids("Fix #abc12 and #def34\n\nSee #123", &config),
+            vec!["abc12", "def34"]
         );
+        assert!(referenced_ids("a#abc12", &config).is_empty());
     }
 
     #[test]
-    fn test_parse_issue_prefix_no_message() {
+    fn test_extract_references_combined_formats_dedupe() {
+        let config = references(&[
+            ReferenceFormat::Bracket,
+            ReferenceFormat::Trailer,
+            ReferenceFormat::Hash,
+        ]);
         assert_eq!(
-            parse_issue_prefix("[abc12]"),
-            Some(("abc12".to_string(), "".to_string()))
+            referenced_ids("[abc12] Fix #abc12\n\nRefs: def34", &config),
+            vec!["abc12", "def34"]
         );
     }
 
     #[test]
-    fn test_parse_issue_prefix_extra_spaces() {
+    fn test_strip_reference() {
+        let config = references(&[ReferenceFormat::Bracket, ReferenceFormat::Scope]);
+        assert_eq!(strip_reference("[abc12]  Fix\nBody", &config), "Fix");
+        assert_eq!(strip_reference("feat(abc12): Fix", &config), "feat: Fix");
+        assert_eq!(strip_reference("Plain message", &config), "Plain message");
+    }
+
+    #[test]
+    fn test_add_reference() {
+        let bracket = Config::default();
         assert_eq!(
-            parse_issue_prefix("[abc12]   Lots of spaces"),
-            Some(("abc12".to_string(), "Lots of spaces".to_string()))
+            add_reference("Fix\nBody", "abc12", &bracket),
+            "[abc12] Fix\nBody"
         );
+        assert_eq!(
+            add_reference("[abc12] Fix", "abc12", &bracket),
+            "[abc12] Fix"
+        );
+
+        let scope = references(&[ReferenceFormat::Scope]);
+        assert_eq!(
+            add_reference("feat: Fix", "abc12", &scope),
+            "feat(abc12): Fix"
+        );
+        assert_eq!(add_reference("Fix", "abc12", &scope), "chore(abc12): Fix");
+        assert_eq!(
+            add_reference("chore(abc12): Fix", "abc12", &scope),
+            "chore(abc12): Fix"
+        );
+
+        let trailer = references(&[ReferenceFormat::Trailer]);
+        assert_eq!(
+            add_reference("Fix\n", "abc12", &trailer),
+            "Fix\n\nRefs: abc12\n"
+        );
+
+        let hash = references(&[ReferenceFormat::Hash]);
+        assert_eq!(add_reference("Fix", "abc12", &hash), "Fix #abc12");
     }
 
     #[test]
-    fn test_parse_issue_prefix_no_prefix() {
-        assert_eq!(parse_issue_prefix("No prefix"), None);
+    fn test_add_reference_round_trips() {
+        let formats = [
+            ReferenceFormat::Bracket,
+            ReferenceFormat::Scope,
+            ReferenceFormat::Trailer,
+            ReferenceFormat::Hash,
+        ];
+        let messages = [
+            "Fix login",
+            "feat: Fix login",
+            "Fix login\n\nBody\n",
+            "[xyz99] Fix",
+        ];
+        for format in formats {
+            let config = references(&[format]);
+            for message in messages {
+                let tagged = add_reference(message, "abc12", &config);
+                assert!(
+                    referenced_ids(&tagged, &config).contains(&"abc12".to_string()),
+                    "{:?} doesn't reference abc12 under {:?}",
+                    tagged,
+                    format
+                );
+                assert_eq!(add_reference(&tagged, "abc12", &config), tagged);
+            }
+        }
+    }
+
+    #[test]
+    fn test_required_ids_from_prefix() {
+        let config = Config::default();
+        let ids = required_ids("[abc12] Fix the bug\n", &config, false, false).unwrap();
+        assert_eq!(ids, vec!["abc12"]);
+
+        let commented = "# Please enter the commit message\n\n[abc12, def34] Fix\n";
+        let ids = required_ids(commented, &config, false, false).unwrap();
+        assert_eq!(ids, vec!["abc12", "def34"]);
+    }
+
+    #[test]
+    fn test_required_ids_rejects_missing_reference() {
+        let mut config = Config::default();
+        let err = required_ids("Fix the bug", &config, false, false).unwrap_err();
+        assert!(err.to_string().contains("e.g. \"[abc12] Fix the bug\""));
+        assert!(required_ids("[wip] Fix the bug", &config, false, false).is_err());
+
+        // The example follows the configured format
+        config.references.formats = vec![ReferenceFormat::Scope];
+        let err = required_ids("Fix the bug", &config, false, false).unwrap_err();
+        assert!(err.to_string().contains("e.g. \"fix(abc12): Fix the bug\""));
+        assert!(required_ids("feat(ui): Fix the bug", &config, false, false).is_err());
+    }
+
+    #[test]
+    fn test_required_ids_with_trailers() {
+        let mut config = Config::default();
+        config.references.formats = vec![ReferenceFormat::Trailer];
+        let ids = required_ids("Fix\n\nRefs: abc12\n", &config, false, false).unwrap();
+        assert_eq!(ids, vec!["abc12"]);
+    }
+
+    #[test]
+    fn test_required_ids_exemptions() {
+        let mut config = Config::default();
+        let exempt = |config: &Config, message: &str, is_merge: bool, is_empty: bool| {
+            required_ids(message, config, is_merge, is_empty)
+                .map(|ids| ids.is_empty())
+                .unwrap_or(false)
+        };
+
+        assert!(exempt(&config, "Merge branch 'x'", true, false));
+        assert!(exempt(&config, "fixup! [abc12] Fix", false, false));
+        assert!(!exempt(&config, "Empty", false, true));
+        assert!(!exempt(&config, "chore: bump deps", false, false));
+
+        config.commit_msg.allow_merge = false;
+        config.commit_msg.allow_empty = true;
+        config.commit_msg.exempt_prefixes.push("chore:".to_string());
+        assert!(!exempt(&config, "Merge branch 'x'", true, false));
+        assert!(exempt(&config, "Empty", false, true));
+        assert!(exempt(&config, "chore: bump deps", false, false));
     }
 }
@@ -1,4 +1,14 @@
+pub mod backend;
+pub mod board_ref;
+#[cfg(feature = "cli")]
 pub mod cmd;
 pub mod config;
+pub mod doctor;
+pub mod error;
+pub mod git;
+pub mod history;
+pub mod index;
 pub mod issue;
 pub mod store;
+
+pub use error::{Error, Result};
@@ -3,6 +3,7 @@ use clap_complete::{Shell, generate};
 use moth::cmd;
 use std::env::args;
 use std::io;
+use std::path::Path;
 use std::process;
 
 #[derive(Parser)]
@@ -24,7 +25,10 @@ struct Cli {
 #[derive(Subcommand, Debug)]
 enum Commands {
     #[command(about = "Initialize .moth/ directory")]
-    Init,
+    Init {
+        #[arg(long, help = "Register the board merge driver with git")]
+        git: bool,
+    },
 
     #[command(about = "Create a new issue")]
     New {
@@ -54,12 +58,26 @@ enum Commands {
 
         #[arg(short = 's', long, help = "Filter by severity (crit, high, med, low)")]
         severity: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "REV",
+            help = "List the board as it was at a git revision"
+        )]
+        at: Option<String>,
     },
 
     #[command(about = "Show issue details")]
     Show {
         #[arg(help = "Issue ID (full or partial)")]
         id: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "REV",
+            help = "Show the issue as it was at a git revision"
+        )]
+        at: Option<String>,
     },
 
     #[command(about = "Move issue to 'doing' status")]
@@ -72,6 +90,79 @@ enum Commands {
     Done {
         #[arg(help = "Issue ID (full or partial)")]
         id: Option<String>,
+
+        #[arg(long, help = "Delete the issue's branch if it has been merged")]
+        delete_branch: bool,
+    },
+
+    #[command(about = "Merge two versions of an issue file (used by git)")]
+    MergeDriver {
+        #[arg(help = "Common ancestor's version (%O)")]
+        base: String,
+
+        #[arg(help = "Our version, which receives the result (%A)")]
+        ours: String,
+
+        #[arg(help = "Their version (%B)")]
+        theirs: String,
+
+        #[arg(help = "Path of the file being merged (%P)")]
+        path: Option<String>,
+    },
+
+    #[command(about = "Keep one copy of issues that appear in several statuses after a merge")]
+    Resolve {
+        #[arg(long, help = "Keep the copy furthest along without asking")]
+        auto: bool,
+    },
+
+    #[command(about = "Check the board for problems and repair what can be")]
+    Doctor {
+        #[arg(
+            long,
+            help = "Repair duplicate priorities, stray priorities and a stale current issue"
+        )]
+        fix: bool,
+    },
+
+    #[command(about = "Fetch, merge and push the board ref (storage.backend: git_ref)")]
+    Sync {
+        #[arg(default_value = "origin", help = "Remote to sync with")]
+        remote: String,
+    },
+
+    #[command(about = "Commit staged changes and the board, referencing the current issue")]
+    Commit {
+        #[arg(short, long, help = "Commit message (defaults to the issue title)")]
+        message: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "ID",
+            help = "Reference this issue instead of the current one"
+        )]
+        issue: Option<String>,
+
+        #[arg(long, help = "Move the issue to the last status in the same commit")]
+        done: bool,
+
+        #[arg(short, long, help = "Also stage changes to tracked files")]
+        all: bool,
+    },
+
+    #[command(about = "Create and check out a git branch for an issue, and start it")]
+    Branch {
+        #[arg(help = "Issue ID (full or partial)")]
+        id: String,
+    },
+
+    #[command(about = "Print, set or clear the current issue for this worktree")]
+    Current {
+        #[arg(help = "Issue ID to make current (full or partial)")]
+        id: Option<String>,
+
+        #[arg(long, conflicts_with = "id", help = "Clear the current issue")]
+        clear: bool,
     },
 
     #[command(about = "Move issue to specific status")]
@@ -95,13 +186,88 @@ enum Commands {
         id: String,
     },
 
-    #[command(about = "Extract story change history from git commits as CSV")]
+    #[command(about = "Extract story change history from git commits")]
     Report {
         #[arg(long, help = "Start from this commit (optional)")]
         since: Option<String>,
 
         #[arg(long, help = "End at this commit (optional)")]
         until: Option<String>,
+
+        #[arg(long, help = "Follow only the first parent of merge commits")]
+        first_parent: bool,
+
+        #[arg(
+            long = "branch",
+            value_name = "NAME",
+            conflicts_with = "until",
+            help = "Report on this branch (repeatable)"
+        )]
+        branches: Vec<String>,
+
+        #[arg(long, conflicts_with = "until", help = "Report on every local branch")]
+        all_branches: bool,
+
+        #[arg(
+            long,
+            default_value = "csv",
+            help = "Output format (csv, json, ndjson, sqlite)"
+        )]
+        format: String,
+
+        #[arg(
+            long,
+            help = "Write to this file instead of stdout (required for sqlite)"
+        )]
+        out: Option<String>,
+    },
+
+    #[command(about = "Show the history of a single issue from git commits")]
+    Log {
+        #[arg(help = "Issue ID (full or partial)")]
+        id: String,
+
+        #[arg(short, long, help = "Show diffs of body edits")]
+        patch: bool,
+    },
+
+    #[command(about = "Summarise board changes between two git revisions")]
+    Diff {
+        #[arg(help = "Revision to compare from")]
+        from: String,
+
+        #[arg(help = "Revision to compare to")]
+        to: String,
+    },
+
+    #[command(about = "Chart board history from git commits")]
+    Chart {
+        #[command(subcommand)]
+        command: ChartCommands,
+    },
+
+    #[command(about = "Forecast delivery dates from historic throughput")]
+    Forecast {
+        #[arg(
+            long,
+            help = "Number of items to forecast (default: issues in first status)"
+        )]
+        items: Option<usize>,
+
+        #[arg(
+            long,
+            help = "Also forecast how many items are done by this date (YYYY-MM-DD)"
+        )]
+        date: Option<String>,
+
+        #[arg(long, help = "Only use the last N weeks of throughput")]
+        weeks: Option<usize>,
+
+        #[arg(long, default_value_t = 10000, help = "Number of Monte Carlo trials")]
+        trials: usize,
+
+        #[arg(long, help = "Random seed for reproducible forecasts")]
+        seed: Option<u64>,
     },
 
     #[command(about = "Set priority order for a story")]
@@ -149,10 +315,17 @@ enum Commands {
         shell: String,
     },
 
-    #[command(about = "Check for issue ID prefix in a message (used by git hook)")]
+    #[command(about = "Check for issue references in a message (used by git hook)")]
     Prefix {
         #[arg(help = "The message to check")]
         message: String,
+
+        #[arg(
+            long,
+            value_name = "ID",
+            help = "Print the message with a reference to this issue added"
+        )]
+        add: Option<String>,
     },
 
     #[command(about = "Create CLAUDE.md with moth agent guide for LLM assistants")]
@@ -165,22 +338,106 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand, Debug)]
+enum ChartCommands {
+    #[command(about = "Cumulative flow diagram of issues per status")]
+    Cfd {
+        #[arg(long, help = "Start from this commit (optional)")]
+        since: Option<String>,
+
+        #[arg(long, help = "End at this commit (optional)")]
+        until: Option<String>,
+
+        #[arg(long, help = "Write an SVG chart to this file instead of printing")]
+        svg: Option<String>,
+
+        #[arg(long, default_value_t = 60, help = "Width of the terminal chart")]
+        width: usize,
+    },
+
+    #[command(about = "Burn-up chart of done issues against total scope")]
+    Burnup {
+        #[arg(long, help = "Start from this commit (optional)")]
+        since: Option<String>,
+
+        #[arg(long, help = "End at this commit (optional)")]
+        until: Option<String>,
+
+        #[arg(long, help = "Write an SVG chart to this file instead of printing")]
+        svg: Option<String>,
+
+        #[arg(long, default_value_t = 60, help = "Width of the terminal chart")]
+        width: usize,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 enum HookCommands {
-    #[command(about = "Install prepare-commit-msg hook")]
+    #[command(about = "Install a git hook (prepare-commit-msg by default)")]
     Install {
         #[arg(long, help = "Overwrite existing hook")]
         force: bool,
 
         #[arg(long, help = "Append to existing hook")]
         append: bool,
+
+        #[arg(
+            long,
+            help = "Add a snippet that runs moth to the hook instead of replacing it"
+        )]
+        snippet: bool,
+
+        #[arg(
+            long,
+            default_value = "prepare-commit-msg",
+            help = "Hook to install (prepare-commit-msg, commit-msg, post-commit, post-merge)"
+        )]
+        kind: String,
+    },
+
+    #[command(about = "Uninstall a moth git hook")]
+    Uninstall {
+        #[arg(
+            long,
+            default_value = "prepare-commit-msg",
+            help = "Hook to uninstall (prepare-commit-msg, commit-msg, post-commit, post-merge)"
+        )]
+        kind: String,
     },
 
-    #[command(about = "Uninstall prepare-commit-msg hook")]
-    Uninstall,
+    #[command(about = "Check a commit message file for an issue prefix (used by commit-msg hook)")]
+    Check {
+        #[arg(help = "Path to the commit message file")]
+        file: String,
+    },
+
+    #[command(about = "Run a moth git hook (used by hook snippets)")]
+    Run {
+        #[arg(help = "Hook to run (prepare-commit-msg, commit-msg, post-commit, post-merge)")]
+        kind: String,
+
+        #[arg(
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            help = "Arguments from git"
+        )]
+        args: Vec<String>,
+    },
+
+    #[command(
+        about = "Move issues referenced by commits (used by post-commit and post-merge hooks)"
+    )]
+    Transition {
+        #[arg(
+            default_value = "HEAD",
+            help = "Commit or range of commits, e.g. ORIG_HEAD..HEAD"
+        )]
+        range: String,
+    },
 }
 
 fn main() {
+    moth::error::set_warning_sink(|message| eprintln!("Warning: {}", message));
     let cli = Cli::parse();
 
     // Handle hidden completion helper flags
@@ -207,7 +464,13 @@ fn main() {
     }
 
     let result = match command {
-        Commands::Init => cmd::init::run(),
+        Commands::Init { git } => {
+            if git {
+                cmd::init::git()
+            } else {
+                cmd::init::run()
+            }
+        }
         Commands::New {
             title,
             severity,
@@ -231,6 +494,7 @@ fn main() {
             status,
             all,
             severity,
+            at,
         } => {
             let sev_filter = severity
                 .as_deref()
@@ -240,15 +504,82 @@ fn main() {
                     eprintln!("Error: {}", e);
                     process::exit(1);
                 });
-            cmd::list::run(status.as_deref(), all, sev_filter)
+            cmd::list::run(status.as_deref(), all, sev_filter, at.as_deref())
         }
-        Commands::Show { id } => cmd::show::run(id.as_deref()),
+        Commands::Show { id, at } => cmd::show::run(id.as_deref(), at.as_deref()),
         Commands::Start { id } => cmd::start::run(&id),
-        Commands::Done { id } => cmd::done::run(id.as_deref()),
+        Commands::Done { id, delete_branch } => cmd::done::run(id.as_deref(), delete_branch),
+        Commands::Branch { id } => cmd::branch::run(&id),
+        Commands::MergeDriver {
+            base, ours, theirs, ..
+        } => cmd::merge::driver(Path::new(&base), Path::new(&ours), Path::new(&theirs)),
+        Commands::Resolve { auto } => cmd::merge::resolve(auto),
+        Commands::Doctor { fix } => cmd::doctor::run(fix),
+        Commands::Sync { remote } => cmd::sync::run(&remote),
+        Commands::Commit {
+            message,
+            issue,
+            done,
+            all,
+        } => cmd::commit::run(&cmd::commit::CommitOptions {
+            message: message.as_deref(),
+            issue: issue.as_deref(),
+            done,
+            all,
+        }),
+        Commands::Current { id, clear } => cmd::current::run(id.as_deref(), clear),
         Commands::Mv { id, status } => cmd::mv::run(&id, &status),
         Commands::Edit { id } => cmd::edit::run(&id),
         Commands::Rm { id } => cmd::rm::run(&id),
-        Commands::Report { since, until } => cmd::report::run(since.as_deref(), until.as_deref()),
+        Commands::Report {
+            since,
+            until,
+            first_parent,
+            branches,
+            all_branches,
+            format,
+            out,
+        } => {
+            let format = format.parse().unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            });
+            let branches = cmd::report::BranchOptions {
+                first_parent,
+                branches,
+                all: all_branches,
+            };
+            cmd::report::run(
+                since.as_deref(),
+                until.as_deref(),
+                &branches,
+                format,
+                out.as_deref(),
+            )
+        }
+        Commands::Log { id, patch } => cmd::log::run(&id, patch),
+        Commands::Diff { from, to } => cmd::diff::run(&from, &to),
+        Commands::Chart { command } => match command {
+            ChartCommands::Cfd {
+                since,
+                until,
+                svg,
+                width,
+            } => cmd::chart::cfd(since.as_deref(), until.as_deref(), svg.as_deref(), width),
+            ChartCommands::Burnup {
+                since,
+                until,
+                svg,
+                width,
+            } => cmd::chart::burnup(since.as_deref(), until.as_deref(), svg.as_deref(), width),
+        },
+        Commands::Forecast {
+            items,
+            date,
+            weeks,
+            trials,
+            seed,
+        } => cmd::forecast::run(items, date.as_deref(), weeks, trials, seed),
         Commands::Priority {
             id,
             position,
@@ -274,14 +605,26 @@ fn main() {
             cmd::severity::run(&id, sev)
         }
         Commands::Hook { command } => match command {
-            HookCommands::Install { force, append } => cmd::hook::install(force, append),
-            HookCommands::Uninstall => cmd::hook::uninstall(),
+            HookCommands::Install {
+                force,
+                append,
+                snippet,
+                kind,
+            } => cmd::hook::install(parse_hook_kind(&kind), force, append, snippet),
+            HookCommands::Uninstall { kind } => cmd::hook::uninstall(parse_hook_kind(&kind)),
+            HookCommands::Check { file } => cmd::hook::check(Path::new(&file)),
+            HookCommands::Transition { range } => cmd::hook::transition(&range),
+            HookCommands::Run { kind, args } => {
+                exit_with(cmd::hook::run(parse_hook_kind(&kind), &args))
+            }
         },
         Commands::Completions { shell } => {
             generate_completions(&shell);
             return;
         }
-        Commands::Prefix { message } => cmd::prefix::check(&message),
+        Commands::Prefix { message, add } => {
+            exit_with(cmd::prefix::check(&message, add.as_deref()))
+        }
         Commands::Claude { force, append } => {
             if append {
                 cmd::claude::append()
@@ -302,71 +645,37 @@ fn main() {
     }
 }
 
+/// Pass on a command's error, or exit with the status it returned.
+fn exit_with(status: anyhow::Result<i32>) -> anyhow::Result<()> {
+    match status {
+        Ok(0) => Ok(()),
+        Ok(code) => process::exit(code),
+        Err(e) => Err(e),
+    }
+}
+
+fn parse_hook_kind(kind: &str) -> cmd::hook::HookKind {
+    kind.parse().unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    })
+}
+
 fn list_story_ids() {
-    use std::fs;
+    use moth::backend::FsBackend;
+    use moth::config::Config;
+    use moth::index::Index;
 
-    // Fast path: scan filesystem without full validation
-    let Ok(mut current) = std::env::current_dir() else {
+    // Fast path: read the index without creating missing directories or
+    // refreshing a git_ref board, and stay quiet on errors
+    let Ok(config) = Config::load() else {
         return;
     };
-
-    // Find .moth directory
-    let moth_dir = loop {
-        let moth = current.join(".moth");
-        if moth.is_dir() {
-            break moth;
-        }
-        if !current.pop() {
-            return; // No .moth found, silently exit
-        }
-    };
-
-    // Scan all status directories
-    let Ok(entries) = fs::read_dir(&moth_dir) else {
+    let Ok(index) = Index::load(&config, &FsBackend) else {
         return;
     };
 
-    let mut ids = Vec::new();
-    for entry in entries.filter_map(Result::ok) {
-        let path = entry.path();
-        if !path.is_dir() || path.file_name().unwrap().to_str().unwrap().starts_with('.') {
-            continue;
-        }
-
-        // Scan files in this status directory
-        let Ok(files) = fs::read_dir(&path) else {
-            continue;
-        };
-
-        for file in files.filter_map(Result::ok) {
-            let filename = file.file_name();
-            let name = filename.to_string_lossy();
-            if !name.ends_with(".md") {
-                continue;
-            }
-
-            // Extract ID from filename: [NNN-]ID-priority-slug.md
-            let parts: Vec<&str> = name.trim_end_matches(".md").split('-').collect();
-            if parts.len() < 3 {
-                continue;
-            }
-
-            // Check if first part is a number (priority order)
-            let id_idx = if parts[0].parse::<u32>().is_ok() {
-                1
-            } else {
-                0
-            };
-            if parts.len() > id_idx {
-                ids.push(parts[id_idx].to_string());
-            }
-        }
-    }
-
-    // Print unique IDs
-    ids.sort();
-    ids.dedup();
-    for id in ids {
+    for id in index.ids() {
         println!("{}", id);
     }
 }
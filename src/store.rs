@@ -1,10 +1,39 @@
-use crate::config::Config;
-use crate::issue::{Issue, Severity, generate_id};
-use anyhow::{Context, Result, anyhow};
-use std::fs;
+use crate::backend::{Backend, FsBackend};
+use crate::board_ref::BoardRef;
+use crate::config::{Config, StorageBackend};
+use crate::error::{Context, Error, Result, warn};
+use crate::git::{commit_paths, exclude_paths, nothing_staged, workdir_relative};
+use crate::index::Index;
+use crate::issue::{Issue, Severity, generate_id, required_ids};
+use git2::{Repository, RepositoryState};
+use std::cell::{Cell, RefCell};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+/// Files moth keeps in `.moth/` for itself, listed in `.moth/.gitignore`
+/// and the repository's `info/exclude`.
+pub const INTERNAL_FILES: &[&str] = &[".lock", ".journal", ".journal.tmp", ".index", ".index.tmp"];
 
 pub struct Store {
     config: Config,
+    backend: Box<dyn Backend>,
+    lock_depth: Cell<usize>,
+    lock_file: RefCell<Option<File>>,
+}
+
+/// The board lock, released when the last guard taken by a store is dropped.
+pub struct StoreLock<'s> {
+    store: &'s Store,
+}
+
+impl Drop for StoreLock<'_> {
+    fn drop(&mut self) {
+        let depth = self.store.lock_depth.get() - 1;
+        self.store.lock_depth.set(depth);
+        if depth == 0 {
+            self.store.lock_file.borrow_mut().take();
+        }
+    }
 }
 
 impl Store {
@@ -20,123 +49,224 @@ impl Store {
             }
         }
 
-        Ok(Store { config })
+        // Boards whose .gitignore predates the internal files, or the
+        // git_ref backend, still need git to leave these alone
+        if let Ok(repo) = Repository::discover(&config.moth_dir)
+            && let Ok(board) = workdir_relative(&repo, &config.moth_dir)
+        {
+            let paths: Vec<PathBuf> = ignored_names(&config)
+                .iter()
+                .map(|name| board.join(name))
+                .collect();
+            exclude_paths(&repo, &paths)?;
+        }
+
+        let backend: Box<dyn Backend> = match config.storage.backend {
+            StorageBackend::Worktree => Box::new(FsBackend),
+            StorageBackend::GitRef => Box::new(BoardRef::open(&config)?),
+        };
+        Ok(Self::with_backend(config, backend))
     }
 
-    pub fn find(&self, partial_id: &str) -> Result<Issue> {
-        let all_issues = self.all_issues()?;
-        let matches: Vec<&Issue> = all_issues
-            .iter()
-            .filter(|issue| issue.id.starts_with(partial_id))
-            .collect();
+    /// A store over another backend, e.g. `MemoryBackend`. Issue paths are
+    /// still built from `config.moth_dir` and the status directories.
+    pub fn with_backend(config: Config, backend: Box<dyn Backend>) -> Self {
+        Store {
+            config,
+            backend,
+            lock_depth: Cell::new(0),
+            lock_file: RefCell::new(None),
+        }
+    }
 
-        match matches.len() {
-            0 => Err(anyhow!("No issue found with ID: {}", partial_id)),
-            1 => Ok(matches[0].clone()),
-            _ => {
-                let ids: Vec<String> = matches.iter().map(|i| i.id.clone()).collect();
-                Err(anyhow!(
-                    "Ambiguous ID '{}'. Matches: {}",
-                    partial_id,
-                    ids.join(", ")
-                ))
-            }
+    /// Lock the board against other moth processes until the guard is
+    /// dropped. The store's own changes take the lock themselves; commands
+    /// hold it across reads and writes that must not interleave with another
+    /// process's. Taking it again while held is free. A batch of renames
+    /// interrupted by a crash is finished first, then the backend picks up
+    /// changes stored elsewhere.
+    pub fn lock(&self) -> Result<StoreLock<'_>> {
+        let depth = self.lock_depth.get();
+        if depth == 0 {
+            *self.lock_file.borrow_mut() =
+                self.backend.lock(&self.config.moth_dir.join(".lock"))?;
+        }
+        self.lock_depth.set(depth + 1);
+        let guard = StoreLock { store: self };
+
+        if depth == 0 {
+            self.recover()?;
+            self.backend.refresh()?;
         }
+        Ok(guard)
+    }
+
+    /// The board's index, read again where status directories changed.
+    /// Saved for next time while this store holds the lock.
+    pub fn index(&self) -> Result<Index<'_>> {
+        let index = Index::load(&self.config, self.backend.as_ref())?;
+        if self.lock_depth.get() > 0 {
+            index.save(self.backend.as_ref());
+        }
+        Ok(index)
+    }
+
+    pub fn find(&self, partial_id: &str) -> Result<Issue> {
+        find_issue(&self.index()?.matching(partial_id), partial_id)
     }
 
     pub fn all_issues(&self) -> Result<Vec<Issue>> {
+        let index = self.index()?;
         let mut issues = Vec::new();
 
         for status in &self.config.statuses {
-            let status_issues = self.issues_by_status(&status.name)?;
-            issues.extend(status_issues);
+            issues.extend(self.indexed_status(&index, &status.name));
         }
 
         Ok(issues)
     }
 
     pub fn issues_by_status(&self, status: &str) -> Result<Vec<Issue>> {
-        let status_config = self
-            .config
-            .get_status(status)
-            .ok_or_else(|| anyhow!("Unknown status: {}", status))?;
-
-        let dir = self.config.status_dir(status_config);
-        let mut issues = Vec::new();
-
-        if !dir.exists() {
-            return Ok(issues);
+        if self.config.get_status(status).is_none() {
+            return Err(Error::UnknownStatus(status.to_string()));
         }
 
-        let entries = fs::read_dir(&dir)
-            .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
-
-        for entry in entries {
-            let entry = entry?;
-            let path = entry.path();
+        Ok(self.indexed_status(&self.index()?, status))
+    }
 
-            if path.extension().and_then(|s| s.to_str()) == Some("md") {
-                match Issue::from_path(&path, status) {
-                    Ok(issue) => issues.push(issue),
-                    Err(e) => eprintln!("Warning: Failed to parse {}: {}", path.display(), e),
-                }
+    fn indexed_status(&self, index: &Index, status: &str) -> Vec<Issue> {
+        for path in index.unparseable(status) {
+            if let Err(e) = Issue::from_path(&path, status) {
+                warn(format_args!("Failed to parse {}: {}", path.display(), e));
             }
         }
 
-        issues.sort_by(|a, b| {
-            // First sort by order (if present), then by severity, then by slug
-            match (a.order, b.order) {
-                (Some(a_order), Some(b_order)) => a_order.cmp(&b_order),
-                (Some(_), None) => std::cmp::Ordering::Less,
-                (None, Some(_)) => std::cmp::Ordering::Greater,
-                (None, None) => a
-                    .severity
-                    .cmp(&b.severity)
-                    .then_with(|| a.slug.cmp(&b.slug)),
-            }
-        });
-
-        Ok(issues)
+        let mut issues = index.status_issues(status);
+        sort_issues(&mut issues);
+        issues
     }
 
-    pub fn move_issue(&self, issue: &Issue, target_status: &str) -> Result<()> {
+    /// Move an issue to another status, returning it as moved.
+    pub fn move_issue(&self, issue: &Issue, target_status: &str) -> Result<Issue> {
         let target_config = self
             .config
             .get_status(target_status)
-            .ok_or_else(|| anyhow!("Unknown status: {}", target_status))?;
-
-        let target_dir = self.config.status_dir(target_config);
+            .ok_or_else(|| Error::UnknownStatus(target_status.to_string()))?;
 
         // Strip priority order if target status is not prioritized
         let mut updated_issue = issue.clone();
         if !target_config.prioritized {
             updated_issue.order = None;
         }
+        updated_issue.status = target_status.to_string();
 
-        let new_path = target_dir.join(updated_issue.filename());
+        self.update_issue(issue, updated_issue)
+    }
+
+    /// Save changes to an issue's status, severity, order or slug by moving
+    /// its file to match. Returns the issue with its new path.
+    pub fn update_issue(&self, issue: &Issue, updated: Issue) -> Result<Issue> {
+        let mut updated = self.update_issues(vec![(issue.clone(), updated)])?;
+        Ok(updated.remove(0))
+    }
 
-        fs::rename(&issue.path, &new_path).with_context(|| {
-            format!(
-                "Failed to move {} to {}",
-                issue.path.display(),
-                new_path.display()
-            )
-        })?;
+    /// `update_issue` for several issues at once, e.g. renumbering a
+    /// prioritized status. The renames are written to `.moth/.journal`
+    /// first, so if the process dies part way through the next `lock()`
+    /// finishes them rather than leaving two issues with the same order.
+    pub fn update_issues(&self, updates: Vec<(Issue, Issue)>) -> Result<Vec<Issue>> {
+        let _lock = self.lock()?;
+
+        let mut renames = Vec::new();
+        let mut updated_issues = Vec::new();
+        for (issue, mut updated) in updates {
+            let status = self
+                .config
+                .get_status(&updated.status)
+                .ok_or_else(|| Error::UnknownStatus(updated.status.clone()))?;
+            updated.path = self.config.status_dir(status).join(updated.filename());
+
+            if updated.path != issue.path {
+                renames.push((issue.path, updated.path.clone()));
+            }
+            updated_issues.push(updated);
+        }
+
+        match renames.as_slice() {
+            [] => {}
+            [(from, to)] => self.backend.rename(from, to)?,
+            _ => {
+                let journal = self.config.moth_dir.join(".journal");
+                let pending = self.config.moth_dir.join(".journal.tmp");
+                let content: String = renames
+                    .iter()
+                    .map(|(from, to)| format!("{}\t{}\n", from.display(), to.display()))
+                    .collect();
+
+                // The journal only appears once it is complete
+                self.backend.write(&pending, &content)?;
+                self.backend.rename(&pending, &journal)?;
+                self.replay(&content)?;
+                self.backend.delete(&journal)?;
+            }
+        }
 
+        Ok(updated_issues)
+    }
+
+    /// Finish the renames of a journaled batch that didn't complete.
+    fn recover(&self) -> Result<()> {
+        let journal = self.config.moth_dir.join(".journal");
+        let Ok(content) = self.backend.read(&journal) else {
+            return Ok(());
+        };
+
+        warn(format_args!(
+            "Finishing changes interrupted in {}",
+            self.config.moth_dir.display()
+        ));
+        self.replay(&content)?;
+        self.backend.delete(&journal)
+    }
+
+    /// Rename each `from\tto` line's file, skipping those already done.
+    fn replay(&self, journal: &str) -> Result<()> {
+        for line in journal.lines() {
+            let Some((from, to)) = line.split_once('\t') else {
+                continue;
+            };
+            let (from, to) = (Path::new(from), Path::new(to));
+            let Some(dir) = from.parent() else {
+                continue;
+            };
+            if self.backend.list(dir)?.iter().any(|path| path == from) {
+                self.backend.rename(from, to)?;
+            }
+        }
         Ok(())
     }
 
     pub fn delete_issue(&self, issue: &Issue) -> Result<()> {
-        fs::remove_file(&issue.path)
-            .with_context(|| format!("Failed to delete {}", issue.path.display()))?;
-        Ok(())
+        let _lock = self.lock()?;
+        self.backend.delete(&issue.path)
+    }
+
+    pub fn read(&self, issue: &Issue) -> Result<String> {
+        self.backend.read(&issue.path)
+    }
+
+    pub fn write(&self, issue: &Issue, content: &str) -> Result<()> {
+        let _lock = self.lock()?;
+        self.backend.write(&issue.path, content)
     }
 
     pub fn create_issue(&self, title: &str, severity: Severity) -> Result<Issue> {
         if title.trim().is_empty() {
-            return Err(anyhow!("Issue title cannot be empty"));
+            return Err(Error::Invalid("Issue title cannot be empty".to_string()));
         }
 
+        // Another process must not take the same ID before the file exists
+        let _lock = self.lock()?;
         let slug = title_to_slug(title);
         let id = self.generate_unique_id()?;
 
@@ -146,8 +276,7 @@ impl Store {
         let filename = format!("{}-{}-{}.md", id, severity.as_str(), slug);
         let path = dir.join(&filename);
 
-        fs::write(&path, "")
-            .with_context(|| format!("Failed to create issue file: {}", path.display()))?;
+        self.backend.write(&path, "")?;
 
         Issue::from_path(&path, &first_status.name)
     }
@@ -165,47 +294,174 @@ impl Store {
             }
         }
 
-        Err(anyhow!(
+        Err(Error::Invalid(format!(
             "Failed to generate unique ID after {} attempts",
             max_attempts
-        ))
+        )))
     }
 
     pub fn config(&self) -> &Config {
         &self.config
     }
 
+    /// With `auto_commit` on, commit the board files a command changed (and
+    /// nothing else). Paths may be files that no longer exist or whole
+    /// status directories. Backends with a history of their own, such as
+    /// `git_ref`, always record the change there instead.
+    pub fn auto_commit(&self, paths: &[&Path], message: &str) -> Result<()> {
+        if self.backend.record(message)? {
+            return Ok(());
+        }
+        if !self.config.auto_commit {
+            return Ok(());
+        }
+        let Ok(repo) = Repository::discover(&self.config.moth_dir) else {
+            warn(format_args!(
+                "auto_commit is on but the board is not in a git repository"
+            ));
+            return Ok(());
+        };
+
+        let paths = paths
+            .iter()
+            .map(|path| workdir_relative(&repo, path))
+            .collect::<Result<Vec<_>>>()?;
+        commit_paths(&repo, &paths, message)?;
+
+        Ok(())
+    }
+
+    /// Check a commit message as the `commit-msg` hook does, returning the
+    /// IDs it references. Exempt commits give no IDs; otherwise the message
+    /// must reference at least one issue, and every issue it references must
+    /// exist.
+    pub fn validate_message(&self, message: &str) -> Result<Vec<String>> {
+        let (is_merge, is_empty) = match Repository::discover(&self.config.moth_dir) {
+            Ok(repo) => (
+                repo.state() == RepositoryState::Merge,
+                nothing_staged(&repo)?,
+            ),
+            Err(_) => (false, false),
+        };
+
+        let ids = required_ids(message, &self.config, is_merge, is_empty)?;
+        if !ids.is_empty() {
+            let known: Vec<String> = self.index()?.ids().map(str::to_string).collect();
+            if let Some(id) = ids.iter().find(|id| !known.contains(id)) {
+                return Err(Error::IssueNotFound(id.clone()));
+            }
+        }
+
+        Ok(ids)
+    }
+
+    /// Where the current issue ID is kept: this worktree's git directory, so
+    /// worktrees don't overwrite each other, or `.moth/.current` outside git.
+    /// A `.moth/.current` left from before is moved to the git directory.
+    fn current_file(&self) -> PathBuf {
+        let old = self.config.moth_dir.join(".current");
+        let Ok(repo) = Repository::discover(&self.config.moth_dir) else {
+            return old;
+        };
+
+        let path = repo.path().join("moth-current");
+        if old.is_file()
+            && let Err(e) = migrate_current(&old, &path)
+        {
+            warn(format_args!("Failed to move {}: {}", old.display(), e));
+        }
+        path
+    }
+
+    /// The ID of the issue being worked on in this worktree. With
+    /// `branches.infer_current`, an issue named by the checked-out branch
+    /// takes precedence over the one set with `moth start`.
+    pub fn current_id(&self) -> Result<Option<String>> {
+        if self.config.branches.infer_current
+            && let Some(id) = self.branch_issue()?
+        {
+            return Ok(Some(id));
+        }
+
+        Ok(self.recorded_current_id())
+    }
+
+    /// The ID set with `moth start` or `moth current`, ignoring the branch.
+    pub fn recorded_current_id(&self) -> Option<String> {
+        fs::read_to_string(self.current_file())
+            .ok()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+    }
+
+    pub fn set_current(&self, id: &str) -> Result<()> {
+        let path = self.current_file();
+        fs::write(&path, id)
+            .with_context(|| format!("Failed to write current issue: {}", path.display()))
+    }
+
+    /// Forget the current issue if it is `id`, or whichever it is.
+    pub fn clear_current(&self, id: Option<&str>) -> Result<()> {
+        let path = self.current_file();
+        let Ok(current_id) = fs::read_to_string(&path) else {
+            return Ok(());
+        };
+
+        if id.is_none_or(|id| current_id.trim() == id) {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// The issue whose ID is one of the words of the checked-out branch's
+    /// name, e.g. `abc12` for `feature/abc12-fix-login`.
+    fn branch_issue(&self) -> Result<Option<String>> {
+        let Ok(repo) = Repository::discover(&self.config.moth_dir) else {
+            return Ok(None);
+        };
+        let Ok(head) = repo.head() else {
+            return Ok(None);
+        };
+        if !head.is_branch() {
+            return Ok(None);
+        }
+        let Some(branch) = head.shorthand() else {
+            return Ok(None);
+        };
+
+        let issues = self.all_issues()?;
+        let id = branch
+            .split(['/', '-', '_'])
+            .find(|word| issues.iter().any(|issue| issue.id == *word));
+
+        Ok(id.map(str::to_string))
+    }
+
     pub fn current(&self) -> Result<Option<Issue>> {
+        if let Some(id) = self.current_id()?
+            && let Some(issue) = self.all_issues()?.into_iter().find(|i| i.id == id)
+        {
+            return Ok(Some(issue));
+        }
+
         let doing_status = self
             .config
             .get_status("doing")
-            .ok_or_else(|| anyhow!("'doing' status not configured"))?;
+            .ok_or_else(|| Error::Config("'doing' status not configured".to_string()))?;
         let doing_dir = self.config.status_dir(doing_status);
 
-        if !doing_dir.exists() {
-            return Ok(None);
-        }
-
         let mut latest_issue: Option<Issue> = None;
-        let mut latest_time = std::time::SystemTime::UNIX_EPOCH;
-
-        let entries = fs::read_dir(&doing_dir)
-            .with_context(|| format!("Failed to read directory: {}", doing_dir.display()))?;
+        let mut latest_time = None;
 
-        for entry in entries {
-            let entry = entry?;
-            let path = entry.path();
+        for path in self.backend.list(&doing_dir)? {
+            let modified_time = self.backend.modified(&path);
 
-            if path.extension().and_then(|s| s.to_str()) == Some("md") {
-                let metadata = fs::metadata(&path)?;
-                let modified_time = metadata.modified()?;
-
-                if modified_time > latest_time {
-                    latest_time = modified_time;
-                    match Issue::from_path(&path, "doing") {
-                        Ok(issue) => latest_issue = Some(issue),
-                        Err(e) => eprintln!("Warning: Failed to parse {}: {}", path.display(), e),
-                    }
+            if latest_issue.is_none() || modified_time > latest_time {
+                latest_time = modified_time;
+                match Issue::from_path(&path, "doing") {
+                    Ok(issue) => latest_issue = Some(issue),
+                    Err(e) => warn(format_args!("Failed to parse {}: {}", path.display(), e)),
                 }
             }
         }
@@ -214,6 +470,63 @@ impl Store {
     }
 }
 
+/// Move the current issue ID from `old` to `path`, unless one is set there
+/// already.
+fn migrate_current(old: &Path, path: &Path) -> Result<()> {
+    if !path.exists() {
+        let id = fs::read_to_string(old)?;
+        fs::write(path, id)?;
+    }
+    fs::remove_file(old)?;
+    Ok(())
+}
+
+/// Make sure `.moth/.gitignore` lists moth's internal files, and with the
+/// `git_ref` backend the status directories, adding any lines that are
+/// missing and leaving the others alone.
+pub fn ignore_board_files(config: &Config) -> Result<()> {
+    let missing = unignored_board_files(config);
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let path = config.moth_dir.join(".gitignore");
+    let mut content = fs::read_to_string(&path).unwrap_or_default();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    for line in missing {
+        content.push_str(&line);
+        content.push('\n');
+    }
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The `.moth/.gitignore` lines `ignore_board_files` would add.
+pub fn unignored_board_files(config: &Config) -> Vec<String> {
+    let content = fs::read_to_string(config.moth_dir.join(".gitignore")).unwrap_or_default();
+    ignored_names(config)
+        .iter()
+        .map(|name| format!("/{}", name))
+        .filter(|line| !content.lines().any(|l| l.trim() == line))
+        .collect()
+}
+
+/// What git should never see in `.moth/`: the internal files, and the
+/// working copy of a board stored on a ref.
+fn ignored_names(config: &Config) -> Vec<String> {
+    let mut names: Vec<String> = INTERNAL_FILES.iter().map(|name| name.to_string()).collect();
+    if config.storage.backend == StorageBackend::GitRef {
+        names.extend(
+            config
+                .statuses
+                .iter()
+                .map(|status| format!("{}/", status.dir)),
+        );
+    }
+    names
+}
+
 fn title_to_slug(title: &str) -> String {
     title
         .trim()
@@ -227,6 +540,39 @@ fn title_to_slug(title: &str) -> String {
         .join("_")
 }
 
+/// Find the single issue whose ID starts with `partial_id`.
+pub fn find_issue(issues: &[Issue], partial_id: &str) -> Result<Issue> {
+    let matches: Vec<&Issue> = issues
+        .iter()
+        .filter(|issue| issue.id.starts_with(partial_id))
+        .collect();
+
+    match matches.len() {
+        0 => Err(Error::IssueNotFound(partial_id.to_string())),
+        1 => Ok(matches[0].clone()),
+        _ => Err(Error::AmbiguousId {
+            id: partial_id.to_string(),
+            matches: matches.iter().map(|i| i.id.clone()).collect(),
+        }),
+    }
+}
+
+/// Sort issues within a status the way they are listed.
+pub fn sort_issues(issues: &mut [Issue]) {
+    issues.sort_by(|a, b| {
+        // First sort by order (if present), then by severity, then by slug
+        match (a.order, b.order) {
+            (Some(a_order), Some(b_order)) => a_order.cmp(&b_order),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => a
+                .severity
+                .cmp(&b.severity)
+                .then_with(|| a.slug.cmp(&b.slug)),
+        }
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -259,4 +605,99 @@ mod tests {
         // Unicode (non-ASCII becomes underscore)
         assert_eq!(title_to_slug("Café Mode"), "caf_mode");
     }
+
+    fn memory_store() -> Store {
+        let config = Config {
+            moth_dir: PathBuf::from("/board"),
+            ..Default::default()
+        };
+        Store::with_backend(config, Box::new(crate::backend::MemoryBackend::new()))
+    }
+
+    #[test]
+    fn test_memory_backend_store() {
+        let store = memory_store();
+        let issue = store.create_issue("Fix login", Severity::High).unwrap();
+        store.write(&issue, "Steps to reproduce\n").unwrap();
+        assert_eq!(store.issues_by_status("ready").unwrap().len(), 1);
+
+        let moved = store.move_issue(&issue, "doing").unwrap();
+        assert_eq!(moved.status, "doing");
+        assert!(store.issues_by_status("ready").unwrap().is_empty());
+        assert_eq!(store.find(&issue.id).unwrap().path, moved.path);
+        assert_eq!(store.read(&moved).unwrap(), "Steps to reproduce\n");
+        assert_eq!(store.current().unwrap().unwrap().id, issue.id);
+
+        store.delete_issue(&moved).unwrap();
+        assert!(store.all_issues().unwrap().is_empty());
+        assert!(matches!(
+            store.find(&issue.id),
+            Err(Error::IssueNotFound(id)) if id == issue.id
+        ));
+    }
+
+    #[test]
+    fn test_update_issue_renames() {
+        let store = memory_store();
+        let issue = store.create_issue("Add search", Severity::Low).unwrap();
+
+        let mut updated = issue.clone();
+        updated.order = Some(1);
+        let updated = store.update_issue(&issue, updated).unwrap();
+
+        assert!(updated.filename().starts_with("001-"));
+        assert_eq!(
+            updated.path,
+            Path::new("/board/ready").join(updated.filename())
+        );
+        let found = store.find(&issue.id).unwrap();
+        assert_eq!(found.order, Some(1));
+        assert!(store.read(&issue).is_err());
+    }
+
+    #[test]
+    fn test_interrupted_batch_is_finished() {
+        let store = memory_store();
+        let a = store.create_issue("First", Severity::Med).unwrap();
+        let b = store.create_issue("Second", Severity::Med).unwrap();
+        let prioritize = |issue: &Issue, order| {
+            let mut updated = issue.clone();
+            updated.order = Some(order);
+            (issue.clone(), updated)
+        };
+        let updated = store
+            .update_issues(vec![prioritize(&a, 3), prioritize(&b, 2)])
+            .unwrap();
+        let (a, b) = (&updated[0], &updated[1]);
+
+        // A renumber that died after moving the first file
+        let mut a_first = a.clone();
+        a_first.order = Some(1);
+        let a_first = store.update_issue(a, a_first).unwrap();
+        let ready = Path::new("/board/ready");
+        let journal = format!(
+            "{}\t{}\n{}\t{}\n",
+            a.path.display(),
+            a_first.path.display(),
+            b.path.display(),
+            ready
+                .join(b.filename().replacen("002-", "003-", 1))
+                .display()
+        );
+        store
+            .backend
+            .write(Path::new("/board/.journal"), &journal)
+            .unwrap();
+
+        drop(store.lock().unwrap());
+        let mut orders: Vec<_> = store
+            .issues_by_status("ready")
+            .unwrap()
+            .iter()
+            .map(|i| i.order)
+            .collect();
+        orders.sort();
+        assert_eq!(orders, vec![Some(1), Some(3)]);
+        assert!(store.backend.read(Path::new("/board/.journal")).is_err());
+    }
 }
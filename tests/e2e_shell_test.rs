@@ -501,3 +501,597 @@ fn test_e2e_new_with_start_flag_moves_to_doing() {
     let name = file_name.to_string_lossy();
     assert!(name.contains("test_issue_with_start"));
 }
+
+fn run_git(args: &[&str], cwd: &std::path::Path) {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(cwd)
+        .output()
+        .expect("Failed to run git");
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+fn commit_all(message: &str, cwd: &std::path::Path) {
+    run_git(&["add", "-A"], cwd);
+    run_git(&["commit", "-q", "--allow-empty", "-m", message], cwd);
+}
+
+#[test]
+#[serial]
+fn test_e2e_log_shows_issue_timeline() {
+    let temp = setup_test_env();
+    let temp_path = temp.path();
+
+    run_git(&["init", "-q"], temp_path);
+    run_git(&["config", "user.email", "test@test.com"], temp_path);
+    run_git(&["config", "user.name", "Test User"], temp_path);
+    run_moth_cmd(&["init"], temp_path);
+    run_moth_cmd(&["new", "Trace me", "--no-edit", "-s", "low"], temp_path);
+    commit_all("Add issue", temp_path);
+
+    let ready_dir = temp_path.join(".moth/ready");
+    let entry = std::fs::read_dir(&ready_dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    let name = entry.file_name().to_string_lossy().to_string();
+    let id: String = name.split('-').next().unwrap().to_string();
+
+    run_moth_cmd(&["start", &id], temp_path);
+    let doing_file = temp_path.join(".moth/doing").join(&name);
+    std::fs::write(&doing_file, "Investigate the crash\n").unwrap();
+    commit_all("Start issue", temp_path);
+    commit_all(&format!("[{}] Fix the crash", id), temp_path);
+
+    let (success, stdout, stderr) = run_moth_cmd(&["log", &id[..3], "-p"], temp_path);
+    assert!(success, "Command failed: {}", stderr);
+
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], format!("{}: Trace Me", id));
+    assert!(lines[1].ends_with("created in ready [low] Trace Me"));
+    assert!(lines[2].ends_with("moved ready -> doing"));
+    assert!(lines[3].ends_with("edited"));
+    assert!(stdout.contains("+Investigate the crash"));
+    assert!(
+        stdout
+            .lines()
+            .last()
+            .unwrap()
+            .ends_with("commit: Fix the crash")
+    );
+
+    let (success, _, stderr) = run_moth_cmd(&["log", "zzzzz"], temp_path);
+    assert!(!success);
+    assert!(stderr.contains("No issue found"));
+}
+
+#[test]
+#[serial]
+fn test_e2e_ls_and_show_at_revision() {
+    let temp = setup_test_env();
+    let temp_path = temp.path();
+
+    run_git(&["init", "-q"], temp_path);
+    run_git(&["config", "user.email", "test@test.com"], temp_path);
+    run_git(&["config", "user.name", "Test User"], temp_path);
+    run_moth_cmd(&["init"], temp_path);
+    run_moth_cmd(&["new", "Release blocker", "--no-edit"], temp_path);
+
+    let entry = std::fs::read_dir(temp_path.join(".moth/ready"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    let name = entry.file_name().to_string_lossy().to_string();
+    let id: String = name.split('-').next().unwrap().to_string();
+
+    run_moth_cmd(&["start", &id], temp_path);
+    std::fs::write(temp_path.join(".moth/doing").join(&name), "As released\n").unwrap();
+    commit_all("Release", temp_path);
+    run_git(&["tag", "v1.0"], temp_path);
+
+    run_moth_cmd(&["done", &id], temp_path);
+    std::fs::write(temp_path.join(".moth/done").join(&name), "After release\n").unwrap();
+    commit_all("Finish", temp_path);
+
+    let (success, stdout, stderr) = run_moth_cmd(&["ls", "--at", "v1.0"], temp_path);
+    assert!(success, "Command failed: {}", stderr);
+    assert!(stdout.contains("doing"));
+    assert!(stdout.contains(&id));
+
+    let (success, stdout, _) = run_moth_cmd(&["ls"], temp_path);
+    assert!(success);
+    assert!(!stdout.contains(&id));
+
+    let (success, stdout, stderr) = run_moth_cmd(&["show", &id[..3], "--at", "v1.0"], temp_path);
+    assert!(success, "Command failed: {}", stderr);
+    assert!(stdout.contains("Status: doing"));
+    assert!(stdout.contains("As released"));
+
+    let (success, _, stderr) = run_moth_cmd(&["ls", "--at", "no-such-rev"], temp_path);
+    assert!(!success);
+    assert!(stderr.contains("Unknown revision"));
+}
+
+#[test]
+#[serial]
+fn test_e2e_forecast_with_seed() {
+    let temp = setup_test_env();
+    let temp_path = temp.path();
+
+    run_git(&["init", "-q"], temp_path);
+    run_git(&["config", "user.email", "test@test.com"], temp_path);
+    run_git(&["config", "user.name", "Test User"], temp_path);
+    run_moth_cmd(&["init"], temp_path);
+
+    // One, three and two issues done in the last three weeks, and one today
+    let now = chrono::Utc::now();
+    let ready = temp_path.join(".moth/ready");
+    let done = temp_path.join(".moth/done");
+    for (days_ago, ids) in [
+        (21, vec!["aaa01"]),
+        (14, vec!["bbb01", "bbb02", "bbb03"]),
+        (7, vec!["ccc01", "ccc02"]),
+        (0, vec!["ddd01"]),
+    ] {
+        let date = format!("@{} +0000", (now - chrono::Days::new(days_ago)).timestamp());
+        let commit = |message: &str| {
+            run_git(&["add", "-A"], temp_path);
+            let status = Command::new("git")
+                .args(["commit", "-q", "-m", message])
+                .current_dir(temp_path)
+                .env("GIT_AUTHOR_DATE", &date)
+                .env("GIT_COMMITTER_DATE", &date)
+                .status()
+                .expect("Failed to run git");
+            assert!(status.success());
+        };
+
+        // Only moves into done count, not issues created there
+        let files: Vec<String> = ids.iter().map(|id| format!("{}-med-task.md", id)).collect();
+        for file in &files {
+            std::fs::write(ready.join(file), "").unwrap();
+        }
+        commit("Add work");
+        for file in &files {
+            std::fs::rename(ready.join(file), done.join(file)).unwrap();
+        }
+        commit("Finish work");
+    }
+
+    let in_three_days = (now + chrono::Days::new(3)).date_naive().to_string();
+    let args = [
+        "forecast",
+        "--items",
+        "4",
+        "--date",
+        &in_three_days,
+        "--trials",
+        "200",
+        "--seed",
+        "42",
+    ];
+    let (success, stdout, stderr) = run_moth_cmd(&args, temp_path);
+    assert!(success, "Command failed: {}", stderr);
+
+    // The unfinished current week isn't a sample
+    assert!(stdout.contains("Throughput: 3 weeks of history, 6 issues done, 2.0 per week"));
+    assert!(stdout.contains("When will 4 items be done? (200 trials)"));
+    // A partial week counts as one
+    assert!(stdout.contains(&format!(
+        "How many items by {}? (1 weeks, 200 trials)",
+        in_three_days
+    )));
+    assert!(!stdout.contains(" 0 items or more"));
+
+    let (_, again, _) = run_moth_cmd(&args, temp_path);
+    assert_eq!(stdout, again);
+}
+
+#[test]
+#[serial]
+fn test_e2e_commit_msg_hook_rejects_missing_prefix() {
+    let temp = setup_test_env();
+    let temp_path = temp.path();
+
+    run_git(&["init", "-q"], temp_path);
+    run_git(&["config", "user.email", "test@test.com"], temp_path);
+    run_git(&["config", "user.name", "Test User"], temp_path);
+    run_moth_cmd(&["init"], temp_path);
+    run_moth_cmd(&["new", "Guarded", "--no-edit"], temp_path);
+    let (success, _, stderr) =
+        run_moth_cmd(&["hook", "install", "--kind", "commit-msg"], temp_path);
+    assert!(success, "Command failed: {}", stderr);
+
+    let entry = std::fs::read_dir(temp_path.join(".moth/ready"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    let name = entry.file_name().to_string_lossy().to_string();
+    let id: String = name.split('-').next().unwrap().to_string();
+
+    // The hook calls `moth`, so put the test binary first on the PATH
+    let bin_dir = get_binary_path().parent().unwrap().to_path_buf();
+    let path = format!("{}:{}", bin_dir.display(), env::var("PATH").unwrap());
+    let commit = |message: &str| {
+        run_git(&["add", "-A"], temp_path);
+        let output = Command::new("git")
+            .args(["commit", "-q", "-m", message])
+            .current_dir(temp_path)
+            .env("PATH", &path)
+            .output()
+            .expect("Failed to run git");
+        (
+            output.status.success(),
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        )
+    };
+
+    let (success, stderr) = commit("No prefix here");
+    assert!(!success);
+    assert!(stderr.contains("must reference an issue ID"));
+
+    let (success, stderr) = commit("[zzzzz] Unknown issue");
+    assert!(!success);
+    assert!(stderr.contains("No issue found with ID: zzzzz"));
+
+    let (success, stderr) = commit(&format!("[{}] Add the issue", id));
+    assert!(success, "Commit failed: {}", stderr);
+}
+
+#[test]
+#[serial]
+fn test_e2e_commit_msg_hook_checks_commit_all() {
+    let temp = setup_test_env();
+    let temp_path = temp.path();
+
+    run_git(&["init", "-q"], temp_path);
+    run_git(&["config", "user.email", "test@test.com"], temp_path);
+    run_git(&["config", "user.name", "Test User"], temp_path);
+    run_moth_cmd(&["init"], temp_path);
+    let config_path = temp_path.join(".moth/config.yml");
+    let config = std::fs::read_to_string(&config_path).unwrap();
+    std::fs::write(
+        &config_path,
+        config.replace("allow_empty: false", "allow_empty: true"),
+    )
+    .unwrap();
+    std::fs::write(temp_path.join("notes.txt"), "first\n").unwrap();
+    commit_all("Initial commit", temp_path);
+    let (success, _, stderr) =
+        run_moth_cmd(&["hook", "install", "--kind", "commit-msg"], temp_path);
+    assert!(success, "Command failed: {}", stderr);
+
+    // `commit -a` stages into a temporary index, so the commit isn't empty
+    std::fs::write(temp_path.join("notes.txt"), "second\n").unwrap();
+    let bin_dir = get_binary_path().parent().unwrap().to_path_buf();
+    let path = format!("{}:{}", bin_dir.display(), env::var("PATH").unwrap());
+    let output = Command::new("git")
+        .args(["commit", "-q", "-am", "No prefix here"])
+        .current_dir(temp_path)
+        .env("PATH", &path)
+        .output()
+        .expect("Failed to run git");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("must reference an issue ID"));
+}
+
+#[test]
+fn test_e2e_post_commit_hook_moves_issues() {
+    let temp = setup_test_env();
+    let temp_path = temp.path();
+
+    run_git(&["init", "-q"], temp_path);
+    run_git(&["config", "user.email", "test@test.com"], temp_path);
+    run_git(&["config", "user.name", "Test User"], temp_path);
+    run_moth_cmd(&["init"], temp_path);
+    run_moth_cmd(&["new", "Automate", "--no-edit"], temp_path);
+    let (success, _, stderr) =
+        run_moth_cmd(&["hook", "install", "--kind", "post-commit"], temp_path);
+    assert!(success, "Command failed: {}", stderr);
+
+    let entry = std::fs::read_dir(temp_path.join(".moth/ready"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    let name = entry.file_name().to_string_lossy().to_string();
+    let id: String = name.split('-').next().unwrap().to_string();
+
+    // The hook calls `moth`, so put the test binary first on the PATH
+    let bin_dir = get_binary_path().parent().unwrap().to_path_buf();
+    let path = format!("{}:{}", bin_dir.display(), env::var("PATH").unwrap());
+    let commit = |message: &str| {
+        run_git(&["add", "-A"], temp_path);
+        let status = Command::new("git")
+            .args(["commit", "-q", "-m", message])
+            .current_dir(temp_path)
+            .env("PATH", &path)
+            .status()
+            .expect("Failed to run git");
+        assert!(status.success());
+    };
+
+    commit(&format!("[{}] Start the work", id));
+    assert!(temp_path.join(".moth/doing").join(&name).exists());
+
+    commit(&format!("[{}] Finish the work\n\nCloses: {}", id, id));
+    assert!(temp_path.join(".moth/done").join(&name).exists());
+}
+
+#[test]
+fn test_e2e_hook_install_from_linked_worktree() {
+    let temp = setup_test_env();
+    let repo_path = temp.path().join("repo");
+    let worktree_path = temp.path().join("feature");
+    std::fs::create_dir(&repo_path).unwrap();
+
+    run_git(&["init", "-q"], &repo_path);
+    run_git(&["config", "user.email", "test@test.com"], &repo_path);
+    run_git(&["config", "user.name", "Test User"], &repo_path);
+    run_moth_cmd(&["init"], &repo_path);
+    commit_all("Add board", &repo_path);
+    run_git(
+        &["worktree", "add", "-q", worktree_path.to_str().unwrap()],
+        &repo_path,
+    );
+
+    // Worktrees share the main repository's hooks
+    let (success, _, stderr) = run_moth_cmd(&["hook", "install"], &worktree_path);
+    assert!(success, "Command failed: {}", stderr);
+    assert!(repo_path.join(".git/hooks/prepare-commit-msg").exists());
+
+    let (success, _, stderr) = run_moth_cmd(&["hook", "uninstall"], &worktree_path);
+    assert!(success, "Command failed: {}", stderr);
+    assert!(!repo_path.join(".git/hooks/prepare-commit-msg").exists());
+}
+
+#[test]
+fn test_e2e_hook_install_into_hooks_path() {
+    let temp = setup_test_env();
+    let temp_path = temp.path();
+
+    run_git(&["init", "-q"], temp_path);
+    run_git(&["config", "core.hooksPath", ".husky"], temp_path);
+    run_moth_cmd(&["init"], temp_path);
+
+    let existing = "#!/bin/sh\nnpx lint-staged\n";
+    std::fs::create_dir(temp_path.join(".husky")).unwrap();
+    std::fs::write(temp_path.join(".husky/commit-msg"), existing).unwrap();
+
+    let (success, stdout, stderr) =
+        run_moth_cmd(&["hook", "install", "--kind", "commit-msg"], temp_path);
+    assert!(success, "Command failed: {}", stderr);
+    assert!(stdout.contains("Added moth snippet"));
+
+    let content = std::fs::read_to_string(temp_path.join(".husky/commit-msg")).unwrap();
+    assert!(content.starts_with(existing));
+    assert!(content.contains("moth hook run commit-msg \"$@\""));
+    assert!(!temp_path.join(".git/hooks/commit-msg").exists());
+
+    // The snippet runs moth's own hook, which calls `moth` from the PATH
+    let message = temp_path.join("MSG");
+    std::fs::write(&message, "No prefix here\n").unwrap();
+    let bin_dir = get_binary_path().parent().unwrap().to_path_buf();
+    let path = format!("{}:{}", bin_dir.display(), env::var("PATH").unwrap());
+    let output = Command::new(get_binary_path())
+        .args(["hook", "run", "commit-msg", message.to_str().unwrap()])
+        .current_dir(temp_path)
+        .env("PATH", &path)
+        .output()
+        .expect("Failed to run moth");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("must reference an issue ID"));
+
+    let (success, _, stderr) =
+        run_moth_cmd(&["hook", "uninstall", "--kind", "commit-msg"], temp_path);
+    assert!(success, "Command failed: {}", stderr);
+    let content = std::fs::read_to_string(temp_path.join(".husky/commit-msg")).unwrap();
+    assert_eq!(content, existing);
+}
+
+#[test]
+fn test_e2e_prepare_commit_msg_hook_tags_current_issue() {
+    let temp = setup_test_env();
+    let temp_path = temp.path();
+
+    run_git(&["init", "-q"], temp_path);
+    run_git(&["config", "user.email", "test@test.com"], temp_path);
+    run_git(&["config", "user.name", "Test User"], temp_path);
+    run_moth_cmd(&["init"], temp_path);
+    run_moth_cmd(&["new", "Tagged", "--no-edit"], temp_path);
+    run_moth_cmd(&["hook", "install"], temp_path);
+
+    let entry = std::fs::read_dir(temp_path.join(".moth/ready"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    let name = entry.file_name().to_string_lossy().to_string();
+    let id: String = name.split('-').next().unwrap().to_string();
+    run_moth_cmd(&["start", &id], temp_path);
+
+    let (success, stdout, _) = run_moth_cmd(&["current"], temp_path);
+    assert!(success);
+    assert_eq!(stdout.trim(), id);
+
+    // The hook calls `moth`, so put the test binary first on the PATH
+    let bin_dir = get_binary_path().parent().unwrap().to_path_buf();
+    let path = format!("{}:{}", bin_dir.display(), env::var("PATH").unwrap());
+    run_git(&["add", "-A"], temp_path);
+    let status = Command::new("git")
+        .args(["commit", "-q", "-m", "Start the work"])
+        .current_dir(temp_path)
+        .env("PATH", &path)
+        .status()
+        .expect("Failed to run git");
+    assert!(status.success());
+
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=%s"])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to run git");
+    let subject = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(subject.trim(), format!("[{}] Start the work", id));
+}
+
+#[test]
+fn test_e2e_merge_driver_and_resolve() {
+    let temp = setup_test_env();
+    let temp_path = temp.path();
+
+    run_git(&["init", "-q"], temp_path);
+    run_git(&["config", "user.email", "test@test.com"], temp_path);
+    run_git(&["config", "user.name", "Test User"], temp_path);
+    let (success, _, stderr) = run_moth_cmd(&["init", "--git"], temp_path);
+    assert!(success, "Command failed: {}", stderr);
+    let attributes = std::fs::read_to_string(temp_path.join(".gitattributes")).unwrap();
+    assert_eq!(attributes, ".moth/**/*.md merge=moth\n");
+
+    run_moth_cmd(&["new", "Shared", "--no-edit"], temp_path);
+    commit_all("Add issue", temp_path);
+    run_git(&["checkout", "-q", "-B", "main"], temp_path);
+
+    let entry = std::fs::read_dir(temp_path.join(".moth/ready"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    let name = entry.file_name().to_string_lossy().to_string();
+    let id: String = name.split('-').next().unwrap().to_string();
+    let ready = temp_path.join(".moth/ready").join(&name);
+
+    // Git runs the driver through `moth`, so put the test binary on the PATH
+    let bin_dir = get_binary_path().parent().unwrap().to_path_buf();
+    let path = format!("{}:{}", bin_dir.display(), env::var("PATH").unwrap());
+    let merge = |branch: &str| {
+        Command::new("git")
+            .args(["merge", "-q", "--no-edit", branch])
+            .current_dir(temp_path)
+            .env("PATH", &path)
+            .status()
+            .expect("Failed to run git")
+            .success()
+    };
+
+    // Both branches add notes to the same issue
+    run_git(&["checkout", "-q", "-b", "notes"], temp_path);
+    std::fs::write(&ready, "Their notes\n").unwrap();
+    commit_all("Their notes", temp_path);
+    run_git(&["checkout", "-q", "main"], temp_path);
+    std::fs::write(&ready, "Our notes\n").unwrap();
+    commit_all("Our notes", temp_path);
+    assert!(merge("notes"));
+    assert_eq!(
+        std::fs::read_to_string(&ready).unwrap(),
+        "Our notes\nTheir notes\n"
+    );
+
+    // Both branches move the issue, leaving a copy in each status
+    run_git(&["checkout", "-q", "-b", "finish"], temp_path);
+    run_moth_cmd(&["done", &id], temp_path);
+    commit_all("Finish", temp_path);
+    run_git(&["checkout", "-q", "main"], temp_path);
+    run_moth_cmd(&["start", &id], temp_path);
+    commit_all("Start", temp_path);
+    // A rename/rename conflict for git
+    assert!(!merge("finish"));
+    assert!(temp_path.join(".moth/doing").join(&name).exists());
+    assert!(temp_path.join(".moth/done").join(&name).exists());
+
+    let (success, stdout, stderr) = run_moth_cmd(&["resolve", "--auto"], temp_path);
+    assert!(success, "Command failed: {}", stderr);
+    assert!(stdout.contains(&format!("Kept done/{}", name)));
+    assert!(!temp_path.join(".moth/doing").join(&name).exists());
+    assert!(temp_path.join(".moth/done").join(&name).exists());
+
+    // The resolution is staged, so the merge can be concluded
+    let status = || {
+        let output = Command::new("git")
+            .args(["status", "--porcelain"])
+            .current_dir(temp_path)
+            .output()
+            .expect("Failed to run git");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    assert_eq!(
+        status(),
+        format!("R  .moth/doing/{} -> .moth/done/{}\n", name, name)
+    );
+    run_git(&["commit", "-q", "--no-edit"], temp_path);
+    assert_eq!(status(), "");
+
+    let (_, stdout, _) = run_moth_cmd(&["resolve"], temp_path);
+    assert!(stdout.contains("No duplicate issues found"));
+}
+
+#[test]
+#[serial]
+fn test_e2e_doctor_reports_and_fixes() {
+    let temp = setup_test_env();
+    let temp_path = temp.path();
+    run_moth_cmd(&["init"], temp_path);
+
+    let (success, stdout, _) = run_moth_cmd(&["doctor"], temp_path);
+    assert!(success);
+    assert!(stdout.contains("No problems found"));
+
+    let ready = temp_path.join(".moth/ready");
+    std::fs::write(ready.join("001-aaa11-med-first.md"), "").unwrap();
+    std::fs::write(ready.join("001-bbb22-med-second.md"), "").unwrap();
+    std::fs::write(ready.join("notes.txt"), "").unwrap();
+
+    let (success, stdout, stderr) = run_moth_cmd(&["doctor"], temp_path);
+    assert!(!success);
+    assert!(stdout.contains("ready: aaa11, bbb22 share priority 1"));
+    assert!(stdout.contains("ready/notes.txt: not an issue file"));
+    assert!(stdout.contains("Run moth doctor --fix to repair 1 of them"));
+    assert!(stderr.contains("2 problem(s) found"));
+
+    let (success, stdout, _) = run_moth_cmd(&["doctor", "--fix"], temp_path);
+    assert!(!success);
+    assert!(stdout.contains("Fixed: ready: aaa11, bbb22 share priority 1"));
+    assert!(ready.join("002-bbb22-med-second.md").exists());
+
+    std::fs::remove_file(ready.join("notes.txt")).unwrap();
+    let (success, _, _) = run_moth_cmd(&["doctor"], temp_path);
+    assert!(success);
+}
+
+#[test]
+#[serial]
+fn test_e2e_list_ids_follows_board_changes() {
+    let temp = setup_test_env();
+    let temp_path = temp.path();
+    run_moth_cmd(&["init"], temp_path);
+    run_moth_cmd(&["new", "First story", "--no-edit"], temp_path);
+    run_moth_cmd(&["new", "Second story", "--no-edit"], temp_path);
+
+    let (success, stdout, _) = run_moth_cmd(&["ls"], temp_path);
+    assert!(success);
+    assert!(stdout.contains("First Story"));
+    let ignore = std::fs::read_to_string(temp_path.join(".moth/.gitignore")).unwrap();
+    assert!(ignore.lines().any(|line| line == "/.index"));
+
+    let (success, stdout, _) = run_moth_cmd(&["--list-ids"], temp_path);
+    assert!(success);
+    let ids: Vec<&str> = stdout.lines().collect();
+    assert_eq!(ids.len(), 2);
+
+    // Issues removed behind moth's back drop out of the index
+    let ready = temp_path.join(".moth/ready");
+    for entry in std::fs::read_dir(&ready).unwrap() {
+        std::fs::remove_file(entry.unwrap().path()).unwrap();
+    }
+    let (_, stdout, _) = run_moth_cmd(&["--list-ids"], temp_path);
+    assert!(stdout.is_empty());
+}
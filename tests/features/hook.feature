@@ -40,3 +40,21 @@ Feature: Git hook management
     Given a moth workspace is initialized
     When the user installs the hook
     Then the command fails with "No .git directory"
+
+  Scenario: Install commit-msg hook alongside prepare-commit-msg
+    Given a moth workspace is initialized
+    And a git repository is initialized
+    And the hook is installed
+    When the user installs the commit-msg hook
+    Then the command succeeds
+    And the commit-msg hook exists
+    And the prepare-commit-msg hook exists
+
+  Scenario: Install post-commit and post-merge hooks
+    Given a moth workspace is initialized
+    And a git repository is initialized
+    When the user installs the post-commit hook
+    And the user installs the post-merge hook
+    Then the command succeeds
+    And the post-commit hook exists
+    And the post-merge hook exists
@@ -13,6 +13,30 @@ fn setup_test_env() -> TempDir {
     temp_dir
 }
 
+fn git(args: &[&str]) {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .output()
+        .expect("Failed to run git");
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+fn setup_git_repo() {
+    git(&["init", "-q"]);
+    git(&["config", "user.email", "test@test.com"]);
+    git(&["config", "user.name", "Test User"]);
+}
+
+fn commit_all(message: &str) {
+    git(&["add", "-A"]);
+    git(&["commit", "-q", "--allow-empty", "-m", message]);
+}
+
 #[test]
 #[serial]
 fn test_init_creates_moth_directory() {
@@ -137,7 +161,7 @@ fn test_show_displays_issue_content() {
 
     fs::write(&issues[0].path, "This is the issue content").unwrap();
 
-    let result = cmd::show::run(Some(id));
+    let result = cmd::show::run(Some(id), None);
     assert!(result.is_ok());
 }
 
@@ -154,7 +178,7 @@ fn test_show_with_partial_id() {
     let issues = store.all_issues().unwrap();
     let partial_id = &issues[0].id[..3];
 
-    let result = cmd::show::run(Some(partial_id));
+    let result = cmd::show::run(Some(partial_id), None);
     assert!(result.is_ok());
 }
 
@@ -164,7 +188,7 @@ fn test_show_fails_with_nonexistent_id() {
     let _temp = setup_test_env();
     cmd::init::run().unwrap();
 
-    let result = cmd::show::run(Some("nonexistent"));
+    let result = cmd::show::run(Some("nonexistent"), None);
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("No issue found"));
 }
@@ -207,7 +231,7 @@ fn test_done_moves_issue_to_done() {
     let issues = store.all_issues().unwrap();
     let id = issues[0].id.clone();
 
-    cmd::done::run(Some(&id)).unwrap();
+    cmd::done::run(Some(&id), false).unwrap();
 
     let config = Config::load().unwrap();
     let store = Store::new(config).unwrap();
@@ -311,7 +335,7 @@ fn test_full_workflow() {
     let mut issues = store.issues_by_status("ready").unwrap();
     assert_eq!(issues.len(), 2);
 
-    issues.sort_by(|a, b| a.severity.cmp(&b.severity));
+    issues.sort_by_key(|a| a.severity);
     let high_priority_id = issues[0].id.clone();
 
     cmd::start::run(&high_priority_id).unwrap();
@@ -324,7 +348,7 @@ fn test_full_workflow() {
     assert_eq!(ready_issues.len(), 1);
     assert_eq!(doing_issues.len(), 1);
 
-    cmd::done::run(Some(&high_priority_id)).unwrap();
+    cmd::done::run(Some(&high_priority_id), false).unwrap();
 
     let config = Config::load().unwrap();
     let store = Store::new(config).unwrap();
@@ -398,7 +422,7 @@ fn test_show_no_args_shows_current() {
 
     cmd::start::run(&id).unwrap();
 
-    let result = cmd::show::run(None);
+    let result = cmd::show::run(None, None);
     assert!(result.is_ok());
 }
 
@@ -408,7 +432,7 @@ fn test_show_no_args_no_current() {
     let _temp = setup_test_env();
     cmd::init::run().unwrap();
 
-    let result = cmd::show::run(None);
+    let result = cmd::show::run(None, None);
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("No current issue"));
 }
@@ -428,7 +452,7 @@ fn test_done_no_args_finishes_current() {
 
     cmd::start::run(&id).unwrap();
 
-    let result = cmd::done::run(None);
+    let result = cmd::done::run(None, false);
     assert!(result.is_ok());
 
     let config = Config::load().unwrap();
@@ -444,7 +468,7 @@ fn test_done_no_args_no_current() {
     let _temp = setup_test_env();
     cmd::init::run().unwrap();
 
-    let result = cmd::done::run(None);
+    let result = cmd::done::run(None, false);
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("No current issue"));
 }
@@ -562,3 +586,1012 @@ fn test_new_with_hooks() {
     assert!(stdout.contains("before hook"));
     assert!(stdout.contains("after hook"));
 }
+
+#[test]
+#[serial]
+fn test_chart_writes_svg_from_history() {
+    let _temp = setup_test_env();
+    setup_git_repo();
+    cmd::init::run().unwrap();
+
+    cmd::new::run("First issue", None, true, false, None).unwrap();
+    cmd::new::run("Second issue", None, true, false, None).unwrap();
+    commit_all("Add issues");
+
+    let config = Config::load().unwrap();
+    let store = Store::new(config).unwrap();
+    let id = store.all_issues().unwrap()[0].id.clone();
+    cmd::start::run(&id).unwrap();
+    commit_all("Start issue");
+
+    cmd::chart::cfd(None, None, Some("cfd.svg"), 40).unwrap();
+    let svg = fs::read_to_string("cfd.svg").unwrap();
+    assert_eq!(svg.matches("<polygon").count(), 3);
+
+    cmd::chart::burnup(None, None, Some("burnup.svg"), 40).unwrap();
+    assert!(
+        fs::read_to_string("burnup.svg")
+            .unwrap()
+            .contains("<polyline")
+    );
+}
+
+#[test]
+#[serial]
+fn test_diff_groups_changes_by_status() {
+    let _temp = setup_test_env();
+    setup_git_repo();
+    cmd::init::run().unwrap();
+    colored::control::set_override(false);
+
+    cmd::new::run("Start me", Some("high"), true, false, None).unwrap();
+    cmd::new::run("Remove me", Some("low"), true, false, None).unwrap();
+    commit_all("Add issues");
+    git(&["tag", "before"]);
+
+    let store = Store::new(Config::load().unwrap()).unwrap();
+    let id = |title: &str| {
+        store
+            .all_issues()
+            .unwrap()
+            .into_iter()
+            .find(|issue| issue.title() == title)
+            .unwrap()
+            .id
+    };
+    let (started, removed) = (id("Start Me"), id("Remove Me"));
+    cmd::start::run(&started).unwrap();
+    cmd::rm::run(&removed).unwrap();
+    cmd::new::run("Add me", Some("med"), true, false, None).unwrap();
+    let added = id("Add Me");
+    commit_all("Change the board");
+
+    let diff = |from: &str, to: &str| {
+        let mut out = Vec::new();
+        cmd::diff::write(from, to, &mut out).map(|_| String::from_utf8(out).unwrap())
+    };
+
+    // Statuses come in board order, not alphabetically; stories by ID
+    let mut ready = [
+        format!("  - {} [low] Remove Me: deleted\n", removed),
+        format!("  + {} [med] Add Me: created\n", added),
+    ];
+    ready.sort_by_key(|line| line[4..].to_string());
+    assert_eq!(
+        diff("before", "HEAD").unwrap(),
+        format!(
+            "ready\n{}doing\n  > {} [high] Start Me: moved from ready\n",
+            ready.concat(),
+            started
+        )
+    );
+    assert_eq!(
+        diff("HEAD~1", "HEAD~1").unwrap(),
+        "No board changes between HEAD~1 and HEAD~1\n"
+    );
+
+    let err = diff("nope", "HEAD").unwrap_err();
+    assert_eq!(err.to_string(), "Unknown revision: nope");
+}
+
+#[test]
+#[serial]
+fn test_report_writes_sqlite_tables() {
+    use moth::cmd::report::ReportFormat;
+
+    let _temp = setup_test_env();
+    setup_git_repo();
+    cmd::init::run().unwrap();
+
+    cmd::new::run("Report issue", None, true, false, None).unwrap();
+    commit_all("Add issue");
+
+    let config = Config::load().unwrap();
+    let store = Store::new(config).unwrap();
+    let id = store.all_issues().unwrap()[0].id.clone();
+    cmd::start::run(&id).unwrap();
+    commit_all(&format!("[{}] Start work", id));
+
+    cmd::report::run(
+        None,
+        None,
+        &Default::default(),
+        ReportFormat::Sqlite,
+        Some("report.db"),
+    )
+    .unwrap();
+
+    let conn = rusqlite::Connection::open("report.db").unwrap();
+    let count = |sql: &str| -> i64 { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+    assert_eq!(count("SELECT COUNT(*) FROM commits"), 2);
+    assert_eq!(count("SELECT COUNT(*) FROM stories"), 1);
+    assert_eq!(count("SELECT COUNT(*) FROM events"), 3);
+    assert_eq!(
+        count("SELECT COUNT(*) FROM events WHERE event = 'code_commit'"),
+        1
+    );
+    drop(conn);
+
+    // An earlier report is replaced, but any other file is left alone
+    let sqlite = |out: &str| {
+        cmd::report::run(
+            None,
+            None,
+            &Default::default(),
+            ReportFormat::Sqlite,
+            Some(out),
+        )
+    };
+    sqlite("report.db").unwrap();
+    fs::write("notes.txt", "keep me").unwrap();
+    let err = sqlite("notes.txt").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Refusing to overwrite notes.txt: it is not a SQLite database"
+    );
+    assert_eq!(fs::read_to_string("notes.txt").unwrap(), "keep me");
+
+    cmd::report::run(
+        None,
+        None,
+        &Default::default(),
+        ReportFormat::Ndjson,
+        Some("report.ndjson"),
+    )
+    .unwrap();
+    let ndjson = fs::read_to_string("report.ndjson").unwrap();
+    assert_eq!(ndjson.lines().count(), 3);
+}
+
+#[test]
+#[serial]
+fn test_report_records_severity_changes_and_priority_renames() {
+    use moth::cmd::report::ReportFormat;
+
+    let _temp = setup_test_env();
+    setup_git_repo();
+    cmd::init::run().unwrap();
+
+    cmd::new::run("Audit me", Some("med"), true, false, None).unwrap();
+    commit_all("Add issue");
+
+    let config = Config::load().unwrap();
+    let store = Store::new(config).unwrap();
+    let id = store.all_issues().unwrap()[0].id.clone();
+    cmd::priority::run(&id, "1", None, None).unwrap();
+    commit_all("Prioritise");
+    cmd::severity::run(&id, "crit".parse().unwrap()).unwrap();
+    commit_all("Raise severity");
+
+    cmd::report::run(
+        None,
+        None,
+        &Default::default(),
+        ReportFormat::Json,
+        Some("report.json"),
+    )
+    .unwrap();
+    let rows: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string("report.json").unwrap()).unwrap();
+    let rows = rows.as_array().unwrap();
+
+    // Prioritising only renames the file, so the story keeps its identity
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["event"], "created");
+    assert_eq!(rows[1]["event"], "severity_changed");
+    assert_eq!(rows[1]["story_id"], id.as_str());
+    assert_eq!(rows[1]["old_value"], "med");
+    assert_eq!(rows[1]["new_value"], "crit");
+    assert_eq!(rows[1]["title"], "Audit Me");
+    assert_eq!(rows[1]["previous_column"], "ready");
+}
+
+#[test]
+#[serial]
+fn test_report_uses_status_names_for_nested_board() {
+    use moth::cmd::report::ReportFormat;
+
+    let _temp = setup_test_env();
+    setup_git_repo();
+    fs::create_dir("board").unwrap();
+    env::set_current_dir("board").unwrap();
+    cmd::init::run().unwrap();
+    fs::write(
+        ".moth/config.yml",
+        "statuses:\n  - name: backlog\n    dir: todo\n  - name: active\n    dir: wip\n  - name: shipped\n    dir: released\ndefault_severity: med\n",
+    )
+    .unwrap();
+    for dir in ["todo", "wip", "released"] {
+        fs::create_dir_all(format!(".moth/{}", dir)).unwrap();
+    }
+
+    cmd::new::run("Nested story", Some("low"), true, false, None).unwrap();
+    commit_all("Add issue");
+
+    let config = Config::load().unwrap();
+    let store = Store::new(config).unwrap();
+    let id = store.all_issues().unwrap()[0].id.clone();
+    cmd::start::run(&id).unwrap();
+    commit_all("Start issue");
+
+    cmd::report::run(
+        None,
+        None,
+        &Default::default(),
+        ReportFormat::Json,
+        Some("report.json"),
+    )
+    .unwrap();
+    let rows: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string("report.json").unwrap()).unwrap();
+    let rows = rows.as_array().unwrap();
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["event"], "created");
+    assert_eq!(rows[0]["column"], "backlog");
+    assert_eq!(rows[1]["event"], "moved");
+    assert_eq!(rows[1]["previous_column"], "backlog");
+    assert_eq!(rows[1]["column"], "active");
+}
+
+#[test]
+#[serial]
+fn test_report_tags_branches_and_follows_first_parent() {
+    use moth::cmd::report::{BranchOptions, ReportFormat};
+
+    let _temp = setup_test_env();
+    setup_git_repo();
+    cmd::init::run().unwrap();
+    cmd::new::run("Shared story", Some("med"), true, false, None).unwrap();
+    commit_all("Add issue");
+    git(&["checkout", "-q", "-B", "main"]);
+
+    let config = Config::load().unwrap();
+    let store = Store::new(config).unwrap();
+    let id = store.all_issues().unwrap()[0].id.clone();
+
+    git(&["checkout", "-q", "-b", "feature"]);
+    cmd::start::run(&id).unwrap();
+    commit_all("Start issue");
+    git(&["checkout", "-q", "main"]);
+    cmd::new::run("Main story", Some("low"), true, false, None).unwrap();
+    commit_all("Add another issue");
+
+    let read_rows = |options: &BranchOptions| {
+        cmd::report::run(None, None, options, ReportFormat::Json, Some("report.json")).unwrap();
+        let rows: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string("report.json").unwrap()).unwrap();
+        rows.as_array().unwrap().clone()
+    };
+
+    // Shared history is attributed to the current branch only
+    let rows = read_rows(&BranchOptions {
+        all: true,
+        ..Default::default()
+    });
+    assert_eq!(rows.len(), 3);
+    let moved: Vec<_> = rows.iter().filter(|r| r["event"] == "moved").collect();
+    assert_eq!(moved.len(), 1);
+    assert_eq!(moved[0]["branch"], "feature");
+    assert!(
+        rows.iter()
+            .filter(|r| r["event"] == "created")
+            .all(|r| r["branch"] == "main")
+    );
+
+    // With --first-parent the merge carries the branch's changes
+    git(&["merge", "-q", "--no-ff", "-m", "Merge feature", "feature"]);
+    let rows = read_rows(&BranchOptions {
+        first_parent: true,
+        ..Default::default()
+    });
+    let events: Vec<_> = rows.iter().map(|r| r["event"].as_str().unwrap()).collect();
+    assert_eq!(events, ["created", "created", "moved"]);
+    assert_eq!(rows[2]["story_id"], id.as_str());
+    assert_eq!(rows[2]["branch"], "main");
+}
+
+#[test]
+#[serial]
+fn test_report_detects_configured_reference_formats() {
+    use moth::cmd::report::ReportFormat;
+    use moth::config::ReferenceFormat;
+
+    let _temp = setup_test_env();
+    setup_git_repo();
+    cmd::init::run().unwrap();
+
+    let mut config = Config::load().unwrap();
+    config.references.formats = vec![ReferenceFormat::Scope, ReferenceFormat::Trailer];
+    fs::write(".moth/config.yml", serde_yaml::to_string(&config).unwrap()).unwrap();
+
+    cmd::new::run("First", Some("med"), true, false, None).unwrap();
+    cmd::new::run("Second", Some("med"), true, false, None).unwrap();
+    commit_all("Add issues");
+
+    let store = Store::new(Config::load().unwrap()).unwrap();
+    let mut ids: Vec<String> = store
+        .all_issues()
+        .unwrap()
+        .into_iter()
+        .map(|i| i.id)
+        .collect();
+    ids.sort();
+    commit_all(&format!("feat({}): Shared fix\n\nRefs: {}", ids[0], ids[1]));
+    commit_all(&format!("[{}] Bracket is not configured", ids[0]));
+
+    cmd::report::run(
+        None,
+        None,
+        &Default::default(),
+        ReportFormat::Json,
+        Some("report.json"),
+    )
+    .unwrap();
+    let rows: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string("report.json").unwrap()).unwrap();
+    let code: Vec<_> = rows
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|r| r["event"] == "code_commit")
+        .collect();
+
+    assert_eq!(code.len(), 2);
+    assert_eq!(code[0]["story_id"], ids[0].as_str());
+    assert_eq!(code[1]["story_id"], ids[1].as_str());
+    assert_eq!(code[0]["message"], "feat: Shared fix");
+}
+
+#[test]
+#[serial]
+fn test_current_issue_is_kept_per_worktree() {
+    let _temp = setup_test_env();
+    setup_git_repo();
+    cmd::init::run().unwrap();
+    cmd::new::run("First story", Some("med"), true, false, None).unwrap();
+
+    let store = Store::new(Config::load().unwrap()).unwrap();
+    let id = store.all_issues().unwrap()[0].id.clone();
+
+    cmd::start::run(&id).unwrap();
+    assert_eq!(store.current_id().unwrap(), Some(id.clone()));
+    assert!(PathBuf::from(".git/moth-current").exists());
+    assert!(!PathBuf::from(".moth/.current").exists());
+
+    // A linked worktree starts without a current issue
+    commit_all("Add board");
+    git(&["worktree", "add", "-q", "wt"]);
+    env::set_current_dir("wt").unwrap();
+    let worktree_store = Store::new(Config::load().unwrap()).unwrap();
+    assert_eq!(worktree_store.current_id().unwrap(), None);
+
+    cmd::current::run(Some(&id[..3]), false).unwrap();
+    assert_eq!(worktree_store.current_id().unwrap(), Some(id.clone()));
+
+    cmd::current::run(None, true).unwrap();
+    assert_eq!(worktree_store.current_id().unwrap(), None);
+    assert_eq!(store.current_id().unwrap(), Some(id));
+}
+
+#[test]
+#[serial]
+fn test_current_issue_moves_out_of_older_boards() {
+    let _temp = setup_test_env();
+    setup_git_repo();
+    cmd::init::run().unwrap();
+    cmd::new::run("First story", Some("med"), true, false, None).unwrap();
+    let store = Store::new(Config::load().unwrap()).unwrap();
+    let id = store.all_issues().unwrap()[0].id.clone();
+
+    // Older versions kept the current issue in the board
+    fs::write(".moth/.current", &id).unwrap();
+    assert_eq!(store.current_id().unwrap(), Some(id.clone()));
+    assert!(!PathBuf::from(".moth/.current").exists());
+    assert_eq!(fs::read_to_string(".git/moth-current").unwrap(), id);
+
+    cmd::done::run(None, false).unwrap();
+    assert_eq!(store.recorded_current_id(), None);
+}
+
+#[test]
+#[serial]
+fn test_current_issue_inferred_from_branch() {
+    let _temp = setup_test_env();
+    setup_git_repo();
+    cmd::init::run().unwrap();
+    cmd::new::run("Fix login", Some("med"), true, false, None).unwrap();
+    cmd::new::run("Other story", Some("low"), true, false, None).unwrap();
+    commit_all("Add issues");
+
+    let mut config = Config::load().unwrap();
+    let issues = Store::new(config.clone()).unwrap().all_issues().unwrap();
+    let (login, other) = (issues[0].id.clone(), issues[1].id.clone());
+    cmd::start::run(&other).unwrap();
+
+    git(&[
+        "checkout",
+        "-q",
+        "-b",
+        &format!("feature/{}-fix-login", login),
+    ]);
+    let store = Store::new(config.clone()).unwrap();
+    assert_eq!(store.current_id().unwrap(), Some(other.clone()));
+
+    config.branches.infer_current = true;
+    let store = Store::new(config).unwrap();
+    assert_eq!(store.current_id().unwrap(), Some(login));
+
+    // Branches that don't name an issue fall back to the one started
+    git(&["checkout", "-q", "-b", "cleanup"]);
+    assert_eq!(store.current_id().unwrap(), Some(other));
+}
+
+#[test]
+#[serial]
+fn test_branch_starts_issue_and_done_deletes_it() {
+    let _temp = setup_test_env();
+    setup_git_repo();
+    cmd::init::run().unwrap();
+    cmd::new::run("Fix login", Some("high"), true, false, None).unwrap();
+    commit_all("Add issue");
+    git(&["checkout", "-q", "-B", "main"]);
+
+    let store = Store::new(Config::load().unwrap()).unwrap();
+    let id = store.all_issues().unwrap()[0].id.clone();
+    let branch = format!("{}-fix-login", id);
+
+    cmd::branch::run(&id).unwrap();
+    let repo = git2::Repository::open(".").unwrap();
+    assert_eq!(repo.head().unwrap().shorthand(), Some(branch.as_str()));
+
+    let issue = store.find(&id).unwrap();
+    assert_eq!(issue.status, "doing");
+    assert_eq!(store.current_id().unwrap(), Some(id.clone()));
+    let content = fs::read_to_string(&issue.path).unwrap();
+    assert_eq!(content, format!("Branch: {}\n", branch));
+
+    // The branch is kept until it has been merged
+    commit_all("Work on the issue");
+    git(&["checkout", "-q", "main"]);
+    cmd::branch::delete_merged(&branch).unwrap();
+    assert!(repo.find_branch(&branch, git2::BranchType::Local).is_ok());
+
+    git(&["merge", "-q", "--no-ff", "-m", "Merge", &branch]);
+    cmd::done::run(Some(&id), true).unwrap();
+    assert!(repo.find_branch(&branch, git2::BranchType::Local).is_err());
+}
+
+#[test]
+#[serial]
+fn test_branch_with_auto_commit_leaves_tree_clean() {
+    let _temp = setup_test_env();
+    setup_git_repo();
+    cmd::init::run().unwrap();
+    let config = fs::read_to_string(".moth/config.yml").unwrap();
+    let config = config.replace("auto_commit: false", "auto_commit: true");
+    fs::write(".moth/config.yml", config).unwrap();
+    cmd::new::run("Fix login", Some("high"), true, false, None).unwrap();
+    commit_all("Add issue");
+
+    let id = Store::new(Config::load().unwrap())
+        .unwrap()
+        .all_issues()
+        .unwrap()[0]
+        .id
+        .clone();
+    cmd::branch::run(&id).unwrap();
+
+    let repo = git2::Repository::open(".").unwrap();
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(
+        head.message(),
+        Some(format!("moth: record branch {}-fix-login for {}", id, id).as_str())
+    );
+    let dirty: Vec<_> = repo
+        .statuses(None)
+        .unwrap()
+        .iter()
+        .filter(|s| !s.status().is_ignored())
+        .map(|s| s.path().unwrap().to_string())
+        .collect();
+    assert!(dirty.is_empty(), "Uncommitted changes: {:?}", dirty);
+}
+
+#[test]
+#[serial]
+fn test_internal_files_stay_out_of_git_on_older_boards() {
+    use moth::cmd::commit::CommitOptions;
+
+    let _temp = setup_test_env();
+    setup_git_repo();
+    cmd::init::run().unwrap();
+    // A board created before the lock, journal and index existed
+    fs::write(".moth/.gitignore", "/.current\n").unwrap();
+    commit_all("Add board");
+
+    cmd::new::run("Fix login", Some("high"), true, false, None).unwrap();
+    let id = Store::new(Config::load().unwrap())
+        .unwrap()
+        .all_issues()
+        .unwrap()[0]
+        .id
+        .clone();
+    cmd::start::run(&id).unwrap();
+    assert!(fs::exists(".moth/.lock").unwrap());
+
+    let repo = git2::Repository::open(".").unwrap();
+    let untracked = |repo: &git2::Repository| -> Vec<String> {
+        let mut options = git2::StatusOptions::new();
+        options.include_untracked(true).recurse_untracked_dirs(true);
+        repo.statuses(Some(&mut options))
+            .unwrap()
+            .iter()
+            .filter(|s| s.status().is_wt_new())
+            .map(|s| s.path().unwrap().to_string())
+            .collect()
+    };
+    assert!(untracked(&repo).iter().all(|path| path.ends_with(".md")));
+
+    cmd::commit::run(&CommitOptions {
+        message: Some("Work"),
+        ..Default::default()
+    })
+    .unwrap();
+    let tree = repo.head().unwrap().peel_to_tree().unwrap();
+    for name in moth::store::INTERNAL_FILES {
+        assert!(tree.get_path(&PathBuf::from(".moth").join(name)).is_err());
+    }
+    assert!(untracked(&repo).is_empty());
+}
+
+#[test]
+#[serial]
+fn test_commit_tags_message_and_stages_board() {
+    use moth::cmd::commit::CommitOptions;
+
+    let _temp = setup_test_env();
+    setup_git_repo();
+    cmd::init::run().unwrap();
+    cmd::new::run("Fix login", Some("high"), true, false, None).unwrap();
+    commit_all("Add issue");
+
+    let store = Store::new(Config::load().unwrap()).unwrap();
+    let id = store.all_issues().unwrap()[0].id.clone();
+    cmd::start::run(&id).unwrap();
+
+    fs::write("login.rs", "fn login() {}\n").unwrap();
+    fs::write("scratch.txt", "not staged\n").unwrap();
+    fs::write(".moth/notes.txt", "not an issue\n").unwrap();
+    git(&["add", "login.rs"]);
+
+    cmd::commit::run(&CommitOptions {
+        message: Some("Fix the bug"),
+        done: true,
+        ..Default::default()
+    })
+    .unwrap();
+
+    let repo = git2::Repository::open(".").unwrap();
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(
+        head.message(),
+        Some(format!("[{}] Fix the bug", id).as_str())
+    );
+
+    let tree = head.tree().unwrap();
+    let issue = store.find(&id).unwrap();
+    assert_eq!(issue.status, "done");
+    let done_path = format!(".moth/done/{}", issue.filename());
+    assert!(tree.get_path(std::path::Path::new(&done_path)).is_ok());
+    assert!(tree.get_path(std::path::Path::new("login.rs")).is_ok());
+    assert!(tree.get_path(std::path::Path::new("scratch.txt")).is_err());
+    assert!(
+        tree.get_path(std::path::Path::new(".moth/notes.txt"))
+            .is_err()
+    );
+    assert!(tree.get_path(std::path::Path::new(".moth/doing")).is_err());
+
+    // The issue's title is the default subject
+    fs::write("login.rs", "fn login() { todo!() }\n").unwrap();
+    cmd::commit::run(&CommitOptions {
+        issue: Some(&id),
+        all: true,
+        ..Default::default()
+    })
+    .unwrap();
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.message(), Some(format!("[{}] Fix Login", id).as_str()));
+
+    let result = cmd::commit::run(&CommitOptions {
+        issue: Some(&id),
+        ..Default::default()
+    });
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("Nothing to commit")
+    );
+
+    // With nothing to commit, --done leaves the issue where it is
+    cmd::new::run("Later", Some("low"), true, false, None).unwrap();
+    let later = store.issues_by_status("ready").unwrap()[0].clone();
+    cmd::commit::run(&CommitOptions {
+        issue: Some(&later.id),
+        ..Default::default()
+    })
+    .unwrap();
+    let result = cmd::commit::run(&CommitOptions {
+        issue: Some(&later.id),
+        done: true,
+        ..Default::default()
+    });
+    assert!(result.is_err());
+    assert_eq!(store.find(&later.id).unwrap().status, "ready");
+}
+
+#[test]
+#[serial]
+fn test_auto_commit_commits_only_board_changes() {
+    let _temp = setup_test_env();
+    setup_git_repo();
+    cmd::init::run().unwrap();
+    let config = fs::read_to_string(".moth/config.yml").unwrap();
+    let config = config.replace("auto_commit: false", "auto_commit: true");
+    fs::write(".moth/config.yml", config).unwrap();
+    commit_all("Add board");
+
+    let repo = git2::Repository::open(".").unwrap();
+    let head = || repo.head().unwrap().peel_to_commit().unwrap();
+    let changed_files = || {
+        let commit = head();
+        let parent = commit.parent(0).unwrap();
+        let diff = repo
+            .diff_tree_to_tree(
+                Some(&parent.tree().unwrap()),
+                Some(&commit.tree().unwrap()),
+                None,
+            )
+            .unwrap();
+        diff.deltas()
+            .map(|d| {
+                let file = d.new_file().path().or(d.old_file().path()).unwrap();
+                file.to_string_lossy().into_owned()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Staged work that isn't part of the board is left alone
+    fs::write("code.rs", "fn main() {}\n").unwrap();
+    git(&["add", "code.rs"]);
+
+    cmd::new::run("Fix login", Some("high"), true, false, None).unwrap();
+    let store = Store::new(Config::load().unwrap()).unwrap();
+    let issue = store.all_issues().unwrap()[0].clone();
+    assert_eq!(
+        head().message(),
+        Some(format!("moth: new {} Fix Login", issue.id).as_str())
+    );
+    assert_eq!(
+        changed_files(),
+        vec![format!(".moth/ready/{}", issue.filename())]
+    );
+
+    cmd::start::run(&issue.id).unwrap();
+    assert_eq!(
+        head().message(),
+        Some(format!("moth: move {} ready -> doing", issue.id).as_str())
+    );
+    assert_eq!(changed_files().len(), 2);
+
+    cmd::severity::run(&issue.id, moth::issue::Severity::Low).unwrap();
+    assert_eq!(
+        head().message(),
+        Some(format!("moth: severity {} high -> low", issue.id).as_str())
+    );
+
+    cmd::rm::run(&issue.id).unwrap();
+    assert_eq!(
+        head().message(),
+        Some(format!("moth: rm {}", issue.id).as_str())
+    );
+
+    // The board is clean and the staged file is still only staged
+    let statuses = repo.statuses(None).unwrap();
+    let dirty: Vec<_> = statuses
+        .iter()
+        .filter(|s| !s.status().is_ignored())
+        .map(|s| (s.path().unwrap().to_string(), s.status()))
+        .collect();
+    assert_eq!(
+        dirty,
+        vec![("code.rs".to_string(), git2::Status::INDEX_NEW)]
+    );
+}
+
+#[test]
+#[serial]
+fn test_auto_commit_after_editing_and_not_during_a_merge() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = setup_test_env();
+    setup_git_repo();
+    cmd::init::run().unwrap();
+
+    let editor = temp.path().join("editor.sh");
+    fs::write(&editor, "#!/bin/sh\necho 'Some notes' >> \"$1\"\n").unwrap();
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+    let config: String = fs::read_to_string(".moth/config.yml")
+        .unwrap()
+        .lines()
+        .map(|line| match line.strip_prefix("editor:") {
+            Some(_) => format!("editor: {}\n", editor.display()),
+            None => format!(
+                "{}\n",
+                line.replace("auto_commit: false", "auto_commit: true")
+            ),
+        })
+        .collect();
+    fs::write(".moth/config.yml", config).unwrap();
+    commit_all("Add board");
+
+    // The commit has what was written in the editor
+    cmd::new::run("Fix login", Some("high"), false, false, None).unwrap();
+    let store = Store::new(Config::load().unwrap()).unwrap();
+    let issue = store.all_issues().unwrap()[0].clone();
+    let repo = git2::Repository::open(".").unwrap();
+    let head = || repo.head().unwrap().peel_to_commit().unwrap();
+    let path = format!(".moth/ready/{}", issue.filename());
+    let entry = head()
+        .tree()
+        .unwrap()
+        .get_path(&PathBuf::from(&path))
+        .unwrap();
+    let blob = repo.find_blob(entry.id()).unwrap();
+    assert_eq!(blob.content(), b"Some notes\n");
+
+    // Nothing is committed in the middle of a merge
+    let before = head().id();
+    fs::write(".git/MERGE_HEAD", format!("{}\n", before)).unwrap();
+    cmd::start::run(&issue.id).unwrap();
+    assert_eq!(head().id(), before);
+    assert_eq!(store.find(&issue.id).unwrap().status, "doing");
+}
+
+#[test]
+#[serial]
+fn test_git_ref_storage_syncs_between_clones() {
+    let temp = setup_test_env();
+    let root = temp.path().to_path_buf();
+    git(&["init", "-q", "--bare", "remote.git"]);
+
+    let clone = |name: &str| {
+        let dir = root.join(name);
+        fs::create_dir(&dir).unwrap();
+        env::set_current_dir(&dir).unwrap();
+        setup_git_repo();
+        git(&["remote", "add", "origin", "../remote.git"]);
+        cmd::init::run().unwrap();
+        let config = fs::read_to_string(".moth/config.yml").unwrap();
+        let config = config.replace("backend: worktree", "backend: git_ref");
+        fs::write(".moth/config.yml", config).unwrap();
+        dir
+    };
+    let store = || Store::new(Config::load().unwrap()).unwrap();
+
+    let alice = clone("alice");
+    cmd::new::run("Shared story", Some("high"), true, false, None).unwrap();
+    let id = store().all_issues().unwrap()[0].id.clone();
+
+    // The board lives on its own ref, not on the branch
+    let repo = git2::Repository::open(&alice).unwrap();
+    let board = repo.find_reference("refs/moth/board").unwrap();
+    let commit = board.peel_to_commit().unwrap();
+    assert_eq!(
+        commit.message(),
+        Some(format!("moth: new {} Shared Story", id).as_str())
+    );
+    assert!(repo.head().is_err());
+    let untracked: Vec<_> = repo
+        .statuses(None)
+        .unwrap()
+        .iter()
+        .filter(|s| !s.status().is_ignored())
+        .map(|s| s.path().unwrap().to_string())
+        .collect();
+    assert_eq!(untracked, vec![".moth/.gitignore", ".moth/config.yml"]);
+
+    // Only doctor --fix touches .moth/.gitignore, adding to what is there
+    fs::write(".moth/.gitignore", "/notes/\n").unwrap();
+    let problems = moth::doctor::check(&store()).unwrap();
+    assert_eq!(problems.len(), 1);
+    moth::doctor::fix(&store(), &problems[0]).unwrap();
+    let ignored = fs::read_to_string(".moth/.gitignore").unwrap();
+    assert!(ignored.starts_with("/notes/\n"));
+    assert!(ignored.contains("/ready/\n"));
+    assert!(ignored.contains("/.lock\n"));
+
+    // Reading the board doesn't record anything on the ref
+    let issue = store().find(&id).unwrap();
+    fs::write(&issue.path, "Notes\n").unwrap();
+    store().all_issues().unwrap();
+    let tip = || {
+        repo.find_reference("refs/moth/board")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap()
+            .id()
+    };
+    assert_eq!(tip(), commit.id());
+
+    cmd::sync::run("origin").unwrap();
+    assert_ne!(tip(), commit.id());
+
+    // History commands read branch commits, which don't hold the board
+    let err = cmd::diff::run("HEAD", "HEAD").unwrap_err();
+    assert!(err.to_string().contains("storage.backend: git_ref"));
+
+    let bob = clone("bob");
+    cmd::sync::run("origin").unwrap();
+    assert_eq!(store().find(&id).unwrap().status, "ready");
+
+    // Concurrent changes on both sides are merged
+    cmd::start::run(&id).unwrap();
+    cmd::sync::run("origin").unwrap();
+    env::set_current_dir(&alice).unwrap();
+    cmd::new::run("Alice story", Some("low"), true, false, None).unwrap();
+    cmd::sync::run("origin").unwrap();
+
+    let issues = store().all_issues().unwrap();
+    assert_eq!(issues.len(), 2);
+    assert_eq!(store().find(&id).unwrap().status, "doing");
+
+    env::set_current_dir(&bob).unwrap();
+    cmd::sync::run("origin").unwrap();
+    assert_eq!(store().all_issues().unwrap().len(), 2);
+}
+
+#[test]
+#[serial]
+fn test_git_ref_sync_keeps_both_sides_of_a_conflict() {
+    let temp = setup_test_env();
+    let root = temp.path().to_path_buf();
+    git(&["init", "-q", "--bare", "remote.git"]);
+
+    let clone = |name: &str| {
+        let dir = root.join(name);
+        fs::create_dir(&dir).unwrap();
+        env::set_current_dir(&dir).unwrap();
+        setup_git_repo();
+        git(&["remote", "add", "origin", "../remote.git"]);
+        cmd::init::run().unwrap();
+        let config = fs::read_to_string(".moth/config.yml").unwrap();
+        let config = config.replace("backend: worktree", "backend: git_ref");
+        fs::write(".moth/config.yml", config).unwrap();
+        dir
+    };
+    let store = || Store::new(Config::load().unwrap()).unwrap();
+
+    let alice = clone("alice");
+    cmd::new::run("Shared story", Some("high"), true, false, None).unwrap();
+    let id = store().all_issues().unwrap()[0].id.clone();
+    cmd::sync::run("origin").unwrap();
+
+    // Both clones move the same issue, to different statuses
+    let bob = clone("bob");
+    cmd::sync::run("origin").unwrap();
+    cmd::start::run(&id).unwrap();
+    cmd::sync::run("origin").unwrap();
+    env::set_current_dir(&alice).unwrap();
+    cmd::done::run(Some(&id), false).unwrap();
+    cmd::sync::run("origin").unwrap();
+
+    let statuses = |issues: Vec<moth::issue::Issue>| {
+        let mut statuses: Vec<String> = issues.into_iter().map(|i| i.status).collect();
+        statuses.sort();
+        statuses
+    };
+    assert_eq!(
+        statuses(store().all_issues().unwrap()),
+        vec!["doing", "done"]
+    );
+
+    cmd::merge::resolve(true).unwrap();
+    assert_eq!(store().find(&id).unwrap().status, "done");
+    cmd::sync::run("origin").unwrap();
+
+    env::set_current_dir(&bob).unwrap();
+    cmd::sync::run("origin").unwrap();
+    assert_eq!(statuses(store().all_issues().unwrap()), vec!["done"]);
+
+    // Both clones edit the same issue: the file keeps both versions
+    let issue = store().find(&id).unwrap();
+    let path = PathBuf::from(".moth/done").join(issue.path.file_name().unwrap());
+    fs::write(&path, "Bob's notes\n").unwrap();
+    cmd::sync::run("origin").unwrap();
+    env::set_current_dir(&alice).unwrap();
+    fs::write(&path, "Alice's notes\n").unwrap();
+    cmd::sync::run("origin").unwrap();
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.contains("Alice's notes\n=======\nBob's notes\n"));
+}
+
+#[test]
+#[serial]
+fn test_board_lock_serializes_processes() {
+    let _temp = setup_test_env();
+    cmd::init::run().unwrap();
+    assert!(
+        fs::read_to_string(".moth/.gitignore")
+            .unwrap()
+            .contains("/.lock")
+    );
+
+    // A second store waits for the first to release the lock
+    let store = Store::new(Config::load().unwrap()).unwrap();
+    let lock = store.lock().unwrap();
+    let config = Config::load().unwrap();
+    let waiting = std::thread::spawn(move || {
+        let other = Store::new(config).unwrap();
+        other
+            .create_issue("Waited", moth::issue::Severity::Med)
+            .unwrap()
+    });
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    assert!(store.all_issues().unwrap().is_empty());
+
+    drop(lock);
+    let issue = waiting.join().unwrap();
+    assert_eq!(store.find(&issue.id).unwrap().title(), "Waited");
+
+    // Concurrent creates never hand out the same ID
+    let handles: Vec<_> = (0..8)
+        .map(|n| {
+            let config = Config::load().unwrap();
+            std::thread::spawn(move || {
+                let store = Store::new(config).unwrap();
+                for i in 0..10 {
+                    store
+                        .create_issue(&format!("Story {} {}", n, i), moth::issue::Severity::Low)
+                        .unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let mut ids: Vec<_> = store
+        .all_issues()
+        .unwrap()
+        .into_iter()
+        .map(|i| i.id)
+        .collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), 81);
+}
+
+#[test]
+#[serial]
+fn test_index_is_only_saved_under_the_lock() {
+    let _temp = setup_test_env();
+    cmd::init::run().unwrap();
+    let config = Config::load().unwrap();
+    let an_hour_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    for status in &config.statuses {
+        let dir = fs::File::open(config.status_dir(status)).unwrap();
+        dir.set_modified(an_hour_ago).unwrap();
+    }
+
+    let store = Store::new(config).unwrap();
+    store.all_issues().unwrap();
+    assert!(!PathBuf::from(".moth/.index").exists());
+
+    let _lock = store.lock().unwrap();
+    store.all_issues().unwrap();
+    assert!(PathBuf::from(".moth/.index").exists());
+    assert!(!PathBuf::from(".moth/.index.tmp").exists());
+}
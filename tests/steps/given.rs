@@ -1,5 +1,6 @@
 use cucumber::given;
 use moth::cmd;
+use moth::cmd::hook::HookKind;
 use moth::config::Config;
 use moth::store::Store;
 use tempfile::TempDir;
@@ -79,7 +80,8 @@ fn git_repo_initialized(_world: &mut MothWorld) {
 
 #[given("the hook is installed")]
 fn hook_is_installed(_world: &mut MothWorld) {
-    cmd::hook::install(false, false).expect("Failed to install hook");
+    cmd::hook::install(HookKind::PrepareCommitMsg, false, false, false)
+        .expect("Failed to install hook");
 }
 
 #[given("a custom prepare-commit-msg hook exists")]
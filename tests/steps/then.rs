@@ -226,15 +226,15 @@ fn issue_has_no_priority(world: &mut MothWorld) {
 }
 
 // Hook assertions
-#[then("the prepare-commit-msg hook exists")]
-fn hook_exists(world: &mut MothWorld) {
-    let hook_path = world.moth_path().join(".git/hooks/prepare-commit-msg");
+#[then(expr = "the {word} hook exists")]
+fn hook_exists(world: &mut MothWorld, kind: String) {
+    let hook_path = world.moth_path().join(".git/hooks").join(kind);
     assert!(hook_path.exists(), "Hook should exist at {:?}", hook_path);
 }
 
-#[then("the prepare-commit-msg hook does not exist")]
-fn hook_does_not_exist(world: &mut MothWorld) {
-    let hook_path = world.moth_path().join(".git/hooks/prepare-commit-msg");
+#[then(expr = "the {word} hook does not exist")]
+fn hook_does_not_exist(world: &mut MothWorld, kind: String) {
+    let hook_path = world.moth_path().join(".git/hooks").join(kind);
     assert!(
         !hook_path.exists(),
         "Hook should not exist at {:?}",
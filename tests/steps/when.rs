@@ -1,5 +1,6 @@
 use cucumber::when;
 use moth::cmd;
+use moth::cmd::hook::HookKind;
 use moth::config::Config;
 use moth::store::Store;
 
@@ -76,22 +77,22 @@ fn user_creates_issue_without_no_edit(world: &mut MothWorld, title: String) {
 
 #[when("the user lists issues")]
 fn user_lists_issues(world: &mut MothWorld) {
-    world.last_result = Some(cmd::list::run(None, false, None).map(|_| ()));
+    world.last_result = Some(cmd::list::run(None, false, None, None).map(|_| ()));
 }
 
 #[when(expr = "the user lists issues with status {string}")]
 fn user_lists_issues_with_status(world: &mut MothWorld, status: String) {
-    world.last_result = Some(cmd::list::run(Some(&status), false, None).map(|_| ()));
+    world.last_result = Some(cmd::list::run(Some(&status), false, None, None).map(|_| ()));
 }
 
 #[when(expr = "the user shows issue {string}")]
 fn user_shows_issue(world: &mut MothWorld, id: String) {
-    world.last_result = Some(cmd::show::run(Some(&id)).map(|_| ()));
+    world.last_result = Some(cmd::show::run(Some(&id), None).map(|_| ()));
 }
 
 #[when("the user shows the current issue")]
 fn user_shows_current_issue(world: &mut MothWorld) {
-    world.last_result = Some(cmd::show::run(None).map(|_| ()));
+    world.last_result = Some(cmd::show::run(None, None).map(|_| ()));
 }
 
 #[when(expr = "the user starts issue {string}")]
@@ -107,18 +108,18 @@ fn user_starts_last_issue(world: &mut MothWorld) {
 
 #[when(expr = "the user marks issue {string} as done")]
 fn user_marks_done(world: &mut MothWorld, id: String) {
-    world.last_result = Some(cmd::done::run(Some(&id)).map(|_| ()));
+    world.last_result = Some(cmd::done::run(Some(&id), false).map(|_| ()));
 }
 
 #[when("the user marks the current issue as done")]
 fn user_marks_current_done(world: &mut MothWorld) {
-    world.last_result = Some(cmd::done::run(None).map(|_| ()));
+    world.last_result = Some(cmd::done::run(None, false).map(|_| ()));
 }
 
 #[when("the user marks the last created issue as done")]
 fn user_marks_last_done(world: &mut MothWorld) {
     let id = world.last_issue_id.clone().expect("No issue ID available");
-    world.last_result = Some(cmd::done::run(Some(&id)).map(|_| ()));
+    world.last_result = Some(cmd::done::run(Some(&id), false).map(|_| ()));
 }
 
 #[when(expr = "the user moves issue {string} to {string}")]
@@ -145,7 +146,7 @@ fn user_deletes_last_issue(world: &mut MothWorld) {
 
 #[when(expr = "the user shows issue with partial id {string}")]
 fn user_shows_partial_id(world: &mut MothWorld, partial_id: String) {
-    world.last_result = Some(cmd::show::run(Some(&partial_id)).map(|_| ()));
+    world.last_result = Some(cmd::show::run(Some(&partial_id), None).map(|_| ()));
 }
 
 // Priority commands
@@ -191,15 +192,23 @@ fn user_changes_severity_by_id(world: &mut MothWorld, id: String, level: String)
 // Hook commands
 #[when("the user installs the hook")]
 fn user_installs_hook(world: &mut MothWorld) {
-    world.last_result = Some(cmd::hook::install(false, false).map(|_| ()));
+    world.last_result =
+        Some(cmd::hook::install(HookKind::PrepareCommitMsg, false, false, false).map(|_| ()));
 }
 
 #[when("the user installs the hook with force")]
 fn user_installs_hook_force(world: &mut MothWorld) {
-    world.last_result = Some(cmd::hook::install(true, false).map(|_| ()));
+    world.last_result =
+        Some(cmd::hook::install(HookKind::PrepareCommitMsg, true, false, false).map(|_| ()));
+}
+
+#[when(expr = "the user installs the {word} hook")]
+fn user_installs_hook_kind(world: &mut MothWorld, kind: String) {
+    let kind: HookKind = kind.parse().expect("Unknown hook kind");
+    world.last_result = Some(cmd::hook::install(kind, false, false, false).map(|_| ()));
 }
 
 #[when("the user uninstalls the hook")]
 fn user_uninstalls_hook(world: &mut MothWorld) {
-    world.last_result = Some(cmd::hook::uninstall().map(|_| ()));
+    world.last_result = Some(cmd::hook::uninstall(HookKind::PrepareCommitMsg).map(|_| ()));
 }